@@ -4,10 +4,12 @@
 
 mod ui;
 
+use std::time::Duration;
+
 use clap::Parser;
-use ractor::{ActorRef, concurrency::Duration};
+use ractor::ActorRef;
 use ractor_wormhole::{
-    conduit::websocket,
+    conduit::{reconnect::ReconnectConfig, websocket},
     nexus::start_nexus,
     portal::{NexusResult, Portal, PortalActorMessage},
     util::{ActorRef_Ask, FnActor},
@@ -15,6 +17,9 @@ use ractor_wormhole::{
 use shared::ChatClientMessage;
 use ui::{UIMsg, spawn_ui_actor};
 
+/// how long the setup of a new connection may take, before the connection is retried
+const BOOTSTRAP_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -72,44 +77,49 @@ async fn run() -> Result<(), anyhow::Error> {
     // Start the nexus actor
     let nexus = start_nexus(None, None).await.unwrap();
 
-    // connect to the server
-    let portal = websocket::client::tokio_tungstenite::connect_to_server(nexus, cli.url).await?;
-
-    // wait for the portal to be ready (handshake)
-    portal
-        .ask(
-            PortalActorMessage::WaitForHandshake,
-            Some(Duration::from_secs(5)),
-        )
-        .await?;
-
-    // the server has published a named actor
-    let remote_hub_address = portal
-        .ask(
-            |rpc| PortalActorMessage::QueryNamedRemoteActor("hub".to_string(), rpc),
-            None,
-        )
-        .await??;
-
-    let remote_hub: ActorRef<shared::HubMessage> = portal
-        .instantiate_proxy_for_remote_actor(remote_hub_address)
-        .await?;
-
     let local_chat_client = start_chatclient_actor(ui.clone()).await?;
-    let (user_alias, remote_chat_server) = remote_hub
-        .ask(
-            |rpc| shared::HubMessage::Connect(local_chat_client, rpc),
-            None,
-        )
-        .await?;
-
-    ui.send_message(UIMsg::Connected(
-        user_alias.clone(),
-        remote_chat_server.clone(),
-    ))?;
+
+    // connect to the server, and reconnect whenever the connection is lost.
+    //  Every new portal needs to be set up again: look up the hub and connect the chat client.
+    let ui_copy = ui.clone();
+    let connection = websocket::client::tokio_tungstenite::connect_to_server_with_reconnect(
+        nexus,
+        cli.url,
+        websocket::client::tokio_tungstenite::ClientConfig::default,
+        move |portal| {
+            let ui = ui_copy.clone();
+            let local_chat_client = local_chat_client.clone();
+            async move {
+                // the server has published a named actor
+                let remote_hub_address = portal
+                    .ask(
+                        |rpc| PortalActorMessage::QueryNamedRemoteActor("hub".to_string(), rpc),
+                        Some(BOOTSTRAP_TIMEOUT),
+                    )
+                    .await??;
+
+                let remote_hub: ActorRef<shared::HubMessage> = portal
+                    .instantiate_proxy_for_remote_actor(remote_hub_address)
+                    .await?;
+
+                let (user_alias, remote_chat_server) = remote_hub
+                    .ask(
+                        |rpc| shared::HubMessage::Connect(local_chat_client, rpc),
+                        Some(BOOTSTRAP_TIMEOUT),
+                    )
+                    .await?;
+
+                ui.send_message(UIMsg::Connected(user_alias, remote_chat_server))?;
+                Ok(())
+            }
+        },
+        ReconnectConfig::default(),
+        None,
+    );
 
     // wait for ui to exit
     ui.wait(None).await?;
+    connection.stop();
 
     ratatui::restore();
 
@@ -1,33 +1,37 @@
-use futures::StreamExt;
-use http_body_util::Full;
+use http_body_util::{BodyExt, Full};
 use hyper::body::Bytes;
 use hyper::{Request, Response};
-use hyper_tungstenite::HyperWebsocket;
 use hyper_util::rt::TokioIo;
 use ractor::ActorRef;
-use ractor_wormhole::conduit::{self};
+use ractor_wormhole::conduit::http_fallback::server::{FallbackBody, HttpFallbackServer};
+use ractor_wormhole::conduit::websocket;
 use ractor_wormhole::nexus::NexusActorMessage;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 
 /// this implements a http server that can accept either GET (heartbeat) or UPGRADE to websocket.
+/// Clients behind proxies which strip the upgrade can use the http fallback below `/fallback` instead.
 pub async fn http_server_fn(
     nexus: ActorRef<NexusActorMessage>,
     addr: SocketAddr,
 ) -> Result<!, anyhow::Error> {
     let listener = TcpListener::bind(addr).await?;
+    let fallback = HttpFallbackServer::new(nexus.clone(), "/fallback");
 
     let mut http = hyper::server::conn::http1::Builder::new();
     http.keep_alive(true);
 
     loop {
-        let (stream, _) = listener.accept().await?;
+        let (stream, addr) = listener.accept().await?;
 
         let nexus_copy = nexus.clone();
+        let fallback_copy = fallback.clone();
         let connection = http
             .serve_connection(
                 TokioIo::new(stream),
-                hyper::service::service_fn(move |req| hello(nexus_copy.clone(), req)),
+                hyper::service::service_fn(move |req| {
+                    hello(nexus_copy.clone(), fallback_copy.clone(), addr, req)
+                }),
             )
             .with_upgrades();
 
@@ -43,48 +47,27 @@ pub async fn http_server_fn(
 
 pub async fn hello(
     nexus: ActorRef<NexusActorMessage>,
+    fallback: HttpFallbackServer,
+    addr: SocketAddr,
     mut req: Request<hyper::body::Incoming>,
-) -> Result<Response<Full<Bytes>>, anyhow::Error> {
-    if hyper_tungstenite::is_upgrade_request(&req) {
-        let (response, websocket) = hyper_tungstenite::upgrade(&mut req, None)?;
-
-        if let Err(e) = serve_websocket(nexus, websocket).await {
-            eprintln!("Error in websocket connection: {e}");
-        }
-        // Return the response so the spawned future can continue.
-        Ok(response)
+) -> Result<Response<FallbackBody>, anyhow::Error> {
+    if websocket::server::hyper::is_upgrade_request(&req) {
+        // the portal is opened in a spawned task, once the upgrade has completed
+        Ok(
+            websocket::server::hyper::upgrade(nexus, &mut req, Some(addr))?
+                .map(BodyExt::boxed_unsync),
+        )
+    } else if fallback.is_fallback_request(&req) {
+        Ok(fallback.handle(req, Some(addr)).await)
     } else if req.method() == hyper::Method::GET {
         // Handle regular HTTP requests here.
-        Ok(Response::new(Full::<Bytes>::from(
-            "https://www.youtube.com/watch?v=SXRteMSSZ14",
-        )))
+        Ok(Response::new(
+            Full::<Bytes>::from("https://www.youtube.com/watch?v=SXRteMSSZ14").boxed_unsync(),
+        ))
     } else {
         Ok(Response::builder()
             .status(hyper::StatusCode::BAD_REQUEST)
-            .body(Full::<Bytes>::from("Method not supported"))
+            .body(Full::<Bytes>::from("Method not supported").boxed_unsync())
             .unwrap())
     }
 }
-
-/// Handle a websocket connection.
-pub async fn serve_websocket(
-    nexus: ActorRef<NexusActorMessage>,
-    websocket: HyperWebsocket,
-) -> Result<(), anyhow::Error> {
-    let websocket = websocket.await?;
-
-    let (tx, rx) = websocket.split();
-
-    // need to adapt streams
-    // Map the tungstenite messages to ConduitMessage
-    let rx = conduit::websocket::server::tokio_tungstenite::map_ws_to_conduit(rx);
-
-    // Convert the ConduitMessage to tungstenite Message
-    let tx = conduit::websocket::server::tokio_tungstenite::map_conduit_to_ws(tx);
-
-    // it seems to be non-trivial to get the address from hyper, and its usefulness is questionable, anyway, with proxies and vpns and what not.
-    let identifier = format!("ws-client://{}", rand::random::<u64>());
-    conduit::from_sink_source(nexus, identifier, tx, rx).await?;
-
-    Ok(())
-}
@@ -10,6 +10,7 @@ use hyper::{Request, Response};
 use hyper_util::rt::TokioIo;
 use ractor::ActorRef;
 use ractor::concurrency::Duration;
+use ractor_wormhole::conduit::websocket;
 use ractor_wormhole::nexus::NexusActorMessage;
 use ractor_wormhole::portal::PortalActorMessage;
 use ractor_wormhole::util::ActorRef_Ask;
@@ -36,13 +37,13 @@ pub async fn http_server_fn(
     http.keep_alive(true);
 
     loop {
-        let (stream, _) = listener.accept().await?;
+        let (stream, addr) = listener.accept().await?;
 
         let nexus_copy = nexus.clone();
         let connection = http
             .serve_connection(
                 TokioIo::new(stream),
-                hyper::service::service_fn(move |req| hello(nexus_copy.clone(), req)),
+                hyper::service::service_fn(move |req| hello(nexus_copy.clone(), addr, req)),
             )
             .with_upgrades();
 
@@ -58,21 +59,14 @@ pub async fn http_server_fn(
 
 pub async fn hello(
     nexus: ActorRef<NexusActorMessage>,
+    addr: SocketAddr,
     mut req: Request<hyper::body::Incoming>,
 ) -> Result<Response<Full<Bytes>>, anyhow::Error> {
     info!("Received request: {req:?}");
 
-    if hyper_tungstenite::is_upgrade_request(&req) {
-        let (response, websocket) = hyper_tungstenite::upgrade(&mut req, None)?;
-
-        // Spawn a task to handle the websocket connection.
-        tokio::spawn(async move {
-            if let Err(e) = http_server::serve_websocket(nexus, websocket).await {
-                eprintln!("Error in websocket connection: {e}");
-            }
-        });
-        // Return the response so the spawned future can continue.
-        Ok(response)
+    if websocket::server::hyper::is_upgrade_request(&req) {
+        // the portal is opened in a spawned task, once the upgrade has completed
+        websocket::server::hyper::upgrade(nexus, &mut req, Some(addr))
     } else if req.method() == hyper::Method::GET {
         let path = req.uri().path();
         info!("GET request for path: {path}");
@@ -0,0 +1,129 @@
+//! The client half of the http fallback, using reqwest.
+
+use futures::{Stream, StreamExt};
+use log::info;
+use ractor::ActorRef;
+use reqwest::header;
+
+use crate::{
+    conduit::{
+        self, ConduitError, ConduitMessage, ConduitSink, ConduitSource, ConnectionContext,
+        layer::ConduitLayers,
+    },
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+};
+
+use super::{CONTENT_TYPE_BINARY, CONTENT_TYPE_EVENT_STREAM, CONTENT_TYPE_TEXT, EventParser};
+
+#[derive(Default)]
+pub struct ClientConfig {
+    /// the layers the conduit is wrapped in, see `conduit::layer`.
+    pub layers: ConduitLayers,
+    /// the http client, e.g. configured with a proxy. A default client is used if not set.
+    pub client: Option<reqwest::Client>,
+}
+
+/// opens a session at the base url of the fallback routes (e.g. `http://localhost:8080/wormhole/fallback`) and a portal on it.
+pub async fn connect_to_server(
+    nexus: ActorRef<NexusActorMessage>,
+    url: &str,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error> {
+    connect_to_server_with_config(nexus, url, ClientConfig::default()).await
+}
+
+pub async fn connect_to_server_with_config(
+    nexus: ActorRef<NexusActorMessage>,
+    url: &str,
+    config: ClientConfig,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error> {
+    let client = config.client.unwrap_or_default();
+    let url = url.trim_end_matches('/');
+    info!("Opening http fallback session at: {url}");
+
+    let session = client
+        .post(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let session_url = format!("{url}/{session}");
+
+    let events = client
+        .get(&session_url)
+        .header(header::ACCEPT, CONTENT_TYPE_EVENT_STREAM)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let context = ConnectionContext {
+        remote_addr: events.remote_addr(),
+        ..Default::default()
+    };
+    let source = event_source(events.bytes_stream());
+    let sink = post_sink(client, session_url);
+
+    let portal = conduit::from_sink_source_with_layers(
+        nexus,
+        url.to_string(),
+        context,
+        sink,
+        source,
+        &config.layers,
+    )
+    .await?;
+
+    info!("Portal actor started for: {url}");
+
+    Ok(portal)
+}
+
+/// parses the frames from the event stream
+fn event_source<S, B>(stream: S) -> ConduitSource
+where
+    S: Stream<Item = Result<B, reqwest::Error>> + Send + 'static,
+    B: AsRef<[u8]>,
+{
+    let state = (Box::pin(stream), EventParser::default());
+    Box::pin(futures::stream::unfold(
+        state,
+        |(mut stream, mut parser)| async move {
+            loop {
+                if let Some(msg) = parser.next_message() {
+                    return Some((msg, (stream, parser)));
+                }
+                match stream.next().await {
+                    Some(Ok(chunk)) => parser.feed(chunk.as_ref()),
+                    Some(Err(err)) => return Some((Err(err.into()), (stream, parser))),
+                    None => return None,
+                }
+            }
+        },
+    ))
+}
+
+/// sends every frame as a POST (and the close as a DELETE). The next frame is only sent after the response, to keep the order.
+fn post_sink(client: reqwest::Client, url: String) -> ConduitSink {
+    Box::pin(futures::sink::unfold(
+        (client, url),
+        |(client, url), msg: ConduitMessage| async move {
+            let request = match msg {
+                ConduitMessage::Text(text) => client
+                    .post(&url)
+                    .header(header::CONTENT_TYPE, CONTENT_TYPE_TEXT)
+                    .body(text),
+                ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => client
+                    .post(&url)
+                    .header(header::CONTENT_TYPE, CONTENT_TYPE_BINARY)
+                    .body(data),
+                ConduitMessage::Close(reason) => client
+                    .delete(&url)
+                    .header(header::CONTENT_TYPE, CONTENT_TYPE_BINARY)
+                    .body(reason.map(|reason| reason.to_bytes()).unwrap_or_default()),
+            };
+            request.send().await?.error_for_status()?;
+            Ok::<_, ConduitError>((client, url))
+        },
+    ))
+}
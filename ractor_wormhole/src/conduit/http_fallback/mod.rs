@@ -0,0 +1,236 @@
+//! A conduit over plain http, for clients behind proxies which strip websocket upgrades.
+//!
+//! The server sends its frames as server-sent events, the client sends its frames as POST requests.
+//! All requests go to the same base path (e.g. `/wormhole/fallback`):
+//!
+//! * `POST {base}` opens a session; the response body is the session id.
+//! * `GET {base}/{session}` is the event stream (server → client), it may only be requested once per session.
+//! * `POST {base}/{session}` sends one frame (client → server). `application/octet-stream` is a binary frame, everything else a text frame.
+//! * `DELETE {base}/{session}` closes the conduit; the body is the (optional) close reason, see `CloseReason::to_bytes`.
+//!
+//! Every event is a single `data:` line: text frames are json strings, binary frames are base64,
+//! the close is a json `[code, reason]` pair or `null`.
+//! The client must wait for the response of a POST before sending the next one, so the frames arrive in order.
+
+#[cfg(feature = "http_fallback_server")]
+pub mod server;
+
+#[cfg(feature = "http_fallback_client")]
+pub mod client;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+use crate::conduit::{CloseCode, CloseReason, ConduitError, ConduitMessage};
+
+pub const CONTENT_TYPE_BINARY: &str = "application/octet-stream";
+pub const CONTENT_TYPE_TEXT: &str = "text/plain; charset=utf-8";
+pub const CONTENT_TYPE_EVENT_STREAM: &str = "text/event-stream";
+
+// -------------------------------------------------------------------------------------------------------
+
+/// one frame as a server-sent event.
+pub fn encode_event(msg: &ConduitMessage) -> Result<String, ConduitError> {
+    let (event, data) = match msg {
+        ConduitMessage::Text(text) => ("text", serde_json::to_string(text)?),
+        ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => {
+            ("binary", BASE64.encode(data))
+        }
+        ConduitMessage::Close(reason) => {
+            let reason = reason
+                .as_ref()
+                .map(|reason| (reason.code.to_u16(), reason.reason.as_str()));
+            ("close", serde_json::to_string(&reason)?)
+        }
+    };
+    Ok(format!("event: {event}\ndata: {data}\n\n"))
+}
+
+/// a comment, which is ignored by the client but keeps proxies from closing an idle event stream.
+pub const KEEPALIVE_EVENT: &str = ": keepalive\n\n";
+
+/// the longest line (and event) `EventParser` accepts by default: a frame of 64 MiB (the default `max_body_size` of the server) in base64, plus some room.
+pub const MAX_EVENT_LENGTH: usize = 96 << 20;
+
+/// parses the event stream from the server back into frames. The stream may be split at arbitrary points.
+pub struct EventParser {
+    max_event_length: usize,
+    buffer: Vec<u8>,
+    /// the start of the first line which hasn't been parsed yet
+    consumed: usize,
+    /// the buffer has been searched for the end of the line up to here
+    scanned: usize,
+    event: Option<String>,
+    data: String,
+}
+
+impl Default for EventParser {
+    fn default() -> Self {
+        Self::new(MAX_EVENT_LENGTH)
+    }
+}
+
+impl EventParser {
+    /// longer lines or events fail the stream, so a broken server can't make the client buffer without bounds.
+    pub fn new(max_event_length: usize) -> Self {
+        Self {
+            max_event_length,
+            buffer: Vec::new(),
+            consumed: 0,
+            scanned: 0,
+            event: None,
+            data: String::new(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// the next complete frame, if any
+    pub fn next_message(&mut self) -> Option<Result<ConduitMessage, ConduitError>> {
+        loop {
+            let Some(end) = self.buffer[self.scanned..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|position| self.scanned + position)
+            else {
+                // drop the parsed lines, and remember that the rest doesn't contain a line break
+                self.buffer.drain(..self.consumed);
+                self.consumed = 0;
+                self.scanned = self.buffer.len();
+                if self.buffer.len() > self.max_event_length {
+                    return Some(Err(self.too_long()));
+                }
+                return None;
+            };
+
+            let line = String::from_utf8_lossy(&self.buffer[self.consumed..end]);
+            let line = line.trim_end_matches('\r');
+
+            if line.is_empty() {
+                self.consumed = end + 1;
+                self.scanned = self.consumed;
+                let data = std::mem::take(&mut self.data);
+                if let Some(event) = self.event.take() {
+                    return Some(decode_event(&event, &data));
+                }
+                continue;
+            }
+
+            // comments start with a colon
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push_str(value),
+                _ => {}
+            }
+            self.consumed = end + 1;
+            self.scanned = self.consumed;
+
+            // an event may consist of many data lines
+            if self.data.len() > self.max_event_length {
+                return Some(Err(self.too_long()));
+            }
+        }
+    }
+
+    /// the stream is broken, so the buffered data is dropped
+    fn too_long(&mut self) -> ConduitError {
+        *self = Self::new(self.max_event_length);
+        anyhow::anyhow!("Event longer than {} bytes", self.max_event_length)
+    }
+}
+
+fn decode_event(event: &str, data: &str) -> Result<ConduitMessage, ConduitError> {
+    match event {
+        "text" => Ok(ConduitMessage::Text(serde_json::from_str(data)?)),
+        "binary" => Ok(ConduitMessage::Binary(BASE64.decode(data)?)),
+        "close" => {
+            let reason: Option<(u16, String)> = serde_json::from_str(data)?;
+            Ok(ConduitMessage::Close(reason.map(|(code, reason)| {
+                CloseReason::new(CloseCode::from_u16(code), reason)
+            })))
+        }
+        other => Err(anyhow::anyhow!("Unknown event '{other}'")),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_roundtrip() -> Result<(), ConduitError> {
+        let stream = [
+            encode_event(&ConduitMessage::Text("two\nlines\r\n".to_string()))?,
+            KEEPALIVE_EVENT.to_string(),
+            encode_event(&ConduitMessage::Binary(vec![0, 1, 2, 255]))?,
+            encode_event(&ConduitMessage::Close(Some(CloseReason::new(
+                CloseCode::PolicyViolation,
+                "bye",
+            ))))?,
+        ]
+        .concat();
+
+        // feed the stream in small pieces, which don't line up with the events
+        let mut parser = EventParser::default();
+        let mut received = Vec::new();
+        for chunk in stream.as_bytes().chunks(3) {
+            parser.feed(chunk);
+            while let Some(msg) = parser.next_message() {
+                received.push(msg?);
+            }
+        }
+
+        assert_eq!(received.len(), 3);
+        assert!(matches!(&received[0], ConduitMessage::Text(text) if text == "two\nlines\r\n"));
+        assert!(matches!(&received[1], ConduitMessage::Binary(data) if data == &[0, 1, 2, 255]));
+        assert!(matches!(&received[2], ConduitMessage::Close(Some(reason))
+                if *reason == CloseReason::new(CloseCode::PolicyViolation, "bye")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_many_events_in_one_chunk() -> Result<(), ConduitError> {
+        let stream: String = (0..1000)
+            .map(|i| encode_event(&ConduitMessage::Text(i.to_string())))
+            .collect::<Result<_, _>>()?;
+
+        let mut parser = EventParser::default();
+        parser.feed(stream.as_bytes());
+        for i in 0..1000 {
+            assert!(
+                matches!(parser.next_message(), Some(Ok(ConduitMessage::Text(text))) if text == i.to_string())
+            );
+        }
+        assert!(parser.next_message().is_none());
+        assert!(parser.buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_length_is_limited() {
+        // a line which never ends
+        let mut parser = EventParser::new(100);
+        parser.feed(b"data: ");
+        for _ in 0..10 {
+            assert!(parser.next_message().is_none());
+            parser.feed(&[b'a'; 10]);
+        }
+        assert!(matches!(parser.next_message(), Some(Err(_))));
+        assert!(parser.buffer.is_empty());
+
+        // an event made of many short lines
+        let mut parser = EventParser::new(100);
+        parser.feed(b"event: text\n");
+        for _ in 0..20 {
+            parser.feed(b"data: 0123456789\n");
+        }
+        assert!(matches!(parser.next_message(), Some(Err(_))));
+    }
+}
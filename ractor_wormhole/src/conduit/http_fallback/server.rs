@@ -0,0 +1,422 @@
+//! The server half of the http fallback, to be integrated into an existing hyper server.
+//!
+//! ```ignore
+//! let fallback = HttpFallbackServer::new(nexus, "/wormhole/fallback");
+//!
+//! async fn handle(fallback: HttpFallbackServer, addr: SocketAddr, req: Request<Incoming>) -> ... {
+//!     if fallback.is_fallback_request(&req) {
+//!         return Ok(fallback.handle(req, Some(addr)).await);
+//!     }
+//!     // ... handle all other requests
+//! }
+//! ```
+//!
+//! `HttpFallbackConfig` limits the number of sessions and the size of the frames, and closes sessions
+//! whose event stream is never requested.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::Display,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Buf;
+use futures::{SinkExt, StreamExt, channel::mpsc};
+use http_body_util::{BodyExt, Full, StreamBody, combinators::UnsyncBoxBody};
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Body, Bytes, Frame},
+    header,
+};
+use log::{error, warn};
+use ractor::{
+    ActorRef,
+    concurrency::{Duration, Instant, sleep, timeout},
+};
+
+use crate::{
+    conduit::{
+        self, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+        ConnectionContext, layer::ConduitLayers,
+    },
+    nexus::NexusActorMessage,
+};
+
+use super::{CONTENT_TYPE_BINARY, CONTENT_TYPE_EVENT_STREAM, KEEPALIVE_EVENT, encode_event};
+
+/// the body of all responses; the event stream is streamed, everything else is a short text.
+pub type FallbackBody = UnsyncBoxBody<Bytes, Infallible>;
+
+/// how long the event stream may be idle before a keepalive comment is sent
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// -------------------------------------------------------------------------------------------------------
+
+pub struct HttpFallbackConfig {
+    /// the maximum number of concurrent sessions. Further sessions are refused with `503 Service Unavailable`.
+    pub max_sessions: Option<usize>,
+    /// sessions whose event stream hasn't been requested after this time are closed.
+    pub open_timeout: Duration,
+    /// sessions without any frame (in either direction) for this time are closed.
+    /// Keepalives don't count, and the portal doesn't send anything on its own, so this also closes quiet but healthy sessions.
+    pub idle_timeout: Option<Duration>,
+    /// the maximum size of a request body, i.e. of a single frame from the client.
+    pub max_body_size: usize,
+    /// the layers every conduit is wrapped in, see `conduit::layer`.
+    pub layers: ConduitLayers,
+}
+
+impl Default for HttpFallbackConfig {
+    fn default() -> Self {
+        Self {
+            max_sessions: Some(1024),
+            open_timeout: Duration::from_secs(30),
+            idle_timeout: None,
+            max_body_size: 64 << 20,
+            layers: ConduitLayers::new(),
+        }
+    }
+}
+
+struct Session {
+    /// the frames from the client, read by the portal
+    incoming: mpsc::UnboundedSender<Result<ConduitMessage, ConduitError>>,
+    /// the frames for the client, taken by the event stream
+    outgoing: Option<mpsc::UnboundedReceiver<ConduitMessage>>,
+    opened_at: Instant,
+    /// the last frame in either direction
+    last_activity: Instant,
+}
+
+struct Shared {
+    nexus: ActorRef<NexusActorMessage>,
+    config: HttpFallbackConfig,
+    base_path: String,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+/// serves the routes below `base_path`, see `conduit::http_fallback`. Every session is a portal.
+#[derive(Clone)]
+pub struct HttpFallbackServer {
+    shared: Arc<Shared>,
+}
+
+impl HttpFallbackServer {
+    pub fn new(nexus: ActorRef<NexusActorMessage>, base_path: impl Into<String>) -> Self {
+        Self::with_layers(nexus, base_path, ConduitLayers::new())
+    }
+
+    /// like `new`, but wraps every conduit in the layers (see `conduit::layer`).
+    pub fn with_layers(
+        nexus: ActorRef<NexusActorMessage>,
+        base_path: impl Into<String>,
+        layers: ConduitLayers,
+    ) -> Self {
+        let config = HttpFallbackConfig {
+            layers,
+            ..Default::default()
+        };
+        Self::with_config(nexus, base_path, config)
+    }
+
+    pub fn with_config(
+        nexus: ActorRef<NexusActorMessage>,
+        base_path: impl Into<String>,
+        config: HttpFallbackConfig,
+    ) -> Self {
+        let base_path: String = base_path.into();
+        Self {
+            shared: Arc::new(Shared {
+                nexus,
+                config,
+                base_path: base_path.trim_end_matches('/').to_string(),
+                sessions: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// returns true if the request is for one of the fallback routes.
+    pub fn is_fallback_request<B>(&self, request: &Request<B>) -> bool {
+        self.route(request.uri().path()).is_some()
+    }
+
+    /// the number of open sessions
+    pub fn session_count(&self) -> usize {
+        self.shared.sessions.lock().unwrap().len()
+    }
+
+    /// `Some(None)` for the base path, `Some(Some(session))` for a session, `None` for any other path.
+    fn route<'a>(&self, path: &'a str) -> Option<Option<&'a str>> {
+        let rest = path.strip_prefix(self.shared.base_path.as_str())?;
+        if rest.is_empty() || rest == "/" {
+            return Some(None);
+        }
+        match rest.strip_prefix('/') {
+            Some(session) if !session.contains('/') => Some(Some(session)),
+            _ => None,
+        }
+    }
+
+    /// handles a request for one of the fallback routes.
+    pub async fn handle<B>(
+        &self,
+        request: Request<B>,
+        remote_addr: Option<SocketAddr>,
+    ) -> Response<FallbackBody>
+    where
+        B: Body,
+        B::Error: Display,
+    {
+        let path = request.uri().path().to_string();
+        let Some(route) = self.route(&path) else {
+            return response(StatusCode::NOT_FOUND, "Not a wormhole route");
+        };
+
+        let max_body_size = self.shared.config.max_body_size;
+        match (request.method().clone(), route) {
+            (Method::POST, None) => self.open(&request, remote_addr),
+            (Method::GET, Some(session)) => self.events(session),
+            (Method::POST, Some(session)) => {
+                let binary = request
+                    .headers()
+                    .get(header::CONTENT_TYPE)
+                    .is_some_and(|value| {
+                        value.as_bytes().starts_with(CONTENT_TYPE_BINARY.as_bytes())
+                    });
+                let msg = match read_body(request, max_body_size).await {
+                    Ok(body) if binary => ConduitMessage::Binary(body),
+                    Ok(body) => match String::from_utf8(body) {
+                        Ok(text) => ConduitMessage::Text(text),
+                        Err(_) => return response(StatusCode::BAD_REQUEST, "Expected utf-8 text"),
+                    },
+                    Err(response) => return response,
+                };
+                self.receive(session, msg)
+            }
+            (Method::DELETE, Some(session)) => {
+                let reason = match read_body(request, max_body_size).await {
+                    Ok(body) if body.is_empty() => None,
+                    Ok(body) => match CloseReason::from_bytes(&body) {
+                        Ok(reason) => Some(reason),
+                        Err(err) => return response(StatusCode::BAD_REQUEST, err.to_string()),
+                    },
+                    Err(response) => return response,
+                };
+                self.receive(session, ConduitMessage::Close(reason))
+            }
+            _ => response(StatusCode::METHOD_NOT_ALLOWED, "Method not supported"),
+        }
+    }
+
+    /// creates a session and opens a portal on it
+    fn open<B>(
+        &self,
+        request: &Request<B>,
+        remote_addr: Option<SocketAddr>,
+    ) -> Response<FallbackBody> {
+        let mut context = ConnectionContext {
+            remote_addr,
+            ..Default::default()
+        };
+        context.add_request_metadata(request);
+
+        // the session id is the only thing which authorizes the POSTs, so it must not be guessable
+        let session_id = format!("{:032x}", rand::random::<u128>());
+        let (incoming_tx, incoming_rx) = mpsc::unbounded();
+        let (outgoing_tx, outgoing_rx) = mpsc::unbounded();
+        {
+            let mut sessions = self.shared.sessions.lock().unwrap();
+            if let Some(max_sessions) = self.shared.config.max_sessions
+                && sessions.len() >= max_sessions
+            {
+                warn!("Refusing http fallback session, there are already {max_sessions} sessions");
+                return response(StatusCode::SERVICE_UNAVAILABLE, "Too many sessions");
+            }
+            let now = Instant::now();
+            sessions.insert(
+                session_id.clone(),
+                Session {
+                    incoming: incoming_tx,
+                    outgoing: Some(outgoing_rx),
+                    opened_at: now,
+                    last_activity: now,
+                },
+            );
+        }
+        ractor::concurrency::spawn(reap(self.shared.clone(), session_id.clone()));
+
+        let sink: ConduitSink = Box::pin(outgoing_tx.sink_map_err(ConduitError::from));
+        let source: ConduitSource = Box::pin(incoming_rx);
+        let identifier = context.portal_identifier("sse");
+
+        // layers may need to exchange frames (e.g. a handshake), which only flow once the client has the event stream
+        let shared = self.shared.clone();
+        let id = session_id.clone();
+        ractor::concurrency::spawn(async move {
+            let result = conduit::from_sink_source_with_layers(
+                shared.nexus.clone(),
+                identifier,
+                context,
+                sink,
+                source,
+                &shared.config.layers,
+            )
+            .await;
+            if let Err(err) = result {
+                error!("Error opening portal for http fallback session: {err}");
+                shared.sessions.lock().unwrap().remove(&id);
+            }
+        });
+
+        response(StatusCode::OK, session_id)
+    }
+
+    /// the event stream of the session. The session ends when the stream is dropped.
+    fn events(&self, session: &str) -> Response<FallbackBody> {
+        let outgoing = match self.shared.sessions.lock().unwrap().get_mut(session) {
+            Some(session) => session.outgoing.take(),
+            None => return response(StatusCode::NOT_FOUND, "Unknown session"),
+        };
+        let Some(outgoing) = outgoing else {
+            return response(StatusCode::CONFLICT, "The event stream is already open");
+        };
+
+        let guard = SessionGuard {
+            shared: self.shared.clone(),
+            id: session.to_string(),
+        };
+        let events = futures::stream::unfold(Some((outgoing, guard)), |state| async move {
+            let (mut outgoing, guard) = state?;
+            match timeout(KEEPALIVE_INTERVAL, outgoing.next()).await {
+                Err(_) => Some((KEEPALIVE_EVENT.to_string(), Some((outgoing, guard)))),
+                Ok(None) => None,
+                Ok(Some(msg)) => {
+                    guard.touch();
+                    let is_close = matches!(msg, ConduitMessage::Close(_));
+                    let event = match encode_event(&msg) {
+                        Ok(event) => event,
+                        Err(err) => {
+                            error!("Error encoding event: {err}");
+                            return None;
+                        }
+                    };
+                    // the close is the last event
+                    Some((event, (!is_close).then_some((outgoing, guard))))
+                }
+            }
+        })
+        .map(|event| Ok(Frame::data(Bytes::from(event))));
+
+        Response::builder()
+            .header(header::CONTENT_TYPE, CONTENT_TYPE_EVENT_STREAM)
+            .header(header::CACHE_CONTROL, "no-cache")
+            // ask nginx & co. not to buffer the stream
+            .header("x-accel-buffering", "no")
+            .body(StreamBody::new(events).boxed_unsync())
+            .expect("the response is valid")
+    }
+
+    /// passes a frame from the client to the portal
+    fn receive(&self, session: &str, msg: ConduitMessage) -> Response<FallbackBody> {
+        let mut sessions = self.shared.sessions.lock().unwrap();
+        let Some(session) = sessions.get_mut(session) else {
+            return response(StatusCode::NOT_FOUND, "Unknown session");
+        };
+        session.last_activity = Instant::now();
+        match session.incoming.unbounded_send(Ok(msg)) {
+            Ok(()) => response(StatusCode::OK, ""),
+            Err(_) => response(StatusCode::GONE, "The portal is closed"),
+        }
+    }
+}
+
+/// removes the session when the event stream is dropped, which ends the source of the portal.
+struct SessionGuard {
+    shared: Arc<Shared>,
+    id: String,
+}
+
+impl SessionGuard {
+    fn touch(&self) {
+        if let Some(session) = self.shared.sessions.lock().unwrap().get_mut(&self.id) {
+            session.last_activity = Instant::now();
+        }
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.shared.sessions.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// closes the session (by removing it, which ends the source of the portal) if its event stream isn't requested in time,
+/// or if it is idle for too long.
+async fn reap(shared: Arc<Shared>, id: String) {
+    let config = &shared.config;
+    loop {
+        let deadline = match shared.sessions.lock().unwrap().get(&id) {
+            None => return,
+            Some(session) if session.outgoing.is_some() => session.opened_at + config.open_timeout,
+            Some(session) => match config.idle_timeout {
+                Some(idle_timeout) => session.last_activity + idle_timeout,
+                None => return,
+            },
+        };
+
+        let now = Instant::now();
+        if now < deadline {
+            sleep(deadline - now).await;
+            continue;
+        }
+        warn!("Closing http fallback session {id}: it timed out");
+        shared.sessions.lock().unwrap().remove(&id);
+        return;
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// reads the whole body, or fails with the response for the client.
+async fn read_body<B>(
+    request: Request<B>,
+    max_size: usize,
+) -> Result<Vec<u8>, Response<FallbackBody>>
+where
+    B: Body,
+    B::Error: Display,
+{
+    let too_large = || {
+        response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "The request body is too large",
+        )
+    };
+    let mut body = Box::pin(request.into_body());
+    if body.size_hint().lower() > max_size as u64 {
+        return Err(too_large());
+    }
+
+    let mut data = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|err| {
+            let err = format!("Error reading the request body: {err}");
+            response(StatusCode::BAD_REQUEST, err)
+        })?;
+        if let Ok(mut chunk) = frame.into_data() {
+            if data.len() + chunk.remaining() > max_size {
+                return Err(too_large());
+            }
+            data.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+        }
+    }
+    Ok(data)
+}
+
+fn response(status: StatusCode, body: impl Into<Bytes>) -> Response<FallbackBody> {
+    let mut response = Response::new(Full::new(body.into()).boxed_unsync());
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,183 @@
+//! Middleware between the portal and the transport.
+//!
+//! A `ConduitLayer` wraps the sink and source of a conduit, e.g. to compress, encrypt, log or count the messages.
+//! Layers are written once and work with every transport: they are stacked in a `ConduitLayers`,
+//! which is passed to `conduit::from_sink_source_with_layers` or the `*_with_config` / `*_with_layers`
+//! functions of the transports.
+//!
+//! ```ignore
+//! let layers = ConduitLayers::new()
+//!     .with(LoggingLayer)
+//!     .with(metrics.clone());
+//! // metrics.metrics().bytes_sent ...
+//! ```
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use futures::{SinkExt, StreamExt, future::BoxFuture};
+use log::info;
+
+use crate::conduit::{ConduitError, ConduitMessage, ConduitSink, ConduitSource, ConnectionContext};
+
+// -------------------------------------------------------------------------------------------------------
+
+/// both halves of a conduit, together with what is known about the remote side.
+pub struct Conduit {
+    pub sink: ConduitSink,
+    pub source: ConduitSource,
+    pub context: ConnectionContext,
+}
+
+/// wraps a conduit into another conduit.
+///
+/// Wrapping is async and may fail, so a layer can perform a handshake with the remote side
+/// (which must use the same layer) before the portal sends its introduction.
+/// A layer may also add to the `ConnectionContext`, e.g. the authenticated identity of the remote side.
+pub trait ConduitLayer: Send + Sync + 'static {
+    fn wrap(&self, conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>>;
+}
+
+/// a stack of layers. The first layer wraps the transport, the last layer is the closest to the portal.
+#[derive(Clone, Default)]
+pub struct ConduitLayers {
+    layers: Vec<Arc<dyn ConduitLayer>>,
+}
+
+impl ConduitLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a layer on top of the already added layers.
+    pub fn with(mut self, layer: impl ConduitLayer) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// wraps the conduit in all layers, starting with the first one.
+    pub async fn apply(&self, mut conduit: Conduit) -> Result<Conduit, ConduitError> {
+        for layer in &self.layers {
+            conduit = layer.wrap(conduit).await?;
+        }
+        Ok(conduit)
+    }
+}
+
+fn message_size(msg: &ConduitMessage) -> usize {
+    match msg {
+        ConduitMessage::Text(text) => text.len(),
+        ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => data.len(),
+        ConduitMessage::Close(reason) => reason.as_ref().map_or(0, |reason| reason.reason.len()),
+    }
+}
+
+fn message_kind(msg: &ConduitMessage) -> &'static str {
+    match msg {
+        ConduitMessage::Text(_) => "text",
+        ConduitMessage::Binary(_) => "binary",
+        ConduitMessage::Bulk(_) => "bulk",
+        ConduitMessage::Close(_) => "close",
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// logs every message which passes through the conduit (at `info` level, without the content).
+pub struct LoggingLayer;
+
+impl ConduitLayer for LoggingLayer {
+    fn wrap(&self, conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>> {
+        Box::pin(async move {
+            let remote = conduit
+                .context
+                .remote_addr
+                .map_or_else(|| "<unknown>".to_string(), |addr| addr.to_string());
+            let remote_copy = remote.clone();
+
+            let sink = conduit.sink.with(move |msg: ConduitMessage| {
+                info!(
+                    "-> {remote}: {} ({} bytes)",
+                    message_kind(&msg),
+                    message_size(&msg)
+                );
+                futures::future::ready(Ok::<_, ConduitError>(msg))
+            });
+            let source = conduit.source.inspect(move |msg| match msg {
+                Ok(msg) => info!(
+                    "<- {remote_copy}: {} ({} bytes)",
+                    message_kind(msg),
+                    message_size(msg)
+                ),
+                Err(err) => info!("<- {remote_copy}: error: {err}"),
+            });
+
+            Ok(Conduit {
+                sink: Box::pin(sink),
+                source: Box::pin(source),
+                context: conduit.context,
+            })
+        })
+    }
+}
+
+/// message and byte counters, shared by all conduits wrapped with the same `MetricsLayer`.
+#[derive(Debug, Default)]
+pub struct ConduitMetrics {
+    pub messages_sent: AtomicU64,
+    pub bytes_sent: AtomicU64,
+    pub messages_received: AtomicU64,
+    pub bytes_received: AtomicU64,
+}
+
+/// counts the messages and bytes sent and received.
+/// Clones share the counters, so one layer can be used for all connections of a server.
+#[derive(Clone, Default)]
+pub struct MetricsLayer {
+    metrics: Arc<ConduitMetrics>,
+}
+
+impl MetricsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn metrics(&self) -> Arc<ConduitMetrics> {
+        self.metrics.clone()
+    }
+}
+
+impl ConduitLayer for MetricsLayer {
+    fn wrap(&self, conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>> {
+        let sent = self.metrics.clone();
+        let received = self.metrics.clone();
+        Box::pin(async move {
+            let sink = conduit.sink.with(move |msg: ConduitMessage| {
+                sent.messages_sent.fetch_add(1, Ordering::Relaxed);
+                sent.bytes_sent
+                    .fetch_add(message_size(&msg) as u64, Ordering::Relaxed);
+                futures::future::ready(Ok::<_, ConduitError>(msg))
+            });
+            let source = conduit.source.inspect(move |msg| {
+                if let Ok(msg) = msg {
+                    received.messages_received.fetch_add(1, Ordering::Relaxed);
+                    received
+                        .bytes_received
+                        .fetch_add(message_size(msg) as u64, Ordering::Relaxed);
+                }
+            });
+
+            Ok(Conduit {
+                sink: Box::pin(sink),
+                source: Box::pin(source),
+                context: conduit.context,
+            })
+        })
+    }
+}
@@ -1,17 +1,42 @@
 #[cfg(any(
     feature = "websocket_client",
     feature = "websocket_client_wasm",
+    feature = "websocket_client_ewebsock",
     feature = "websocket_server"
 ))]
 pub mod websocket;
 
+#[cfg(feature = "quic")]
+pub mod quic;
+
+#[cfg(any(feature = "websocket_server", feature = "quic"))]
+pub mod server;
+
+pub mod layer;
+pub mod multiplex;
+pub mod priority;
+pub mod reconnect;
+
+#[cfg(feature = "serial")]
+pub mod serial;
+
+#[cfg(feature = "noise")]
+pub mod noise;
+
+#[cfg(all(feature = "shared_memory", target_os = "linux"))]
+pub mod shared_memory;
+
+#[cfg(any(feature = "http_fallback_server", feature = "http_fallback_client"))]
+pub mod http_fallback;
+
 use futures::{Sink, Stream, StreamExt};
 use ractor::ActorRef;
-use std::pin::Pin;
+use std::{fmt::Display, net::SocketAddr, pin::Pin};
 
 use log::{error, info};
 
 use crate::{
+    conduit::layer::{Conduit, ConduitLayers},
     nexus::{self, NexusActorMessage},
     portal::PortalActorMessage,
     util::ActorRef_Ask,
@@ -22,11 +47,104 @@ use crate::{
 pub enum ConduitMessage {
     Text(String),
     Binary(Vec<u8>),
-    Close(Option<String>),
+    /// a binary frame which carries application messages (see `CrossPortalMessage::to_conduit_message`).
+    /// Conduits with a separate channel for them (e.g. QUIC) send it there, all others send it like `Binary`.
+    /// It is received as `Binary`.
+    Bulk(Vec<u8>),
+    Close(Option<CloseReason>),
 }
 
 pub type ConduitError = anyhow::Error;
 
+/// why a conduit was closed. The codes map to websocket close codes, see `to_u16`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub enum CloseCode {
+    /// the portal is done, e.g. the user logged out
+    Normal,
+    /// the side is shutting down or restarting (a server), or navigating away (a browser)
+    GoingAway,
+    /// the remote side sent something invalid
+    ProtocolError,
+    /// the remote side broke a rule of the application, e.g. it was kicked
+    PolicyViolation,
+    /// the remote side exceeded a limit, e.g. it sent too many messages
+    QuotaExceeded,
+    /// the remote side couldn't be authenticated, or isn't authorized
+    AuthFailed,
+    /// any other websocket close code
+    Other(u16),
+}
+
+impl CloseCode {
+    /// the websocket close code. `QuotaExceeded` and `AuthFailed` use the application range (4000-4999),
+    /// modeled after the http status codes 429 and 401.
+    pub fn to_u16(self) -> u16 {
+        match self {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::QuotaExceeded => 4429,
+            CloseCode::AuthFailed => 4401,
+            CloseCode::Other(code) => code,
+        }
+    }
+
+    pub fn from_u16(code: u16) -> Self {
+        match code {
+            1000 => CloseCode::Normal,
+            1001 => CloseCode::GoingAway,
+            1002 => CloseCode::ProtocolError,
+            1008 => CloseCode::PolicyViolation,
+            4429 => CloseCode::QuotaExceeded,
+            4401 => CloseCode::AuthFailed,
+            other => CloseCode::Other(other),
+        }
+    }
+}
+
+/// the code and (human readable) reason of a close, sent to the remote side and surfaced when the portal terminates.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct CloseReason {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+impl CloseReason {
+    pub fn new(code: CloseCode, reason: impl Into<String>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
+
+    /// `code:u16 + reason`, for conduits which send the close as a plain frame.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.code.to_u16().to_le_bytes().to_vec();
+        bytes.extend_from_slice(self.reason.as_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ConduitError> {
+        let Some((code, reason)) = bytes.split_first_chunk::<2>() else {
+            return Err(anyhow::anyhow!("The close reason is too short"));
+        };
+        Ok(Self {
+            code: CloseCode::from_u16(u16::from_le_bytes(*code)),
+            reason: String::from_utf8(reason.to_vec())?,
+        })
+    }
+}
+
+impl Display for CloseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.reason.is_empty() {
+            true => write!(f, "{:?}", self.code),
+            false => write!(f, "{} ({:?})", self.reason, self.code),
+        }
+    }
+}
+
 /// the sink, from the point of view of the Conduit; that is, the 'tx' end of a websocket for example.
 /// The conduit writes messages into it.
 pub type ConduitSink = Pin<Box<dyn Sink<ConduitMessage, Error = ConduitError> + Send>>;
@@ -34,6 +152,58 @@ pub type ConduitSink = Pin<Box<dyn Sink<ConduitMessage, Error = ConduitError> +
 /// the Conduit (asynchronously) reads messages from it.
 pub type ConduitSource = Pin<Box<dyn Stream<Item = Result<ConduitMessage, ConduitError>> + Send>>;
 
+/// what the conduit knows about the remote side of the connection.
+/// It is passed to the nexus and from there to the application, e.g. to authorize the peer.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionContext {
+    pub remote_addr: Option<SocketAddr>,
+    /// the certificate chain presented by the peer and verified by TLS (DER encoded, leaf certificate first).
+    /// Empty if the conduit doesn't use TLS, or the peer didn't present a certificate.
+    pub peer_certificates: Vec<Vec<u8>>,
+    /// the path of the http request, if the conduit was opened by upgrading a http request (e.g. a websocket)
+    pub path: Option<String>,
+    /// the headers of the http request, if the conduit was opened by upgrading a http request.
+    /// Header names are lowercase, values which aren't valid utf-8 are skipped.
+    pub headers: Vec<(String, String)>,
+    /// the conduit prefers the compact binary `Introduction` over json, e.g. because it is a slow serial link.
+    pub binary_introduction: bool,
+    /// the static public key of the remote side, authenticated by an encryption layer (e.g. `conduit::noise::NoiseLayer`).
+    pub remote_static_key: Option<Vec<u8>>,
+}
+
+impl ConnectionContext {
+    /// returns the first value of the header with the given name (case insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// copies the path and headers of the http request which opened the conduit (e.g. the websocket upgrade) into the context.
+    #[cfg(any(feature = "websocket_server", feature = "http_fallback_server"))]
+    pub(crate) fn add_request_metadata<B>(&mut self, request: &http::Request<B>) {
+        self.path = Some(request.uri().path().to_string());
+        self.headers = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+    }
+
+    /// `{scheme}://{remote_addr}{path}`, or a random id instead of the address if it isn't known (e.g. behind a proxy).
+    #[cfg(any(feature = "websocket_server", feature = "http_fallback_server"))]
+    pub(crate) fn portal_identifier(&self, scheme: &str) -> String {
+        let path = self.path.as_deref().unwrap_or_default();
+        match self.remote_addr {
+            Some(addr) => format!("{scheme}://{addr}{path}"),
+            None => format!("{scheme}-client://{}{path}", rand::random::<u64>()),
+        }
+    }
+}
+
 // -------------------------------------------------------------------------------------------------------
 
 pub async fn receive_loop(
@@ -42,6 +212,7 @@ pub async fn receive_loop(
     actor_ref: ActorRef<PortalActorMessage>,
 ) {
     // Process incoming messages
+    let mut close_reason = None;
     while let Some(msg) = receiver.next().await {
         match msg {
             Ok(msg) => match msg {
@@ -51,14 +222,15 @@ pub async fn receive_loop(
                         break;
                     }
                 }
-                ConduitMessage::Binary(data) => {
+                ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => {
                     if let Err(err) = actor_ref.cast(PortalActorMessage::Binary(data.to_vec())) {
                         error!("Error sending binary message to actor: {err}");
                         break;
                     }
                 }
-                ConduitMessage::Close(close_frame) => {
-                    info!("Portal with {identifier} closed because of reason: {close_frame:?}");
+                ConduitMessage::Close(reason) => {
+                    info!("Portal with {identifier} closed because of reason: {reason:?}");
+                    close_reason = reason;
                     break;
                 }
             },
@@ -70,7 +242,7 @@ pub async fn receive_loop(
     }
 
     info!("Portal with {identifier} closed");
-    let _ = actor_ref.cast(PortalActorMessage::Close);
+    let _ = actor_ref.cast(PortalActorMessage::Close(close_reason));
 }
 
 pub async fn from_sink_source(
@@ -78,10 +250,54 @@ pub async fn from_sink_source(
     portal_identifier: String,
     sink: ConduitSink,
     source: ConduitSource,
+) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
+    from_sink_source_with_context(
+        nexus,
+        portal_identifier,
+        ConnectionContext::default(),
+        sink,
+        source,
+    )
+    .await
+}
+
+/// wraps the sink and source in the layers (see `conduit::layer`), then opens a portal on the result.
+pub async fn from_sink_source_with_layers(
+    nexus: ActorRef<nexus::NexusActorMessage>,
+    portal_identifier: String,
+    context: ConnectionContext,
+    sink: ConduitSink,
+    source: ConduitSource,
+    layers: &ConduitLayers,
+) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
+    let conduit = layers
+        .apply(Conduit {
+            sink,
+            source,
+            context,
+        })
+        .await?;
+
+    from_sink_source_with_context(
+        nexus,
+        portal_identifier,
+        conduit.context,
+        conduit.sink,
+        conduit.source,
+    )
+    .await
+}
+
+pub async fn from_sink_source_with_context(
+    nexus: ActorRef<nexus::NexusActorMessage>,
+    portal_identifier: String,
+    context: ConnectionContext,
+    sink: ConduitSink,
+    source: ConduitSource,
 ) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
     let portal = nexus
         .ask(
-            |rpc| NexusActorMessage::Connected(portal_identifier.clone(), sink, rpc),
+            |rpc| NexusActorMessage::Connected(portal_identifier.clone(), context, sink, rpc),
             None,
         )
         .await;
@@ -101,3 +317,33 @@ pub async fn from_sink_source(
         }
     }
 }
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_reason_roundtrip() -> Result<(), ConduitError> {
+        for code in [
+            CloseCode::Normal,
+            CloseCode::GoingAway,
+            CloseCode::ProtocolError,
+            CloseCode::PolicyViolation,
+            CloseCode::QuotaExceeded,
+            CloseCode::AuthFailed,
+            CloseCode::Other(4000),
+        ] {
+            assert_eq!(CloseCode::from_u16(code.to_u16()), code);
+
+            let reason = CloseReason::new(code, "kicked: spamming");
+            assert_eq!(CloseReason::from_bytes(&reason.to_bytes())?, reason);
+        }
+
+        let empty = CloseReason::new(CloseCode::Normal, "");
+        assert_eq!(CloseReason::from_bytes(&empty.to_bytes())?, empty);
+        assert!(CloseReason::from_bytes(&[0xe8]).is_err());
+        Ok(())
+    }
+}
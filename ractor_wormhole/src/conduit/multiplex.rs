@@ -0,0 +1,556 @@
+//! Several independent portals over one conduit.
+//!
+//! A `MultiplexLink` takes over a sink and source (e.g. a websocket) and carries any number of channels over it.
+//! Each channel is a complete portal, with its own `ConduitID`, handshake, published actors and lifecycle;
+//! only the transport (and its TLS handshake) is shared.
+//!
+//! Both sides create a link on their end of the transport. Either side can then open a portal with `open_portal`;
+//! on the remote side, the new portal is passed to the nexus like any other connection.
+//!
+//! ```ignore
+//! let (sink, source) = split_websocket(ws_stream);
+//! let link = MultiplexLink::new(nexus, "ws://server".to_string(), context, sink, source);
+//! let tenant_a = link.open_portal().await?;
+//! let tenant_b = link.open_portal().await?;
+//! // ...
+//! link.close_portal(&tenant_a, Some(CloseReason::new(CloseCode::Normal, "tenant logged out")));
+//! ```
+//!
+//! The number of channels the remote side may open is limited, see `MultiplexConfig`.
+//!
+//! Every message of a channel is sent as one binary frame: `[kind: u8][channel: u32 LE][payload]`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    SinkExt, StreamExt,
+    channel::{mpsc, oneshot},
+};
+use log::{error, info, warn};
+use ractor::ActorRef;
+
+use crate::{
+    conduit::{
+        CloseCode, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+        ConnectionContext, from_sink_source_with_context,
+    },
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+const FRAME_OPEN: u8 = 0;
+const FRAME_TEXT: u8 = 1;
+const FRAME_BINARY: u8 = 2;
+const FRAME_CLOSE: u8 = 3;
+
+const HEADER_SIZE: usize = 5;
+
+/// identifies a channel on one side of the link.
+///
+/// Both sides allocate channel ids independently, so the id alone is ambiguous;
+/// on the wire, the lowest bit says whether the channel was opened by the receiver of the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ChannelKey {
+    opened_locally: bool,
+    id: u32,
+}
+
+impl ChannelKey {
+    fn to_wire(self) -> u32 {
+        (self.id << 1) | u32::from(!self.opened_locally)
+    }
+
+    fn from_wire(wire: u32) -> Self {
+        Self {
+            opened_locally: wire & 1 == 1,
+            id: wire >> 1,
+        }
+    }
+}
+
+fn frame(kind: u8, key: ChannelKey, payload: &[u8]) -> ConduitMessage {
+    ConduitMessage::Binary(frame_bytes(kind, key, payload))
+}
+
+fn frame_bytes(kind: u8, key: ChannelKey, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_SIZE + payload.len());
+    data.push(kind);
+    data.extend_from_slice(&key.to_wire().to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+struct Channel {
+    /// the source of the portal of this channel
+    tx: mpsc::UnboundedSender<Result<ConduitMessage, ConduitError>>,
+    /// None while the portal is starting
+    portal: Option<ActorRef<PortalActorMessage>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiplexConfig {
+    /// the maximum number of open channels opened by the remote side. Further channels are closed right away
+    /// (with `CloseCode::QuotaExceeded`). Channels opened with `open_portal` don't count.
+    pub max_remote_channels: usize,
+}
+
+impl Default for MultiplexConfig {
+    fn default() -> Self {
+        Self {
+            max_remote_channels: 64,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LinkState {
+    closed: bool,
+    next_id: u32,
+    channels: HashMap<ChannelKey, Channel>,
+    waiting_for_close: Vec<oneshot::Sender<()>>,
+}
+
+struct LinkShared {
+    nexus: ActorRef<NexusActorMessage>,
+    identifier: String,
+    context: ConnectionContext,
+    config: MultiplexConfig,
+    /// all frames go through the writer task, which owns the sink
+    outgoing: mpsc::UnboundedSender<ConduitMessage>,
+    state: Mutex<LinkState>,
+}
+
+/// a conduit which carries several portals. Clones refer to the same link.
+#[derive(Clone)]
+pub struct MultiplexLink {
+    shared: Arc<LinkShared>,
+}
+
+impl MultiplexLink {
+    /// takes over the sink and source. The remote side must also use a `MultiplexLink`.
+    ///
+    /// Portals opened by the remote side are created on the nexus, with `{identifier}#{channel}` as their identifier
+    /// and a copy of the context.
+    pub fn new(
+        nexus: ActorRef<NexusActorMessage>,
+        identifier: String,
+        context: ConnectionContext,
+        sink: ConduitSink,
+        source: ConduitSource,
+    ) -> Self {
+        Self::with_config(
+            nexus,
+            identifier,
+            context,
+            sink,
+            source,
+            MultiplexConfig::default(),
+        )
+    }
+
+    pub fn with_config(
+        nexus: ActorRef<NexusActorMessage>,
+        identifier: String,
+        context: ConnectionContext,
+        sink: ConduitSink,
+        source: ConduitSource,
+        config: MultiplexConfig,
+    ) -> Self {
+        let (outgoing, outgoing_rx) = mpsc::unbounded();
+        let shared = Arc::new(LinkShared {
+            nexus,
+            identifier,
+            context,
+            config,
+            outgoing,
+            state: Mutex::new(LinkState::default()),
+        });
+
+        ractor::concurrency::spawn(write_loop(sink, outgoing_rx, shared.clone()));
+        ractor::concurrency::spawn(read_loop(source, shared.clone()));
+
+        Self { shared }
+    }
+
+    /// opens a new channel and a portal on it. The remote side creates the other end of the portal on its nexus.
+    pub async fn open_portal(&self) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
+        let (key, source) = {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.closed {
+                return Err(anyhow::anyhow!("The multiplexed link is closed"));
+            }
+            if state.next_id > u32::MAX >> 1 {
+                return Err(anyhow::anyhow!(
+                    "The multiplexed link ran out of channel ids"
+                ));
+            }
+            let key = ChannelKey {
+                opened_locally: true,
+                id: state.next_id,
+            };
+            state.next_id += 1;
+
+            let (tx, rx) = mpsc::unbounded();
+            state.channels.insert(key, Channel { tx, portal: None });
+            // queued before the introduction of the portal, so the remote side knows the channel when it arrives
+            let _ = self
+                .shared
+                .outgoing
+                .unbounded_send(frame(FRAME_OPEN, key, &[]));
+            (key, rx)
+        };
+
+        self.shared.clone().start_portal(key, source).await
+    }
+
+    /// closes the portal, which must have been opened on this link (by either side). The link stays open.
+    pub fn close_portal(&self, portal: &ActorRef<PortalActorMessage>, reason: Option<CloseReason>) {
+        let key = {
+            let state = self.shared.state.lock().unwrap();
+            state.channels.iter().find_map(|(key, channel)| {
+                channel
+                    .portal
+                    .as_ref()
+                    .filter(|p| p.get_id() == portal.get_id())
+                    .map(|_| *key)
+            })
+        };
+
+        // the portal sends the close frame through its sink when it disconnects
+        if portal
+            .send_message(PortalActorMessage::Disconnect(reason.clone()))
+            .is_err()
+            && let Some(key) = key
+        {
+            self.shared.close_channel(key, reason);
+        }
+    }
+
+    /// the portals of all open channels, including the ones opened by the remote side.
+    pub fn portals(&self) -> Vec<ActorRef<PortalActorMessage>> {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .channels
+            .values()
+            .filter_map(|channel| channel.portal.clone())
+            .collect()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap().closed
+    }
+
+    /// closes the transport, and with it all portals of this link.
+    pub fn close(&self, reason: Option<CloseReason>) {
+        self.shared.shutdown(reason);
+    }
+
+    /// waits until the link has been closed, by either side or because the transport failed.
+    pub async fn closed(&self) {
+        let rx = {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+            let (tx, rx) = oneshot::channel();
+            state.waiting_for_close.push(tx);
+            rx
+        };
+        let _ = rx.await;
+    }
+}
+
+impl LinkShared {
+    async fn start_portal(
+        self: Arc<Self>,
+        key: ChannelKey,
+        source: mpsc::UnboundedReceiver<Result<ConduitMessage, ConduitError>>,
+    ) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
+        let sink = futures::sink::unfold(
+            self.clone(),
+            move |shared, msg: ConduitMessage| async move {
+                shared.send_on_channel(key, msg)?;
+                Ok::<_, ConduitError>(shared)
+            },
+        );
+
+        let portal = from_sink_source_with_context(
+            self.nexus.clone(),
+            format!("{}#{}", self.identifier, key.to_wire()),
+            self.context.clone(),
+            Box::pin(sink),
+            Box::pin(source),
+        )
+        .await;
+
+        let portal = match portal {
+            Ok(portal) => portal,
+            Err(err) => {
+                let reason = CloseReason::new(
+                    CloseCode::ProtocolError,
+                    format!("Failed to start the portal: {err}"),
+                );
+                self.close_channel(key, Some(reason));
+                return Err(err);
+            }
+        };
+
+        let still_open = {
+            let mut state = self.state.lock().unwrap();
+            match state.channels.get_mut(&key) {
+                Some(channel) => {
+                    channel.portal = Some(portal.clone());
+                    true
+                }
+                None => false,
+            }
+        };
+        if !still_open {
+            let _ = portal.send_message(PortalActorMessage::Disconnect(None));
+        }
+
+        // a portal which stops without closing its conduit (e.g. because it crashed) must still release its channel
+        let shared = self.clone();
+        let cell = portal.get_cell();
+        ractor::concurrency::spawn(async move {
+            let _ = cell.wait(None).await;
+            shared.close_channel(key, None);
+        });
+
+        Ok(portal)
+    }
+
+    /// called by the sink of the portal on the channel.
+    fn send_on_channel(&self, key: ChannelKey, msg: ConduitMessage) -> Result<(), ConduitError> {
+        let frame = match msg {
+            ConduitMessage::Text(text) => frame(FRAME_TEXT, key, text.as_bytes()),
+            ConduitMessage::Binary(data) => frame(FRAME_BINARY, key, &data),
+            ConduitMessage::Bulk(data) => {
+                ConduitMessage::Bulk(frame_bytes(FRAME_BINARY, key, &data))
+            }
+            ConduitMessage::Close(reason) => {
+                self.close_channel(key, reason);
+                return Ok(());
+            }
+        };
+
+        let state = self.state.lock().unwrap();
+        if state.closed || !state.channels.contains_key(&key) {
+            return Err(anyhow::anyhow!("The channel is closed"));
+        }
+        self.outgoing
+            .unbounded_send(frame)
+            .map_err(|_| anyhow::anyhow!("The multiplexed link is closed"))
+    }
+
+    /// closes the channel on both sides. Does nothing if it is already closed.
+    fn close_channel(&self, key: ChannelKey, reason: Option<CloseReason>) {
+        let mut state = self.state.lock().unwrap();
+        if state.channels.remove(&key).is_some() && !state.closed {
+            let reason = reason.map(|reason| reason.to_bytes()).unwrap_or_default();
+            let _ = self
+                .outgoing
+                .unbounded_send(frame(FRAME_CLOSE, key, &reason));
+        }
+    }
+
+    /// handles a frame from the remote side.
+    fn receive(self: &Arc<Self>, data: &[u8]) -> Result<(), ConduitError> {
+        if data.len() < HEADER_SIZE {
+            return Err(anyhow::anyhow!("Frame is too short"));
+        }
+        let kind = data[0];
+        let key = ChannelKey::from_wire(u32::from_le_bytes([data[1], data[2], data[3], data[4]]));
+        let payload = &data[HEADER_SIZE..];
+
+        let msg = match kind {
+            FRAME_OPEN => return self.accept(key),
+            FRAME_TEXT => ConduitMessage::Text(String::from_utf8(payload.to_vec())?),
+            FRAME_BINARY => ConduitMessage::Binary(payload.to_vec()),
+            FRAME_CLOSE => {
+                let reason = match payload.is_empty() {
+                    true => None,
+                    false => Some(CloseReason::from_bytes(payload)?),
+                };
+                let channel = self.state.lock().unwrap().channels.remove(&key);
+                if let Some(channel) = channel {
+                    let _ = channel.tx.unbounded_send(Ok(ConduitMessage::Close(reason)));
+                }
+                return Ok(());
+            }
+            other => return Err(anyhow::anyhow!("Unknown frame kind {other}")),
+        };
+
+        let state = self.state.lock().unwrap();
+        // frames for a channel which was closed in the meantime are dropped
+        if let Some(channel) = state.channels.get(&key) {
+            let _ = channel.tx.unbounded_send(Ok(msg));
+        }
+        Ok(())
+    }
+
+    /// the remote side opened a channel
+    fn accept(self: &Arc<Self>, key: ChannelKey) -> Result<(), ConduitError> {
+        if key.opened_locally {
+            return Err(anyhow::anyhow!("Remote side opened a local channel"));
+        }
+
+        let (tx, rx) = mpsc::unbounded();
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                return Ok(());
+            }
+            if state.channels.contains_key(&key) {
+                return Err(anyhow::anyhow!("Channel {} is already open", key.id));
+            }
+
+            let max = self.config.max_remote_channels;
+            let remote_channels = state.channels.keys().filter(|k| !k.opened_locally).count();
+            if remote_channels >= max {
+                warn!(
+                    "Refusing channel on multiplexed link {}: the remote side already opened {max} channels",
+                    self.identifier
+                );
+                let reason = CloseReason::new(CloseCode::QuotaExceeded, "Too many channels");
+                let _ = self
+                    .outgoing
+                    .unbounded_send(frame(FRAME_CLOSE, key, &reason.to_bytes()));
+                return Ok(());
+            }
+
+            state.channels.insert(key, Channel { tx, portal: None });
+        }
+
+        let shared = self.clone();
+        ractor::concurrency::spawn(async move {
+            if let Err(err) = shared.start_portal(key, rx).await {
+                error!("Error opening portal on multiplexed link: {err}");
+            }
+        });
+        Ok(())
+    }
+
+    /// closes the transport and all channels. Does nothing if the link is already closed.
+    fn shutdown(&self, reason: Option<CloseReason>) {
+        let (channels, waiting) = {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+            state.closed = true;
+            (
+                std::mem::take(&mut state.channels),
+                std::mem::take(&mut state.waiting_for_close),
+            )
+        };
+
+        info!(
+            "Multiplexed link {} closed, reason: {reason:?}",
+            self.identifier
+        );
+        let _ = self
+            .outgoing
+            .unbounded_send(ConduitMessage::Close(reason.clone()));
+
+        for channel in channels.into_values() {
+            let _ = channel
+                .tx
+                .unbounded_send(Ok(ConduitMessage::Close(reason.clone())));
+        }
+        for tx in waiting {
+            let _ = tx.send(());
+        }
+    }
+}
+
+async fn write_loop(
+    mut sink: ConduitSink,
+    mut outgoing: mpsc::UnboundedReceiver<ConduitMessage>,
+    shared: Arc<LinkShared>,
+) {
+    while let Some(msg) = outgoing.next().await {
+        let is_close = matches!(msg, ConduitMessage::Close(_));
+        if let Err(err) = sink.send(msg).await {
+            error!(
+                "Error sending on multiplexed link {}: {err}",
+                shared.identifier
+            );
+            shared.shutdown(Some(CloseReason::new(
+                CloseCode::GoingAway,
+                format!("Error sending: {err}"),
+            )));
+            break;
+        }
+        if is_close {
+            break;
+        }
+    }
+}
+
+async fn read_loop(mut source: ConduitSource, shared: Arc<LinkShared>) {
+    let reason = loop {
+        match source.next().await {
+            Some(Ok(ConduitMessage::Binary(data) | ConduitMessage::Bulk(data))) => {
+                if let Err(err) = shared.receive(&data) {
+                    warn!(
+                        "Dropping invalid frame on multiplexed link {}: {err}",
+                        shared.identifier
+                    );
+                }
+            }
+            Some(Ok(ConduitMessage::Text(_))) => {
+                warn!(
+                    "Dropping text message on multiplexed link {}",
+                    shared.identifier
+                );
+            }
+            Some(Ok(ConduitMessage::Close(reason))) => break reason,
+            Some(Err(err)) => {
+                error!(
+                    "Error receiving on multiplexed link {}: {err}",
+                    shared.identifier
+                );
+                break Some(CloseReason::new(
+                    CloseCode::GoingAway,
+                    format!("Error receiving: {err}"),
+                ));
+            }
+            None => break None,
+        }
+    };
+
+    shared.shutdown(reason);
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_key_perspective() {
+        // a channel opened by one side is seen as opened remotely by the other side, and vice versa
+        let local = ChannelKey {
+            opened_locally: true,
+            id: 7,
+        };
+        let remote = ChannelKey::from_wire(local.to_wire());
+        assert_eq!(
+            remote,
+            ChannelKey {
+                opened_locally: false,
+                id: 7
+            }
+        );
+        assert_eq!(ChannelKey::from_wire(remote.to_wire()), local);
+    }
+}
@@ -0,0 +1,726 @@
+//! An encryption layer using the Noise protocol framework (`Noise_XX_25519_ChaChaPoly_BLAKE2s`, using snow).
+//!
+//! Use it when the transport itself can't be trusted, e.g. a relay, a raw tcp connection or a serial link.
+//! Both sides must wrap their conduit in a `NoiseLayer`, one as initiator (usually the client), one as responder.
+//!
+//! The handshake runs before the portal sends its introduction. Afterwards, the static public key of the
+//! remote side is available as `ConnectionContext::remote_static_key`, so applications can pin peers by key
+//! (or let the layer do it, with `NoiseLayer::with_allowed_remote_keys`).
+//!
+//! Every message is encrypted into one binary frame: `sequence:u64` followed by one or more `length:u16 + ciphertext` chunks
+//! (a noise message is limited to 64KiB). The nonce of each chunk is derived from the sequence number of the frame and
+//! the index of the chunk, and its plaintext starts with flags marking the first and the last chunk,
+//! so a frame can't be truncated or spliced together from the chunks of other frames.
+//!
+//! Bulk frames are numbered separately from all other frames (the top bit of the sequence number marks them),
+//! because the conduit below may send them on a channel of their own (like QUIC's bulk stream),
+//! where they can fall behind the other frames by any amount.
+//! By default the frames of each of the two sequences must arrive in order, so dropped, reordered and replayed frames are rejected.
+//! Over a conduit which may also reorder frames within a sequence, use `NoiseLayer::allow_reordering`;
+//! then the frames of each sequence may be reordered by up to 64 frames, and replayed frames are still rejected.
+//!
+//! Close reasons are sent encrypted. A close of the conduit below is unauthenticated, so its reason is dropped.
+
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt, future::BoxFuture};
+use log::{info, warn};
+use ractor::concurrency::Duration;
+use snow::{HandshakeState, StatelessTransportState};
+
+use crate::conduit::{
+    CloseCode, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+    layer::{Conduit, ConduitLayer},
+};
+
+pub use snow;
+
+// -------------------------------------------------------------------------------------------------------
+
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+/// the maximum size of a noise message, including the authentication tag
+const MAX_NOISE_MESSAGE: usize = 65535;
+const TAG_LENGTH: usize = 16;
+const MAX_CHUNK: usize = MAX_NOISE_MESSAGE - TAG_LENGTH;
+
+const FRAME_TEXT: u8 = 0;
+const FRAME_BINARY: u8 = 1;
+const FRAME_CLOSE: u8 = 2;
+
+/// the flags at the start of the plaintext of every chunk
+const CHUNK_FIRST: u8 = 1;
+const CHUNK_LAST: u8 = 2;
+const MAX_CHUNK_DATA: usize = MAX_CHUNK - 1;
+
+/// set in the sequence number of a bulk frame
+const BULK_SEQUENCE: u64 = 1 << 63;
+/// the nonce of a chunk is `bulk bit | sequence << CHUNK_BITS | chunk index`
+const CHUNK_BITS: u32 = 16;
+const MAX_CHUNKS: usize = 1 << CHUNK_BITS;
+const MAX_SEQUENCE: u64 = 1 << (63 - CHUNK_BITS);
+
+/// generates a new static keypair. Persist the private key to keep the identity across restarts.
+pub fn generate_keypair() -> Result<snow::Keypair, ConduitError> {
+    Ok(snow::Builder::new(NOISE_PARAMS.parse()?).generate_keypair()?)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseRole {
+    Initiator,
+    Responder,
+}
+
+#[derive(Clone)]
+pub struct NoiseLayer {
+    role: NoiseRole,
+    private_key: Vec<u8>,
+    allowed_remote_keys: Option<Vec<Vec<u8>>>,
+    handshake_timeout: Duration,
+    allow_reordering: bool,
+}
+
+impl NoiseLayer {
+    pub fn new(role: NoiseRole, private_key: Vec<u8>) -> Self {
+        Self {
+            role,
+            private_key,
+            allowed_remote_keys: None,
+            handshake_timeout: Duration::from_secs(10),
+            allow_reordering: false,
+        }
+    }
+
+    /// the side which sends the first handshake message, usually the client.
+    pub fn initiator(private_key: Vec<u8>) -> Self {
+        Self::new(NoiseRole::Initiator, private_key)
+    }
+
+    /// the side which waits for the first handshake message, usually the server.
+    pub fn responder(private_key: Vec<u8>) -> Self {
+        Self::new(NoiseRole::Responder, private_key)
+    }
+
+    /// only accept remote sides with one of these static public keys; the handshake fails for all others.
+    pub fn with_allowed_remote_keys(mut self, keys: Vec<Vec<u8>>) -> Self {
+        self.allowed_remote_keys = Some(keys);
+        self
+    }
+
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// accept frames which are reordered (by up to 64 frames of the same sequence), for conduits which don't keep the order.
+    /// Without it, every frame must carry the next sequence number (bulk frames have their own sequence, see the module docs).
+    pub fn allow_reordering(mut self) -> Self {
+        self.allow_reordering = true;
+        self
+    }
+
+    async fn handshake(self, mut conduit: Conduit) -> Result<Conduit, ConduitError> {
+        let builder =
+            snow::Builder::new(NOISE_PARAMS.parse()?).local_private_key(&self.private_key);
+        let handshake = match self.role {
+            NoiseRole::Initiator => builder.build_initiator()?,
+            NoiseRole::Responder => builder.build_responder()?,
+        };
+
+        let handshake = ractor::concurrency::timeout(
+            self.handshake_timeout,
+            run_handshake(handshake, &mut conduit.sink, &mut conduit.source),
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("Noise handshake timed out"))??;
+
+        let remote_static_key = handshake
+            .get_remote_static()
+            .map(|key| key.to_vec())
+            .ok_or_else(|| anyhow::anyhow!("The remote side didn't send its static key"))?;
+
+        if let Some(allowed) = &self.allowed_remote_keys
+            && !allowed.contains(&remote_static_key)
+        {
+            let _ = conduit
+                .sink
+                .send(ConduitMessage::Close(Some(CloseReason::new(
+                    CloseCode::AuthFailed,
+                    "Unknown key",
+                ))))
+                .await;
+            return Err(anyhow::anyhow!(
+                "The static key of the remote side is not allowed"
+            ));
+        }
+
+        info!("Noise handshake complete");
+
+        let transport = Arc::new(handshake.into_stateless_transport_mode()?);
+        let mut context = conduit.context;
+        context.remote_static_key = Some(remote_static_key);
+
+        Ok(Conduit {
+            sink: encrypt_sink(conduit.sink, transport.clone()),
+            source: decrypt_source(
+                conduit.source,
+                transport,
+                ReplayWindows::new(self.allow_reordering),
+            ),
+            context,
+        })
+    }
+}
+
+impl ConduitLayer for NoiseLayer {
+    fn wrap(&self, conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>> {
+        Box::pin(self.clone().handshake(conduit))
+    }
+}
+
+async fn run_handshake(
+    mut handshake: HandshakeState,
+    sink: &mut ConduitSink,
+    source: &mut ConduitSource,
+) -> Result<HandshakeState, ConduitError> {
+    let mut buffer = vec![0u8; MAX_NOISE_MESSAGE];
+
+    while !handshake.is_handshake_finished() {
+        if handshake.is_my_turn() {
+            let len = handshake.write_message(&[], &mut buffer)?;
+            sink.send(ConduitMessage::Binary(buffer[..len].to_vec()))
+                .await?;
+        } else {
+            let message = match source.next().await {
+                Some(Ok(ConduitMessage::Binary(message) | ConduitMessage::Bulk(message))) => {
+                    message
+                }
+                Some(Ok(ConduitMessage::Text(_))) => {
+                    return Err(anyhow::anyhow!(
+                        "Received a text message during the noise handshake, is the remote side using the NoiseLayer?"
+                    ));
+                }
+                Some(Ok(ConduitMessage::Close(reason))) => {
+                    return Err(anyhow::anyhow!(
+                        "The connection was closed during the noise handshake: {reason:?}"
+                    ));
+                }
+                Some(Err(err)) => return Err(err),
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "The connection was closed during the noise handshake"
+                    ));
+                }
+            };
+            handshake.read_message(&message, &mut buffer)?;
+        }
+    }
+
+    Ok(handshake)
+}
+
+// ---------------------------------------------------------------------------------
+
+fn encrypt_sink(sink: ConduitSink, transport: Arc<StatelessTransportState>) -> ConduitSink {
+    let mut sequences = Sequences::default();
+    let sink = sink.with_flat_map(move |msg: ConduitMessage| {
+        let mut encrypt =
+            |bulk, kind, payload: &[u8]| encrypt(&transport, sequences.next(bulk)?, kind, payload);
+        let messages = match msg {
+            ConduitMessage::Text(text) => {
+                vec![encrypt(false, FRAME_TEXT, text.as_bytes()).map(ConduitMessage::Binary)]
+            }
+            ConduitMessage::Binary(data) => {
+                vec![encrypt(false, FRAME_BINARY, &data).map(ConduitMessage::Binary)]
+            }
+            // stays a bulk frame, so the conduit below can still send it on its bulk channel
+            ConduitMessage::Bulk(data) => {
+                vec![encrypt(true, FRAME_BINARY, &data).map(ConduitMessage::Bulk)]
+            }
+            // the reason is sent encrypted, then the conduit below is closed without one
+            ConduitMessage::Close(reason) => {
+                let reason = reason.map(|reason| reason.to_bytes()).unwrap_or_default();
+                vec![
+                    encrypt(false, FRAME_CLOSE, &reason).map(ConduitMessage::Binary),
+                    Ok(ConduitMessage::Close(None)),
+                ]
+            }
+        };
+        futures::stream::iter(messages)
+    });
+    Box::pin(sink)
+}
+
+fn decrypt_source(
+    source: ConduitSource,
+    transport: Arc<StatelessTransportState>,
+    mut windows: ReplayWindows,
+) -> ConduitSource {
+    let source = source.map(move |msg| match msg? {
+        ConduitMessage::Binary(frame) | ConduitMessage::Bulk(frame) => {
+            decrypt(&transport, &mut windows, &frame)
+        }
+        ConduitMessage::Text(_) => Err(anyhow::anyhow!(
+            "Received an unencrypted text message on an encrypted conduit"
+        )),
+        ConduitMessage::Close(reason) => {
+            if let Some(reason) = reason {
+                warn!("Ignoring the unauthenticated reason of a close: {reason}");
+            }
+            Ok(ConduitMessage::Close(None))
+        }
+    });
+    Box::pin(source)
+}
+
+/// the next sequence number of the bulk frames and of all other frames
+#[derive(Default)]
+struct Sequences {
+    other: u64,
+    bulk: u64,
+}
+
+impl Sequences {
+    fn next(&mut self, bulk: bool) -> Result<u64, ConduitError> {
+        let next = match bulk {
+            true => &mut self.bulk,
+            false => &mut self.other,
+        };
+        if *next >= MAX_SEQUENCE {
+            return Err(anyhow::anyhow!(
+                "All sequence numbers of the encrypted conduit are used up, it must be reconnected"
+            ));
+        }
+        let sequence = *next;
+        *next += 1;
+        Ok(if bulk {
+            BULK_SEQUENCE | sequence
+        } else {
+            sequence
+        })
+    }
+}
+
+fn chunk_nonce(sequence: u64, chunk: usize) -> u64 {
+    (sequence & BULK_SEQUENCE) | (sequence & !BULK_SEQUENCE) << CHUNK_BITS | chunk as u64
+}
+
+fn encrypt(
+    transport: &StatelessTransportState,
+    sequence: u64,
+    kind: u8,
+    payload: &[u8],
+) -> Result<Vec<u8>, ConduitError> {
+    let mut plaintext = Vec::with_capacity(payload.len() + 1);
+    plaintext.push(kind);
+    plaintext.extend_from_slice(payload);
+
+    let chunks = plaintext.len().div_ceil(MAX_CHUNK_DATA);
+    if chunks > MAX_CHUNKS {
+        return Err(anyhow::anyhow!(
+            "Message too large to encrypt: {} bytes",
+            payload.len()
+        ));
+    }
+    let mut frame = Vec::with_capacity(8 + plaintext.len() + chunks * (3 + TAG_LENGTH));
+    frame.extend_from_slice(&sequence.to_le_bytes());
+
+    let mut chunk = Vec::with_capacity(MAX_CHUNK);
+    let mut buffer = vec![0u8; MAX_NOISE_MESSAGE];
+    for (i, data) in plaintext.chunks(MAX_CHUNK_DATA).enumerate() {
+        let mut flags = 0;
+        if i == 0 {
+            flags |= CHUNK_FIRST;
+        }
+        if i == chunks - 1 {
+            flags |= CHUNK_LAST;
+        }
+        chunk.clear();
+        chunk.push(flags);
+        chunk.extend_from_slice(data);
+
+        let len = transport.write_message(chunk_nonce(sequence, i), &chunk, &mut buffer)?;
+        frame.extend_from_slice(&(len as u16).to_le_bytes());
+        frame.extend_from_slice(&buffer[..len]);
+    }
+    Ok(frame)
+}
+
+fn decrypt(
+    transport: &StatelessTransportState,
+    windows: &mut ReplayWindows,
+    frame: &[u8],
+) -> Result<ConduitMessage, ConduitError> {
+    let Some((sequence, mut rest)) = frame.split_first_chunk::<8>() else {
+        return Err(anyhow::anyhow!("Encrypted frame too short"));
+    };
+    let sequence = u64::from_le_bytes(*sequence);
+    let window = windows.get(sequence);
+    let number = sequence & !BULK_SEQUENCE;
+    if number >= MAX_SEQUENCE || !window.is_fresh(number) {
+        return Err(anyhow::anyhow!(
+            "Replayed or out of order encrypted frame (sequence {sequence:#x})"
+        ));
+    }
+
+    let mut plaintext = Vec::with_capacity(frame.len());
+    let mut buffer = vec![0u8; MAX_NOISE_MESSAGE];
+    let mut complete = false;
+    let mut chunks = 0;
+    while let Some((len, tail)) = rest.split_first_chunk::<2>() {
+        // (no frame has more than `MAX_CHUNKS` chunks, the nonces of the next ones would belong to the next sequence number)
+        if complete || chunks == MAX_CHUNKS {
+            return Err(anyhow::anyhow!(
+                "Encrypted frame continues after its last chunk"
+            ));
+        }
+        let len = u16::from_le_bytes(*len) as usize;
+        if tail.len() < len {
+            return Err(anyhow::anyhow!("Encrypted frame truncated"));
+        }
+        let (chunk, tail) = tail.split_at(len);
+
+        let n = transport.read_message(chunk_nonce(sequence, chunks), chunk, &mut buffer)?;
+
+        let Some((&flags, data)) = buffer[..n].split_first() else {
+            return Err(anyhow::anyhow!("Empty encrypted chunk"));
+        };
+        if (flags & CHUNK_FIRST != 0) != plaintext.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Encrypted frame doesn't start with its first chunk"
+            ));
+        }
+        complete = flags & CHUNK_LAST != 0;
+
+        plaintext.extend_from_slice(data);
+        chunks += 1;
+        rest = tail;
+    }
+    if !rest.is_empty() || !complete {
+        return Err(anyhow::anyhow!("Encrypted frame truncated"));
+    }
+    window.mark(number);
+
+    let Some((&kind, payload)) = plaintext.split_first() else {
+        return Err(anyhow::anyhow!("Empty encrypted frame"));
+    };
+    match kind {
+        FRAME_TEXT => Ok(ConduitMessage::Text(String::from_utf8(payload.to_vec())?)),
+        FRAME_BINARY => Ok(ConduitMessage::Binary(payload.to_vec())),
+        FRAME_CLOSE if payload.is_empty() => Ok(ConduitMessage::Close(None)),
+        FRAME_CLOSE => Ok(ConduitMessage::Close(Some(CloseReason::from_bytes(
+            payload,
+        )?))),
+        other => Err(anyhow::anyhow!("Unknown encrypted frame kind: {other}")),
+    }
+}
+
+/// the replay windows of the bulk frames and of all other frames
+struct ReplayWindows {
+    other: ReplayWindow,
+    bulk: ReplayWindow,
+}
+
+impl ReplayWindows {
+    fn new(allow_reordering: bool) -> Self {
+        Self {
+            other: ReplayWindow::new(allow_reordering),
+            bulk: ReplayWindow::new(allow_reordering),
+        }
+    }
+
+    /// the window of the sequence which the sequence number belongs to
+    fn get(&mut self, sequence: u64) -> &mut ReplayWindow {
+        match sequence & BULK_SEQUENCE != 0 {
+            true => &mut self.bulk,
+            false => &mut self.other,
+        }
+    }
+}
+
+/// remembers the recently received sequence numbers, so that a replayed frame is rejected.
+/// If reordering is allowed, frames may be reordered by up to 64 sequence numbers; otherwise every frame must be the next one.
+#[derive(Default)]
+struct ReplayWindow {
+    allow_reordering: bool,
+    highest: Option<u64>,
+    /// bit `i` is set if `highest - i` was received
+    seen: u64,
+}
+
+impl ReplayWindow {
+    fn new(allow_reordering: bool) -> Self {
+        Self {
+            allow_reordering,
+            ..Default::default()
+        }
+    }
+
+    fn is_fresh(&self, sequence: u64) -> bool {
+        match self.highest {
+            None if !self.allow_reordering => sequence == 0,
+            None => true,
+            Some(highest) if !self.allow_reordering => highest.checked_add(1) == Some(sequence),
+            Some(highest) if sequence > highest => true,
+            Some(highest) => {
+                let age = highest - sequence;
+                age < 64 && self.seen & (1 << age) == 0
+            }
+        }
+    }
+
+    fn mark(&mut self, sequence: u64) {
+        match self.highest {
+            Some(highest) if sequence <= highest => self.seen |= 1 << (highest - sequence),
+            Some(highest) => {
+                let shift = sequence - highest;
+                self.seen = if shift >= 64 { 0 } else { self.seen << shift } | 1;
+                self.highest = Some(sequence);
+            }
+            None => {
+                self.seen = 1;
+                self.highest = Some(sequence);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// runs the handshake in memory and returns the transports of the initiator and the responder
+    fn transports() -> (StatelessTransportState, StatelessTransportState) {
+        let params: snow::params::NoiseParams = NOISE_PARAMS.parse().unwrap();
+        let initiator_key = generate_keypair().unwrap();
+        let responder_key = generate_keypair().unwrap();
+        let mut initiator = snow::Builder::new(params.clone())
+            .local_private_key(&initiator_key.private)
+            .build_initiator()
+            .unwrap();
+        let mut responder = snow::Builder::new(params)
+            .local_private_key(&responder_key.private)
+            .build_responder()
+            .unwrap();
+
+        let mut message = vec![0u8; MAX_NOISE_MESSAGE];
+        let mut payload = vec![0u8; MAX_NOISE_MESSAGE];
+        while !initiator.is_handshake_finished() || !responder.is_handshake_finished() {
+            let (writer, reader) = if initiator.is_my_turn() {
+                (&mut initiator, &mut responder)
+            } else {
+                (&mut responder, &mut initiator)
+            };
+            let len = writer.write_message(&[], &mut message).unwrap();
+            reader.read_message(&message[..len], &mut payload).unwrap();
+        }
+
+        (
+            initiator.into_stateless_transport_mode().unwrap(),
+            responder.into_stateless_transport_mode().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_large_message_roundtrip() {
+        let (initiator, responder) = transports();
+        let mut sequences = Sequences::default();
+        let mut windows = ReplayWindows::new(false);
+
+        // spans several noise messages
+        let payload: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        let frame = encrypt(
+            &initiator,
+            sequences.next(false).unwrap(),
+            FRAME_BINARY,
+            &payload,
+        )
+        .unwrap();
+        assert_eq!(split_frame(&frame).1.len(), 4);
+
+        match decrypt(&responder, &mut windows, &frame).unwrap() {
+            ConduitMessage::Binary(data) => assert_eq!(data, payload),
+            _ => panic!("expected a binary message"),
+        }
+
+        // the same frame again is rejected
+        assert!(decrypt(&responder, &mut windows, &frame).is_err());
+
+        // a tampered frame is rejected
+        let mut tampered = encrypt(
+            &initiator,
+            sequences.next(false).unwrap(),
+            FRAME_TEXT,
+            b"hello",
+        )
+        .unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(decrypt(&responder, &mut windows, &tampered).is_err());
+    }
+
+    /// splits a frame into its sequence number and chunks
+    fn split_frame(frame: &[u8]) -> (u64, Vec<Vec<u8>>) {
+        let sequence = u64::from_le_bytes(frame[..8].try_into().unwrap());
+        let mut chunks = Vec::new();
+        let mut rest = &frame[8..];
+        while !rest.is_empty() {
+            let len = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            chunks.push(rest[..2 + len].to_vec());
+            rest = &rest[2 + len..];
+        }
+        (sequence, chunks)
+    }
+
+    fn join_frame(sequence: u64, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut frame = sequence.to_le_bytes().to_vec();
+        for chunk in chunks {
+            frame.extend_from_slice(chunk);
+        }
+        frame
+    }
+
+    #[test]
+    fn test_truncated_and_spliced_frames_are_rejected() {
+        let (initiator, responder) = transports();
+        let mut sequences = Sequences::default();
+        let payload = vec![7u8; 3 * MAX_CHUNK];
+        let frame = encrypt(
+            &initiator,
+            sequences.next(false).unwrap(),
+            FRAME_BINARY,
+            &payload,
+        )
+        .unwrap();
+        let (sequence, chunks) = split_frame(&frame);
+        assert_eq!(chunks.len(), 4);
+
+        // the last chunk was dropped
+        let mut windows = ReplayWindows::new(true);
+        let truncated = join_frame(sequence, &chunks[..3]);
+        assert!(decrypt(&responder, &mut windows, &truncated).is_err());
+
+        // the first chunk was dropped, and the sequence number rewritten
+        let mut windows = ReplayWindows::new(true);
+        let spliced = join_frame(sequence + 1, &chunks[1..]);
+        assert!(decrypt(&responder, &mut windows, &spliced).is_err());
+
+        // the chunks of the next frame were appended
+        let next = encrypt(
+            &initiator,
+            sequences.next(false).unwrap(),
+            FRAME_BINARY,
+            b"next",
+        )
+        .unwrap();
+        let (_, next_chunks) = split_frame(&next);
+        let mut windows = ReplayWindows::new(true);
+        let appended = join_frame(sequence, &[chunks.clone(), next_chunks].concat());
+        assert!(decrypt(&responder, &mut windows, &appended).is_err());
+
+        // moved into the other sequence
+        let mut windows = ReplayWindows::new(true);
+        let moved = join_frame(sequence | BULK_SEQUENCE, &chunks);
+        assert!(decrypt(&responder, &mut windows, &moved).is_err());
+
+        // the original frame is fine
+        let mut windows = ReplayWindows::new(true);
+        assert!(decrypt(&responder, &mut windows, &frame).is_ok());
+    }
+
+    #[test]
+    fn test_ordered_window_rejects_gaps() {
+        let (initiator, responder) = transports();
+        let mut sequences = Sequences::default();
+        let mut text = |text: &str| {
+            encrypt(
+                &initiator,
+                sequences.next(false).unwrap(),
+                FRAME_TEXT,
+                text.as_bytes(),
+            )
+            .unwrap()
+        };
+        let first = text("first");
+        let second = text("second");
+        let third = text("third");
+
+        let mut windows = ReplayWindows::new(false);
+        assert!(decrypt(&responder, &mut windows, &first).is_ok());
+        // the second frame was dropped
+        assert!(decrypt(&responder, &mut windows, &third).is_err());
+
+        let mut windows = ReplayWindows::new(true);
+        assert!(decrypt(&responder, &mut windows, &first).is_ok());
+        assert!(decrypt(&responder, &mut windows, &third).is_ok());
+        assert!(decrypt(&responder, &mut windows, &second).is_ok());
+    }
+
+    #[test]
+    fn test_bulk_frames_may_fall_behind() {
+        let (initiator, responder) = transports();
+        let mut sequences = Sequences::default();
+
+        // a bulk frame with more than 64 chunks, overtaken by more than 64 other frames (like on QUIC's two streams)
+        let payload = vec![7u8; 65 * MAX_CHUNK];
+        let bulk = encrypt(
+            &initiator,
+            sequences.next(true).unwrap(),
+            FRAME_BINARY,
+            &payload,
+        )
+        .unwrap();
+        assert!(split_frame(&bulk).1.len() > 64);
+        let others: Vec<_> = (0..100)
+            .map(|i| {
+                let text = format!("control {i}");
+                encrypt(
+                    &initiator,
+                    sequences.next(false).unwrap(),
+                    FRAME_TEXT,
+                    text.as_bytes(),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        for allow_reordering in [false, true] {
+            let mut windows = ReplayWindows::new(allow_reordering);
+            for frame in &others {
+                assert!(decrypt(&responder, &mut windows, frame).is_ok());
+            }
+            match decrypt(&responder, &mut windows, &bulk).unwrap() {
+                ConduitMessage::Binary(data) => assert_eq!(data, payload),
+                _ => panic!("expected a binary message"),
+            }
+            assert!(decrypt(&responder, &mut windows, &bulk).is_err());
+        }
+    }
+
+    #[test]
+    fn test_close_reason_is_encrypted() {
+        let (initiator, responder) = transports();
+        let reason = CloseReason::new(CloseCode::PolicyViolation, "kicked");
+        let frame = encrypt(&initiator, 0, FRAME_CLOSE, &reason.to_bytes()).unwrap();
+        assert!(!String::from_utf8_lossy(&frame).contains("kicked"));
+
+        let mut windows = ReplayWindows::new(false);
+        match decrypt(&responder, &mut windows, &frame).unwrap() {
+            ConduitMessage::Close(Some(received)) => assert_eq!(received, reason),
+            _ => panic!("expected a close"),
+        }
+    }
+
+    #[test]
+    fn test_replay_window() {
+        let mut window = ReplayWindow::new(true);
+        for sequence in [5, 3, 4, 100, 40] {
+            assert!(window.is_fresh(sequence));
+            window.mark(sequence);
+            assert!(!window.is_fresh(sequence));
+        }
+        // too old
+        assert!(!window.is_fresh(36));
+        assert!(window.is_fresh(37));
+    }
+}
@@ -0,0 +1,536 @@
+//! Priority lanes and batching for the outgoing frames of a portal.
+//!
+//! Every frame is put into one of four lanes, and a weighted scheduler in front of the conduit sink decides which frame goes next.
+//! Control traffic and RPC replies are therefore not stuck behind large application messages,
+//! while the lower lanes still get their share of the bandwidth (they are never starved).
+//!
+//! Application messages are sent in `Priority::Normal`, unless the message type opts into another lane with
+//! `#[wormhole(priority = bulk)]` (see `ContextTransmaterializable::priority`),
+//! or the proxy was created with `Portal::instantiate_proxy_for_remote_actor_with_priority`.
+//!
+//! Note that a frame is never interrupted once it has been passed to the sink; the lanes only decide the order of the queued frames.
+//! Each lane is bounded (`PortalConfig::lane_capacity`), so a slow conduit makes the portal wait instead of buffering without limit.
+//! Exit notifications never overtake a message which was queued before them, see `PriorityOutbox::send_after_queued`,
+//! as long as the conduit keeps the order of the frames. Conduits which send bulk frames on a channel of their own (like QUIC)
+//! may still deliver an exit before such a message; the receiving portal then stops the proxy it creates for the exited actor.
+//!
+//! With `PortalConfig::batching`, small frames are coalesced into one `CrossPortalMessage::Batch` (see `BatchConfig`),
+//! which cuts the per-frame overhead (and syscalls) at high message rates.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+    task::Poll,
+};
+
+use futures::{SinkExt, StreamExt, channel::mpsc, future};
+use log::error;
+use ractor::{
+    ActorRef,
+    concurrency::{Duration, Instant, timeout},
+};
+
+use crate::{
+    conduit::{CloseReason, ConduitError, ConduitMessage, ConduitSink},
+    portal::{CrossPortalMessage, PortalActorMessage},
+    transmaterialization::internal_serializations::SimpleByteTransmaterializable,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the lane of an outgoing frame, from the highest to the lowest priority.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Priority {
+    /// the handshake, actor lookups and exit notifications
+    Control,
+    /// replies to a `RpcReplyPort`
+    Reply,
+    /// application messages
+    #[default]
+    Normal,
+    /// large application messages, which may be delayed in favor of everything else
+    Bulk,
+}
+
+impl Priority {
+    const ALL: [Priority; 4] = [
+        Priority::Control,
+        Priority::Reply,
+        Priority::Normal,
+        Priority::Bulk,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// how many frames each lane may send in one round, while the other lanes are waiting.
+/// A weight of 0 is treated as 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct LaneWeights {
+    pub control: u32,
+    pub reply: u32,
+    pub normal: u32,
+    pub bulk: u32,
+}
+
+impl Default for LaneWeights {
+    fn default() -> Self {
+        Self {
+            control: 16,
+            reply: 8,
+            normal: 4,
+            bulk: 1,
+        }
+    }
+}
+
+impl LaneWeights {
+    fn get(&self, priority: Priority) -> u32 {
+        let weight = match priority {
+            Priority::Control => self.control,
+            Priority::Reply => self.reply,
+            Priority::Normal => self.normal,
+            Priority::Bulk => self.bulk,
+        };
+        weight.max(1)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// weighted round robin over the lanes.
+///
+/// In each round, a lane may send up to its weight in frames. The highest lane with frames and credit left goes first;
+/// when no lane with queued frames has credit left, a new round starts.
+pub struct WeightedScheduler<T = ConduitMessage> {
+    weights: LaneWeights,
+    lanes: [VecDeque<T>; 4],
+    credits: [u32; 4],
+}
+
+impl<T> WeightedScheduler<T> {
+    pub fn new(weights: LaneWeights) -> Self {
+        Self {
+            weights,
+            lanes: Default::default(),
+            credits: Priority::ALL.map(|priority| weights.get(priority)),
+        }
+    }
+
+    pub fn push(&mut self, priority: Priority, msg: T) {
+        self.lanes[priority.index()].push_back(msg);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.iter().all(VecDeque::is_empty)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.iter().map(VecDeque::len).sum()
+    }
+
+    /// the oldest message of a lane
+    pub fn front(&self, priority: Priority) -> Option<&T> {
+        self.lanes[priority.index()].front()
+    }
+
+    /// the message which `pop` would return next.
+    pub fn peek(&self) -> Option<&T> {
+        self.next_lane().and_then(|i| self.lanes[i].front())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let i = self.next_lane()?;
+        if self.credits[i] == 0 {
+            self.credits = Priority::ALL.map(|priority| self.weights.get(priority));
+        }
+        self.credits[i] -= 1;
+        self.lanes[i].pop_front()
+    }
+
+    /// the highest lane with frames and credit left; if there is none, a new round starts with the highest lane with frames.
+    fn next_lane(&self) -> Option<usize> {
+        let queued = || (0..self.lanes.len()).filter(|i| !self.lanes[*i].is_empty());
+        queued()
+            .find(|i| self.credits[*i] > 0)
+            .or_else(|| queued().next())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// coalescing of small frames: instead of sending each frame on its own, the outbox waits (up to `max_delay`)
+/// for more frames and sends them together in one `CrossPortalMessage::Batch`, up to `max_bytes`.
+/// Frames which are larger than `max_bytes` are sent on their own, and so is the introduction of the portal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
+pub struct BatchConfig {
+    pub max_bytes: usize,
+    pub max_delay: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024,
+            max_delay: Duration::from_millis(2),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the sending half of a portal: frames are queued in their lane and written to the sink by a separate task.
+///
+/// Each lane holds at most `capacity` frames; when it is full, `send` waits until the writer has made room,
+/// so a slow conduit slows down the portal instead of filling up the memory.
+pub struct PriorityOutbox {
+    lanes: [mpsc::Sender<QueuedFrame>; 4],
+    close: mpsc::UnboundedSender<Option<CloseReason>>,
+    next_seq: AtomicU64,
+}
+
+/// a frame in a lane, numbered in the order in which it was queued (over all lanes)
+struct QueuedFrame {
+    seq: u64,
+    /// only sent once all frames which were queued before it are sent, see `PriorityOutbox::send_after_queued`
+    after_queued: bool,
+    /// never put into a batch, see `PriorityOutbox::send_unbatched`
+    unbatched: bool,
+    msg: ConduitMessage,
+}
+
+impl PriorityOutbox {
+    /// spawns the task which owns the sink. If the sink fails, the portal is closed.
+    pub fn spawn(
+        sink: ConduitSink,
+        weights: LaneWeights,
+        capacity: usize,
+        batching: Option<BatchConfig>,
+        portal: ActorRef<PortalActorMessage>,
+        identifier: String,
+    ) -> Self {
+        let (senders, receivers): (Vec<_>, Vec<_>) = Priority::ALL
+            .iter()
+            .map(|_| mpsc::channel(capacity))
+            .unzip();
+        let (close, close_rx) = mpsc::unbounded();
+        let writer = Writer {
+            sink,
+            lanes: receivers.try_into().unwrap_or_else(|_| unreachable!()),
+            close_rx,
+            scheduler: WeightedScheduler::new(weights),
+            held: Default::default(),
+            batching,
+            close: None,
+        };
+        ractor::concurrency::spawn(async move {
+            if let Err(err) = writer.run().await {
+                error!("Error sending to {identifier}: {err}");
+                let _ = portal.cast(PortalActorMessage::Close(None));
+            }
+        });
+        Self {
+            lanes: senders.try_into().unwrap_or_else(|_| unreachable!()),
+            close,
+            next_seq: AtomicU64::new(0),
+        }
+    }
+
+    /// queues the frame, and waits while its lane is full.
+    /// A `Close` is sent after all frames which are already queued, and ends the outbox.
+    pub async fn send(&self, priority: Priority, msg: ConduitMessage) -> Result<(), ConduitError> {
+        self.queue(priority, msg, false, false).await
+    }
+
+    /// like `send`, but the frame doesn't overtake any frame which is already queued, not even one in a lower lane.
+    /// Used for exit notifications, which must not arrive before a message that still refers to the actor.
+    pub async fn send_after_queued(
+        &self,
+        priority: Priority,
+        msg: ConduitMessage,
+    ) -> Result<(), ConduitError> {
+        self.queue(priority, msg, true, false).await
+    }
+
+    /// like `send`, but the frame is always sent on its own, even with batching.
+    /// Used for the introduction, which the peer has to read before it knows that the following frames may be batched.
+    pub async fn send_unbatched(
+        &self,
+        priority: Priority,
+        msg: ConduitMessage,
+    ) -> Result<(), ConduitError> {
+        self.queue(priority, msg, false, true).await
+    }
+
+    async fn queue(
+        &self,
+        priority: Priority,
+        msg: ConduitMessage,
+        after_queued: bool,
+        unbatched: bool,
+    ) -> Result<(), ConduitError> {
+        let closed = || anyhow::anyhow!("The conduit is closed");
+        if let ConduitMessage::Close(reason) = msg {
+            return self.close.unbounded_send(reason).map_err(|_| closed());
+        }
+        let frame = QueuedFrame {
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            after_queued,
+            unbatched,
+            msg,
+        };
+        self.lanes[priority.index()]
+            .clone()
+            .send(frame)
+            .await
+            .map_err(|_| closed())
+    }
+}
+
+struct Writer {
+    sink: ConduitSink,
+    lanes: [mpsc::Receiver<QueuedFrame>; 4],
+    close_rx: mpsc::UnboundedReceiver<Option<CloseReason>>,
+    /// holds at most one frame per lane (the front of the lane), the rest waits in the bounded lanes
+    scheduler: WeightedScheduler<QueuedFrame>,
+    /// frames of `send_after_queued` which wait for the frames queued before them; their lane is paused meanwhile
+    held: [Option<QueuedFrame>; 4],
+    batching: Option<BatchConfig>,
+    /// set once the portal has asked to close the conduit (or is gone)
+    close: Option<Option<CloseReason>>,
+}
+
+impl Writer {
+    async fn run(mut self) -> Result<(), ConduitError> {
+        loop {
+            self.take_queued();
+
+            if let Some(frame) = self.scheduler.pop() {
+                let msg = match self.batching {
+                    Some(batching) if !frame.unbatched => {
+                        self.fill_batch(frame.msg, batching).await?
+                    }
+                    _ => frame.msg,
+                };
+                // the sink is only flushed once there is nothing left to send
+                self.sink.feed(msg).await?;
+                continue;
+            }
+
+            self.sink.flush().await?;
+
+            if let Some(reason) = self.close.take() {
+                self.sink.send(ConduitMessage::Close(reason)).await?;
+                return Ok(());
+            }
+
+            self.receive().await;
+        }
+    }
+
+    /// takes the front of each lane which has none in the scheduler, so the scheduler can pick from all lanes
+    fn take_queued(&mut self) {
+        if self.close.is_none() {
+            match self.close_rx.try_next() {
+                Ok(Some(reason)) => self.close = Some(reason),
+                // the portal is gone
+                Ok(None) => self.close = Some(None),
+                // no close requested right now
+                Err(_) => {}
+            }
+        }
+        loop {
+            for priority in Priority::ALL {
+                let i = priority.index();
+                if self.held[i].is_none()
+                    && self.scheduler.front(priority).is_none()
+                    && let Ok(Some(frame)) = self.lanes[i].try_next()
+                {
+                    self.enqueue(priority, frame);
+                }
+            }
+            if !self.release_held() {
+                break;
+            }
+        }
+    }
+
+    /// waits for the next frame (or close)
+    async fn receive(&mut self) {
+        let Writer {
+            lanes,
+            close_rx,
+            scheduler,
+            held,
+            close,
+            ..
+        } = self;
+        let mut received = Vec::new();
+        future::poll_fn(|cx| {
+            if close.is_none()
+                && let Poll::Ready(reason) = close_rx.poll_next_unpin(cx)
+            {
+                *close = Some(reason.flatten());
+            }
+            for priority in Priority::ALL {
+                let i = priority.index();
+                if held[i].is_none()
+                    && scheduler.front(priority).is_none()
+                    && let Poll::Ready(Some(frame)) = lanes[i].poll_next_unpin(cx)
+                {
+                    received.push((priority, frame));
+                }
+            }
+            match close.is_some() || !received.is_empty() {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await;
+        for (priority, frame) in received {
+            self.enqueue(priority, frame);
+        }
+        self.release_held();
+    }
+
+    fn enqueue(&mut self, priority: Priority, frame: QueuedFrame) {
+        match frame.after_queued {
+            true => self.held[priority.index()] = Some(frame),
+            false => self.scheduler.push(priority, frame),
+        }
+    }
+
+    /// passes held frames to the scheduler once everything queued before them has been sent.
+    /// The fronts of the lanes are their oldest frames, so it's enough to compare with those.
+    fn release_held(&mut self) -> bool {
+        let mut released = false;
+        for priority in Priority::ALL {
+            let Some(seq) = self.held[priority.index()].as_ref().map(|frame| frame.seq) else {
+                continue;
+            };
+            let older_queued = Priority::ALL.iter().any(|other| {
+                let front = self.scheduler.front(*other).map(|frame| frame.seq);
+                let held = self.held[other.index()].as_ref().map(|frame| frame.seq);
+                front.into_iter().chain(held).any(|other| other < seq)
+            });
+            if !older_queued && let Some(frame) = self.held[priority.index()].take() {
+                self.scheduler.push(priority, frame);
+                released = true;
+            }
+        }
+        released
+    }
+
+    /// adds the following frames to the first one, as long as they fit and arrive in time.
+    /// The batch is a `ConduitMessage::Bulk` if any of its frames is.
+    async fn fill_batch(
+        &mut self,
+        first: ConduitMessage,
+        config: BatchConfig,
+    ) -> Result<ConduitMessage, ConduitError> {
+        let (first, mut bulk) = match first {
+            ConduitMessage::Binary(first) => (first, false),
+            ConduitMessage::Bulk(first) => (first, true),
+            other => return Ok(other),
+        };
+        let frame = |data, bulk| match bulk {
+            true => ConduitMessage::Bulk(data),
+            false => ConduitMessage::Binary(data),
+        };
+        if first.len() >= config.max_bytes {
+            return Ok(frame(first, bulk));
+        }
+
+        let deadline = Instant::now() + config.max_delay;
+        let mut size = first.len();
+        let mut frames = vec![first];
+
+        loop {
+            self.take_queued();
+
+            match self.scheduler.peek() {
+                Some(QueuedFrame {
+                    unbatched: false,
+                    msg: ConduitMessage::Binary(next) | ConduitMessage::Bulk(next),
+                    ..
+                }) if size + next.len() <= config.max_bytes => {
+                    size += next.len();
+                    match self.scheduler.pop().map(|frame| frame.msg) {
+                        Some(ConduitMessage::Binary(next)) => frames.push(next),
+                        Some(ConduitMessage::Bulk(next)) => {
+                            bulk = true;
+                            frames.push(next);
+                        }
+                        _ => {}
+                    }
+                }
+                // the next frame doesn't fit into the batch
+                Some(_) => break,
+                None => {
+                    let now = Instant::now();
+                    if self.close.is_some() || now >= deadline {
+                        break;
+                    }
+                    if timeout(deadline - now, self.receive()).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if frames.len() == 1 {
+            return Ok(frame(frames.remove(0), bulk));
+        }
+        let batch =
+            CrossPortalMessage::Batch(frames.into_iter().map(Vec::into_boxed_slice).collect());
+        Ok(frame(batch.immaterialize()?, bulk))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn text(msg: ConduitMessage) -> String {
+        match msg {
+            ConduitMessage::Text(text) => text,
+            _ => panic!("expected a text message"),
+        }
+    }
+
+    #[test]
+    fn test_higher_lanes_go_first() {
+        let mut scheduler = WeightedScheduler::new(LaneWeights::default());
+        scheduler.push(Priority::Bulk, ConduitMessage::Text("bulk".into()));
+        scheduler.push(Priority::Normal, ConduitMessage::Text("normal".into()));
+        scheduler.push(Priority::Reply, ConduitMessage::Text("reply".into()));
+        scheduler.push(Priority::Control, ConduitMessage::Text("control".into()));
+
+        let order: Vec<_> = std::iter::from_fn(|| scheduler.pop()).map(text).collect();
+        assert_eq!(order, vec!["control", "reply", "normal", "bulk"]);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_lower_lanes_are_not_starved() {
+        let mut scheduler = WeightedScheduler::new(LaneWeights {
+            control: 3,
+            reply: 1,
+            normal: 1,
+            bulk: 1,
+        });
+        for _ in 0..10 {
+            scheduler.push(Priority::Control, ConduitMessage::Text("control".into()));
+        }
+        scheduler.push(Priority::Bulk, ConduitMessage::Text("bulk".into()));
+
+        let order: Vec<_> = std::iter::from_fn(|| scheduler.pop()).map(text).collect();
+        // three control frames per round, then the bulk lane gets its turn
+        assert_eq!(order.iter().position(|msg| msg == "bulk"), Some(3));
+        assert_eq!(order.len(), 11);
+    }
+}
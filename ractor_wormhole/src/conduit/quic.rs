@@ -0,0 +1,461 @@
+//! A conduit over QUIC (using quinn).
+//!
+//! Every connection carries two bidirectional streams:
+//!  * the **control** stream carries the introduction, close frames and all control messages
+//!    (`RequestActorByName`, `ActorExited`, replies to `RpcReplyPort`s, ...)
+//!  * the **bulk** stream carries the application messages (`ConduitMessage::Bulk`, see `CrossPortalMessage::to_conduit_message`)
+//!
+//! so a large message doesn't block the control traffic behind it.
+//!
+//! QUIC connections are identified by a connection id, not by the address, so they survive the client
+//! switching networks (see `quinn::Endpoint::rebind`).
+//!
+//! `start_server_with_config` limits the number of connections and the time a client may take to open its streams,
+//! like the websocket server; both return a `ServerHandle` (see `conduit::server`).
+
+use std::{future::IntoFuture, net::SocketAddr, sync::Arc};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    future,
+};
+use log::{error, info, warn};
+use quinn::{
+    Connection, Endpoint, Incoming, RecvStream, SendStream,
+    rustls::pki_types::{CertificateDer, PrivateKeyDer},
+};
+use ractor::{
+    ActorRef,
+    concurrency::{Duration, Instant},
+};
+
+use crate::{
+    conduit::{
+        self, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+        ConnectionContext, server::ServerShared,
+    },
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+};
+
+pub use crate::conduit::server::ServerHandle;
+pub use quinn;
+
+// -------------------------------------------------------------------------------------------------------
+
+const STREAM_CONTROL: u8 = 0;
+const STREAM_BULK: u8 = 1;
+
+const FRAME_TEXT: u8 = 0;
+const FRAME_BINARY: u8 = 1;
+const FRAME_CLOSE: u8 = 2;
+const FRAME_CLOSE_WITH_REASON: u8 = 3;
+
+/// frames larger than this are rejected, so a malicious peer can't make us allocate arbitrary amounts of memory.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+// -------------------------------------------------------------------------------------------------------
+
+/// creates a server endpoint with the given certificate chain and private key.
+pub fn server_endpoint(
+    bind: SocketAddr,
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<Endpoint, anyhow::Error> {
+    let mut server_config = quinn::ServerConfig::with_single_cert(cert_chain, key)?;
+    // allow clients to change their address, e.g. when switching from wifi to mobile data.
+    server_config.migration(true);
+
+    Ok(Endpoint::server(server_config, bind)?)
+}
+
+/// creates a client endpoint which trusts the given root certificates.
+pub fn client_endpoint(
+    bind: SocketAddr,
+    root_certificates: Vec<CertificateDer<'static>>,
+) -> Result<Endpoint, anyhow::Error> {
+    let mut roots = quinn::rustls::RootCertStore::empty();
+    for cert in root_certificates {
+        roots.add(cert)?;
+    }
+    let client_config = quinn::ClientConfig::with_root_certificates(Arc::new(roots))?;
+
+    let mut endpoint = Endpoint::client(bind)?;
+    endpoint.set_default_client_config(client_config);
+    Ok(endpoint)
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+pub struct ServerConfig {
+    /// the maximum number of concurrent connections (including connections which are still in the handshake).
+    /// Further connections are refused immediately.
+    pub max_connections: Option<usize>,
+    /// the maximum number of concurrent connections from a single IP address.
+    pub max_connections_per_ip: Option<usize>,
+    /// connections which haven't completed the QUIC handshake and opened both streams after this time are closed.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            max_connections_per_ip: None,
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// accepts QUIC connections on the endpoint and opens a portal for each of them.
+pub async fn start_server(
+    nexus: ActorRef<NexusActorMessage>,
+    endpoint: Endpoint,
+) -> Result<ServerHandle, anyhow::Error> {
+    start_server_with_config(nexus, endpoint, ServerConfig::default()).await
+}
+
+pub async fn start_server_with_config(
+    nexus: ActorRef<NexusActorMessage>,
+    endpoint: Endpoint,
+    config: ServerConfig,
+) -> Result<ServerHandle, anyhow::Error> {
+    let local_addr = endpoint.local_addr()?;
+    info!("QUIC server listening on: {local_addr}");
+
+    let (shared, mut stop_rx) = ServerShared::new();
+
+    let config = Arc::new(config);
+    let shared_copy = shared.clone();
+    ractor::concurrency::spawn(async move {
+        loop {
+            let incoming = match future::select(Box::pin(endpoint.accept()), &mut stop_rx).await {
+                future::Either::Left((Some(incoming), _)) => incoming,
+                // the endpoint was closed
+                future::Either::Left((None, _)) => break,
+                // shutdown was requested. Note: the sender is kept alive by `shared_copy`, it can't be cancelled.
+                future::Either::Right(_) => break,
+            };
+
+            let addr = incoming.remote_address();
+            info!("New QUIC connection from: {addr}");
+            let Some(slot) = shared_copy.try_admit(
+                addr.ip(),
+                config.max_connections,
+                config.max_connections_per_ip,
+            ) else {
+                incoming.refuse();
+                continue;
+            };
+
+            // handshake each connection in its own task, so a slow client doesn't block the others
+            let nexus = nexus.clone();
+            let handshake_timeout = config.handshake_timeout;
+            ractor::concurrency::spawn(async move {
+                if let Some(portal) = open_portal(incoming, nexus, handshake_timeout).await {
+                    slot.hold(portal).await;
+                }
+            });
+        }
+        info!("QUIC server on {local_addr} stopped accepting connections");
+
+        // refuse further connections right away, instead of leaving the clients hanging in the handshake
+        while let Some(incoming) = endpoint.accept().await {
+            incoming.refuse();
+        }
+    });
+
+    Ok(ServerHandle::new("QUIC", local_addr, shared))
+}
+
+/// handles a single connection, without connection limits (but with the default handshake timeout).
+/// This is useful if you accept the connections yourself; `start_server` handles each connection in its own task.
+pub async fn handle_connection(incoming: Incoming, nexus: ActorRef<NexusActorMessage>) {
+    open_portal(incoming, nexus, ServerConfig::default().handshake_timeout).await;
+}
+
+async fn open_portal(
+    incoming: Incoming,
+    nexus: ActorRef<NexusActorMessage>,
+    handshake_timeout: Duration,
+) -> Option<ActorRef<PortalActorMessage>> {
+    let started = Instant::now();
+    let connection =
+        match ractor::concurrency::timeout(handshake_timeout, incoming.into_future()).await {
+            Ok(Ok(connection)) => connection,
+            Ok(Err(e)) => {
+                error!("Error during QUIC handshake: {e}");
+                return None;
+            }
+            Err(_) => {
+                warn!("QUIC handshake timed out");
+                return None;
+            }
+        };
+
+    let addr = connection.remote_address();
+    info!("QUIC connection established with: {addr}");
+
+    // the client has to open its streams within the same timeout
+    let remaining = handshake_timeout.saturating_sub(started.elapsed());
+    let (sink, source) =
+        match ractor::concurrency::timeout(remaining, accept_streams(&connection)).await {
+            Ok(Ok(conduit)) => conduit,
+            Ok(Err(e)) => {
+                error!("Error accepting the QUIC streams from {addr}: {e}");
+                connection.close(0u32.into(), b"protocol error");
+                return None;
+            }
+            Err(_) => {
+                warn!("{addr} didn't open its QUIC streams in time");
+                connection.close(0u32.into(), b"handshake timeout");
+                return None;
+            }
+        };
+
+    let context = ConnectionContext {
+        remote_addr: Some(addr),
+        peer_certificates: peer_certificates(&connection),
+        ..Default::default()
+    };
+
+    let portal_identifier = format!("quic://{addr}");
+    match conduit::from_sink_source_with_context(nexus, portal_identifier, context, sink, source)
+        .await
+    {
+        Ok(portal) => Some(portal),
+        Err(err) => {
+            error!("Error creating portal: {err}");
+            None
+        }
+    }
+}
+
+pub async fn connect_to_server(
+    nexus: ActorRef<NexusActorMessage>,
+    endpoint: &Endpoint,
+    addr: SocketAddr,
+    server_name: &str,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error> {
+    info!("Connecting to QUIC server at: {addr}");
+
+    let connection = endpoint.connect(addr, server_name)?.await?;
+    info!("QUIC connection established to: {addr}");
+
+    let (sink, source) = open_streams(&connection).await?;
+
+    let context = ConnectionContext {
+        remote_addr: Some(addr),
+        peer_certificates: peer_certificates(&connection),
+        ..Default::default()
+    };
+
+    let portal_identifier = format!("quic://{server_name}@{addr}");
+    conduit::from_sink_source_with_context(nexus, portal_identifier, context, sink, source).await
+}
+
+/// the certificate chain the peer authenticated with, if any
+fn peer_certificates(connection: &Connection) -> Vec<Vec<u8>> {
+    connection
+        .peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+        .map(|certs| certs.iter().map(|cert| cert.to_vec()).collect())
+        .unwrap_or_default()
+}
+
+// ---------------------------------------------------------------------------------
+
+/// the client side opens both streams. Each stream starts with a single byte which identifies it.
+pub async fn open_streams(
+    connection: &Connection,
+) -> Result<(ConduitSink, ConduitSource), ConduitError> {
+    let (mut control_tx, control_rx) = connection.open_bi().await?;
+    control_tx.write_all(&[STREAM_CONTROL]).await?;
+
+    let (mut bulk_tx, bulk_rx) = connection.open_bi().await?;
+    bulk_tx.write_all(&[STREAM_BULK]).await?;
+
+    Ok(map_streams_to_conduit(
+        connection.clone(),
+        (control_tx, control_rx),
+        (bulk_tx, bulk_rx),
+    ))
+}
+
+/// the server side accepts both streams opened by `open_streams`.
+pub async fn accept_streams(
+    connection: &Connection,
+) -> Result<(ConduitSink, ConduitSource), ConduitError> {
+    let mut control = None;
+    let mut bulk = None;
+
+    while control.is_none() || bulk.is_none() {
+        let (tx, mut rx) = connection.accept_bi().await?;
+        let mut kind = [0u8];
+        rx.read_exact(&mut kind).await?;
+
+        let slot = match kind[0] {
+            STREAM_CONTROL => &mut control,
+            STREAM_BULK => &mut bulk,
+            other => return Err(anyhow::anyhow!("Unknown QUIC stream kind: {other}")),
+        };
+        if slot.replace((tx, rx)).is_some() {
+            return Err(anyhow::anyhow!("QUIC stream kind {} opened twice", kind[0]));
+        }
+    }
+
+    Ok(map_streams_to_conduit(
+        connection.clone(),
+        control.unwrap(),
+        bulk.unwrap(),
+    ))
+}
+
+struct QuicSinkState {
+    connection: Connection,
+    control: SendStream,
+    bulk: SendStream,
+}
+
+fn map_streams_to_conduit(
+    connection: Connection,
+    control: (SendStream, RecvStream),
+    bulk: (SendStream, RecvStream),
+) -> (ConduitSink, ConduitSource) {
+    let (control_tx, control_rx) = control;
+    let (bulk_tx, bulk_rx) = bulk;
+
+    let state = QuicSinkState {
+        connection,
+        control: control_tx,
+        bulk: bulk_tx,
+    };
+
+    let sink = futures::sink::unfold(state, |mut state, msg: ConduitMessage| async move {
+        match msg {
+            ConduitMessage::Text(text) => {
+                write_frame(&mut state.control, FRAME_TEXT, text.as_bytes()).await?;
+            }
+            ConduitMessage::Binary(data) => {
+                write_frame(&mut state.control, FRAME_BINARY, &data).await?;
+            }
+            ConduitMessage::Bulk(data) => {
+                write_frame(&mut state.bulk, FRAME_BINARY, &data).await?;
+            }
+            ConduitMessage::Close(reason) => {
+                match &reason {
+                    Some(reason) => {
+                        write_frame(
+                            &mut state.control,
+                            FRAME_CLOSE_WITH_REASON,
+                            &reason.to_bytes(),
+                        )
+                        .await?
+                    }
+                    None => write_frame(&mut state.control, FRAME_CLOSE, &[]).await?,
+                }
+                let _ = state.control.finish();
+                let _ = state.bulk.finish();
+                info!(
+                    "Closing QUIC connection to {}: {reason:?}",
+                    state.connection.remote_address()
+                );
+            }
+        }
+        Ok::<_, ConduitError>(state)
+    });
+
+    // both streams are read by their own task and merged into one channel.
+    // Messages on the bulk stream must not overtake the introduction on the control stream,
+    //  so the bulk stream is only read after the first control frame has been forwarded.
+    let (tx, rx) = mpsc::unbounded();
+    let (first_frame_tx, first_frame_rx) = oneshot::channel();
+
+    ractor::concurrency::spawn(read_loop(control_rx, tx.clone(), Some(first_frame_tx)));
+    ractor::concurrency::spawn(async move {
+        if first_frame_rx.await.is_ok() {
+            read_loop(bulk_rx, tx, None).await;
+        }
+    });
+
+    (Box::pin(sink), Box::pin(rx))
+}
+
+async fn read_loop(
+    mut stream: RecvStream,
+    tx: mpsc::UnboundedSender<Result<ConduitMessage, ConduitError>>,
+    mut on_first_frame: Option<oneshot::Sender<()>>,
+) {
+    loop {
+        match read_frame(&mut stream).await {
+            Ok(Some(msg)) => {
+                if tx.unbounded_send(Ok(msg)).is_err() {
+                    break;
+                }
+                if let Some(on_first_frame) = on_first_frame.take() {
+                    let _ = on_first_frame.send(());
+                }
+            }
+            // the stream was finished by the remote side
+            Ok(None) => break,
+            Err(e) => {
+                let _ = tx.unbounded_send(Err(e));
+                break;
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------
+
+/// the framing is: kind:u8 + length:u32 + payload
+async fn write_frame(
+    stream: &mut SendStream,
+    kind: u8,
+    payload: &[u8],
+) -> Result<(), ConduitError> {
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(anyhow::anyhow!(
+            "Frame too large: {} bytes, the maximum is {MAX_FRAME_SIZE}",
+            payload.len()
+        ));
+    }
+
+    let mut header = [0u8; 5];
+    header[0] = kind;
+    header[1..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+    stream.write_all(&header).await?;
+    stream.write_all(payload).await?;
+    Ok(())
+}
+
+/// returns None if the stream was finished cleanly before the start of a frame.
+async fn read_frame(stream: &mut RecvStream) -> Result<Option<ConduitMessage>, ConduitError> {
+    let mut kind = [0u8];
+    match stream.read(&mut kind).await? {
+        None => return Ok(None),
+        Some(0) => return Err(anyhow::anyhow!("Unexpected empty read on QUIC stream")),
+        Some(_) => {}
+    }
+
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length).await?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(anyhow::anyhow!(
+            "Frame too large: {length} bytes, the maximum is {MAX_FRAME_SIZE}"
+        ));
+    }
+
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload).await?;
+
+    let msg = match kind[0] {
+        FRAME_TEXT => ConduitMessage::Text(String::from_utf8(payload)?),
+        FRAME_BINARY => ConduitMessage::Binary(payload),
+        FRAME_CLOSE => ConduitMessage::Close(None),
+        FRAME_CLOSE_WITH_REASON => ConduitMessage::Close(Some(CloseReason::from_bytes(&payload)?)),
+        other => return Err(anyhow::anyhow!("Unknown QUIC frame kind: {other}")),
+    };
+    Ok(Some(msg))
+}
@@ -0,0 +1,255 @@
+//! Keeps a client connected: when the connection is lost, it is re-established with exponential backoff.
+//!
+//! A new connection means a new portal, so all proxies for remote actors of the old portal are dead.
+//! The bootstrap closure is run after every successful (re)connect to set everything up again,
+//! e.g. to query named actors on the remote side.
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use futures::{channel::oneshot, future};
+use log::{info, warn};
+use ractor::{
+    ActorRef,
+    concurrency::{Duration, Instant},
+};
+
+use crate::{
+    conduit::{CloseCode, CloseReason},
+    portal::{Portal, PortalActorMessage},
+    util::MaybeSend,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+pub struct ReconnectConfig {
+    /// the delay before reconnecting after a connection was lost
+    pub initial_delay: Duration,
+    /// the delay doesn't grow beyond this
+    pub max_delay: Duration,
+    /// the delay is multiplied by this after every failed attempt
+    pub multiplier: f64,
+    /// the delay is randomized by up to ± this fraction,
+    /// so that all clients don't reconnect at the same moment after a server restart.
+    pub jitter: f64,
+    /// give up after this many failed attempts in a row. None retries forever.
+    pub max_attempts: Option<u32>,
+    /// a connection which is lost within this time after it was opened counts as a failed attempt,
+    /// so a server which accepts and immediately drops the client doesn't cause a reconnect loop without backoff.
+    pub min_uptime: Duration,
+    /// the time the portal may take to complete its handshake, before the attempt counts as failed
+    pub open_timeout: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+            min_uptime: Duration::from_secs(5),
+            open_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// the delay before the next attempt, after `failures` failed attempts in a row.
+    pub fn delay(&self, failures: u32) -> Duration {
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(failures as i32);
+        let delay = delay.min(self.max_delay.as_secs_f64());
+        let jitter = if self.jitter > 0.0 {
+            rand::random_range(-self.jitter..=self.jitter)
+        } else {
+            0.0
+        };
+        Duration::from_secs_f64((delay * (1.0 + jitter)).max(0.0))
+    }
+}
+
+#[cfg_attr(
+    feature = "ractor_cluster",
+    derive(ractor_cluster_derive::RactorMessage)
+)]
+#[derive(Debug, Clone)]
+pub enum ConnectionState {
+    /// a connection attempt is started. `attempt` counts the failed attempts in a row, starting at 1.
+    Connecting { attempt: u32 },
+    /// the portal is open and the bootstrap closure has completed.
+    Open(ActorRef<PortalActorMessage>),
+    /// the connection (or a connection attempt) failed, it will be retried.
+    Lost(String),
+    /// `max_attempts` was reached (or the handle was stopped), there will be no further attempts.
+    GaveUp(String),
+}
+
+/// a handle to the reconnecting task. Dropping it does **not** stop the task, use `stop` for that.
+#[derive(Clone)]
+pub struct ReconnectHandle {
+    current: Arc<Mutex<Option<ActorRef<PortalActorMessage>>>>,
+    stop: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+}
+
+impl ReconnectHandle {
+    /// the currently open portal, if any.
+    pub fn portal(&self) -> Option<ActorRef<PortalActorMessage>> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// stops reconnecting and closes the current portal. A connection attempt which is in progress is abandoned.
+    pub fn stop(&self) {
+        if let Some(stop) = self.stop.lock().unwrap().take() {
+            let _ = stop.send(());
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// connects using `connect` and keeps reconnecting whenever the connection is lost.
+///
+/// `bootstrap` is run after every successful connect, before the portal is reported as `Open`.
+/// If it fails, the connection is closed and retried. It should not wait forever (e.g. use timeouts for `ask`),
+/// because the next attempt only starts once it has returned.
+pub fn start_reconnecting<C, CFut, B, BFut>(
+    connect: C,
+    bootstrap: B,
+    config: ReconnectConfig,
+    on_state: Option<ActorRef<ConnectionState>>,
+) -> ReconnectHandle
+where
+    C: Fn() -> CFut + MaybeSend + 'static,
+    CFut: Future<Output = Result<ActorRef<PortalActorMessage>, anyhow::Error>> + MaybeSend,
+    B: Fn(ActorRef<PortalActorMessage>) -> BFut + MaybeSend + 'static,
+    BFut: Future<Output = Result<(), anyhow::Error>> + MaybeSend,
+{
+    let (stop_tx, mut stop_rx) = oneshot::channel();
+    let handle = ReconnectHandle {
+        current: Arc::new(Mutex::new(None)),
+        stop: Arc::new(Mutex::new(Some(stop_tx))),
+    };
+
+    let emit = move |state: ConnectionState| {
+        if let Some(on_state) = &on_state {
+            let _ = on_state.send_message(state);
+        }
+    };
+
+    let task_handle = handle.clone();
+    ractor::concurrency::spawn(async move {
+        // keeps the stop sender alive, even if the user drops all handles
+        let handle = task_handle;
+        let mut failures = 0u32;
+
+        let reason = loop {
+            emit(ConnectionState::Connecting {
+                attempt: failures + 1,
+            });
+
+            // note: the futures are created in their own statement, so no reference to the closures is held across an await
+            let connecting = connect();
+            let Some(connected) = until_stopped(connecting, &mut stop_rx).await else {
+                break "Stopped".to_string();
+            };
+            let attempt = match connected {
+                Ok(portal) => {
+                    let bootstrapping = bootstrap(portal.clone());
+                    let opened = portal.clone();
+                    let open_timeout = config.open_timeout;
+                    let opening = async move {
+                        opened.wait_for_opened(open_timeout).await?;
+                        bootstrapping.await
+                    };
+                    match until_stopped(opening, &mut stop_rx).await {
+                        Some(Ok(())) => Ok(portal),
+                        Some(Err(err)) => {
+                            disconnect(&portal, "Bootstrap failed");
+                            Err(err)
+                        }
+                        None => {
+                            disconnect(&portal, "Client stopped");
+                            break "Stopped".to_string();
+                        }
+                    }
+                }
+                Err(err) => Err(err),
+            };
+
+            let lost = match attempt {
+                Ok(portal) => {
+                    let opened_at = Instant::now();
+                    *handle.current.lock().unwrap() = Some(portal.clone());
+                    emit(ConnectionState::Open(portal.clone()));
+
+                    let watched = portal.clone();
+                    let closed = until_stopped(watched.wait_for_closed(), &mut stop_rx).await;
+                    *handle.current.lock().unwrap() = None;
+
+                    // only a connection which stayed up for a while resets the backoff
+                    if opened_at.elapsed() >= config.min_uptime {
+                        failures = 0;
+                    } else {
+                        failures += 1;
+                    }
+
+                    match closed {
+                        None => {
+                            disconnect(&portal, "Client stopped");
+                            break "Stopped".to_string();
+                        }
+                        Some(Ok(Some(reason))) => format!("Connection closed: {reason}"),
+                        // the portal was already gone, or closed without a reason
+                        Some(_) => "Connection closed".to_string(),
+                    }
+                }
+                Err(err) => {
+                    failures += 1;
+                    format!("Connection attempt failed: {err}")
+                }
+            };
+
+            if let Some(max_attempts) = config.max_attempts
+                && failures >= max_attempts
+            {
+                break format!(
+                    "Giving up after {failures} failed attempts, the last error was: {lost}"
+                );
+            }
+
+            warn!("{lost}, reconnecting");
+            emit(ConnectionState::Lost(lost));
+
+            let delay = ractor::concurrency::sleep(config.delay(failures));
+            if until_stopped(delay, &mut stop_rx).await.is_none() {
+                break "Stopped".to_string();
+            }
+        };
+
+        info!("Stopped reconnecting: {reason}");
+        emit(ConnectionState::GaveUp(reason));
+    });
+
+    handle
+}
+
+/// runs the future, unless `stop` fires first.
+async fn until_stopped<F: Future>(
+    future: F,
+    stop: &mut oneshot::Receiver<()>,
+) -> Option<F::Output> {
+    match future::select(Box::pin(future), stop).await {
+        future::Either::Left((output, _)) => Some(output),
+        future::Either::Right(_) => None,
+    }
+}
+
+fn disconnect(portal: &ActorRef<PortalActorMessage>, reason: &str) {
+    let _ = portal.send_message(PortalActorMessage::Disconnect(Some(CloseReason::new(
+        CloseCode::Normal,
+        reason,
+    ))));
+}
@@ -0,0 +1,332 @@
+//! A conduit over a byte stream, e.g. a serial port (UART) to a microcontroller.
+//!
+//! It works with anything implementing `AsyncRead + AsyncWrite`: a serial port, a pty, a pipe, stdio, a tcp socket ...
+//!
+//! Every frame is `kind:u8 + payload + crc32:u32`, COBS encoded and delimited by a zero byte.
+//! COBS guarantees that the encoded frame contains no zero bytes, so after line noise the receiver simply
+//! drops everything up to the next delimiter; frames with a wrong checksum are dropped, too.
+//!
+//! A byte stream is usually slow, so the portal sends the compact binary `Introduction` instead of json.
+
+use std::collections::VecDeque;
+
+use log::{info, warn};
+use ractor::ActorRef;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    conduit::{
+        self, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+        ConnectionContext,
+    },
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+const FRAME_TEXT: u8 = 0;
+const FRAME_BINARY: u8 = 1;
+const FRAME_CLOSE: u8 = 2;
+const FRAME_CLOSE_WITH_REASON: u8 = 3;
+
+const DELIMITER: u8 = 0;
+
+/// frames larger than this (before encoding) are rejected.
+/// The receiver drops longer frames, so a missing delimiter can't make it buffer forever.
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+// -------------------------------------------------------------------------------------------------------
+
+/// opens a portal over the byte stream.
+/// Both sides of a serial connection are equal, there is no client or server.
+pub async fn from_stream<S>(
+    nexus: ActorRef<NexusActorMessage>,
+    identifier: String,
+    stream: S,
+) -> Result<ActorRef<PortalActorMessage>, ConduitError>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (sink, source) = map_stream_to_conduit(stream);
+
+    let context = ConnectionContext {
+        binary_introduction: true,
+        ..Default::default()
+    };
+    conduit::from_sink_source_with_context(nexus, identifier, context, sink, source).await
+}
+
+struct SerialSourceState<R> {
+    reader: R,
+    decoder: FrameDecoder,
+    buffer: Box<[u8; 1024]>,
+    pending: VecDeque<ConduitMessage>,
+    failed: bool,
+}
+
+pub fn map_stream_to_conduit<S>(stream: S) -> (ConduitSink, ConduitSource)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+
+    let sink = futures::sink::unfold(writer, |mut writer, msg: ConduitMessage| async move {
+        let frame = match &msg {
+            ConduitMessage::Text(text) => encode_frame(FRAME_TEXT, text.as_bytes())?,
+            ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => {
+                encode_frame(FRAME_BINARY, data)?
+            }
+            ConduitMessage::Close(Some(reason)) => {
+                encode_frame(FRAME_CLOSE_WITH_REASON, &reason.to_bytes())?
+            }
+            ConduitMessage::Close(None) => encode_frame(FRAME_CLOSE, &[])?,
+        };
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+
+        if let ConduitMessage::Close(reason) = msg {
+            info!("Closing serial connection: {reason:?}");
+            let _ = writer.shutdown().await;
+        }
+        Ok::<_, ConduitError>(writer)
+    });
+
+    let state = SerialSourceState {
+        reader,
+        decoder: FrameDecoder::new(MAX_FRAME_SIZE),
+        buffer: Box::new([0u8; 1024]),
+        pending: VecDeque::new(),
+        failed: false,
+    };
+    let source = futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(msg) = state.pending.pop_front() {
+                return Some((Ok(msg), state));
+            }
+            if state.failed {
+                return None;
+            }
+
+            match state.reader.read(&mut state.buffer[..]).await {
+                // end of stream
+                Ok(0) => return None,
+                Ok(n) => {
+                    for &byte in &state.buffer[..n] {
+                        if let Some(msg) = state.decoder.push(byte) {
+                            state.pending.push_back(msg);
+                        }
+                    }
+                }
+                Err(e) => {
+                    state.failed = true;
+                    return Some((Err(e.into()), state));
+                }
+            }
+        }
+    });
+
+    (Box::pin(sink), Box::pin(source))
+}
+
+// ---------------------------------------------------------------------------------
+
+/// encodes a complete frame, including the delimiters.
+fn encode_frame(kind: u8, payload: &[u8]) -> Result<Vec<u8>, ConduitError> {
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(anyhow::anyhow!(
+            "Frame too large: {} bytes, the maximum is {MAX_FRAME_SIZE}",
+            payload.len()
+        ));
+    }
+
+    let mut raw = Vec::with_capacity(payload.len() + 5);
+    raw.push(kind);
+    raw.extend_from_slice(payload);
+    let crc = crc32fast::hash(&raw);
+    raw.extend_from_slice(&crc.to_le_bytes());
+
+    // the leading delimiter costs one byte, but line noise before this frame can't corrupt it.
+    let mut frame = Vec::with_capacity(raw.len() + raw.len() / 254 + 3);
+    frame.push(DELIMITER);
+    cobs_encode(&raw, &mut frame);
+    frame.push(DELIMITER);
+    Ok(frame)
+}
+
+/// splits the received bytes at the delimiters and decodes the frames in between.
+/// Corrupted frames are dropped (and logged), the decoder continues with the next frame.
+struct FrameDecoder {
+    buffer: Vec<u8>,
+    max_frame_size: usize,
+    /// the current frame is too large, everything up to the next delimiter is dropped
+    overflowed: bool,
+}
+
+impl FrameDecoder {
+    fn new(max_frame_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_frame_size,
+            overflowed: false,
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> Option<ConduitMessage> {
+        if byte != DELIMITER {
+            // COBS adds at most one byte per 254, plus the kind and the checksum
+            if self.buffer.len() > self.max_frame_size + self.max_frame_size / 254 + 6 {
+                if !self.overflowed {
+                    warn!("Serial frame too large, dropping it");
+                    self.overflowed = true;
+                }
+                self.buffer.clear();
+            } else if !self.overflowed {
+                self.buffer.push(byte);
+            }
+            return None;
+        }
+
+        let overflowed = std::mem::replace(&mut self.overflowed, false);
+        let encoded = std::mem::take(&mut self.buffer);
+        // two delimiters in a row
+        if encoded.is_empty() || overflowed {
+            return None;
+        }
+
+        match decode_frame(&encoded) {
+            Ok(msg) => Some(msg),
+            Err(err) => {
+                warn!(
+                    "Dropping corrupted serial frame ({} bytes): {err}",
+                    encoded.len()
+                );
+                None
+            }
+        }
+    }
+}
+
+fn decode_frame(encoded: &[u8]) -> Result<ConduitMessage, ConduitError> {
+    let raw = cobs_decode(encoded).ok_or_else(|| anyhow::anyhow!("Invalid COBS encoding"))?;
+    if raw.len() < 5 {
+        return Err(anyhow::anyhow!("Frame too short: {} bytes", raw.len()));
+    }
+
+    let (content, crc) = raw.split_at(raw.len() - 4);
+    let crc = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+    if crc32fast::hash(content) != crc {
+        return Err(anyhow::anyhow!("Checksum mismatch"));
+    }
+
+    let (kind, payload) = (content[0], &content[1..]);
+    let msg = match kind {
+        FRAME_TEXT => ConduitMessage::Text(String::from_utf8(payload.to_vec())?),
+        FRAME_BINARY => ConduitMessage::Binary(payload.to_vec()),
+        FRAME_CLOSE => ConduitMessage::Close(None),
+        FRAME_CLOSE_WITH_REASON => ConduitMessage::Close(Some(CloseReason::from_bytes(payload)?)),
+        other => return Err(anyhow::anyhow!("Unknown serial frame kind: {other}")),
+    };
+    Ok(msg)
+}
+
+/// Consistent Overhead Byte Stuffing: replaces all zero bytes, so that zero can be used as the frame delimiter.
+fn cobs_encode(data: &[u8], out: &mut Vec<u8>) {
+    let mut code_index = out.len();
+    let mut code = 1u8;
+    out.push(0);
+
+    for &byte in data {
+        if byte != 0 {
+            out.push(byte);
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_index] = code;
+            code_index = out.len();
+            code = 1;
+            out.push(0);
+        }
+    }
+    out[code_index] = code;
+}
+
+fn cobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+
+    while i < data.len() {
+        let code = data[i] as usize;
+        if code == 0 || i + code > data.len() {
+            return None;
+        }
+        out.extend_from_slice(&data[i + 1..i + code]);
+        i += code;
+        // a block shorter than the maximum was followed by a zero, unless it is the last one
+        if code < 0xFF && i < data.len() {
+            out.push(0);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::conduit::CloseCode;
+
+    fn decode_all(decoder: &mut FrameDecoder, bytes: &[u8]) -> Vec<ConduitMessage> {
+        bytes.iter().filter_map(|b| decoder.push(*b)).collect()
+    }
+
+    #[test]
+    fn test_cobs_roundtrip() {
+        let cases: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0],
+            vec![0, 0],
+            vec![1, 2, 0, 3],
+            (1..=254).collect(),
+            (0..=255).collect(),
+            (0..1000).map(|i| (i % 7) as u8).collect(),
+        ];
+        for case in cases {
+            let mut encoded = Vec::new();
+            cobs_encode(&case, &mut encoded);
+            assert!(!encoded.contains(&0));
+            assert_eq!(cobs_decode(&encoded), Some(case));
+        }
+    }
+
+    #[test]
+    fn test_resync_after_noise() {
+        let mut decoder = FrameDecoder::new(MAX_FRAME_SIZE);
+
+        let mut bytes = vec![0x13, 0x37, 0xff];
+        bytes.extend(encode_frame(FRAME_BINARY, &[1, 0, 2]).unwrap());
+        // a frame with a flipped bit
+        let mut corrupted = encode_frame(FRAME_TEXT, b"hello").unwrap();
+        corrupted[4] ^= 0x10;
+        bytes.extend(corrupted);
+        bytes.extend([0x42, 0x00, 0x42]);
+        bytes.extend(encode_frame(FRAME_TEXT, b"world").unwrap());
+
+        let msgs = decode_all(&mut decoder, &bytes);
+        assert_eq!(msgs.len(), 2);
+        assert!(matches!(&msgs[0], ConduitMessage::Binary(data) if data == &[1, 0, 2]));
+        assert!(matches!(&msgs[1], ConduitMessage::Text(text) if text == "world"));
+    }
+
+    #[test]
+    fn test_oversized_frame_is_dropped() {
+        let mut decoder = FrameDecoder::new(16);
+
+        let mut bytes = vec![0x55; 100];
+        let reason = CloseReason::new(CloseCode::GoingAway, "bye");
+        bytes.extend(encode_frame(FRAME_CLOSE_WITH_REASON, &reason.to_bytes()).unwrap());
+
+        let msgs = decode_all(&mut decoder, &bytes);
+        assert_eq!(msgs.len(), 1);
+        assert!(matches!(&msgs[0], ConduitMessage::Close(Some(received)) if received == &reason));
+    }
+}
@@ -0,0 +1,205 @@
+//! The bookkeeping of servers which accept the connections themselves (the websocket server in
+//! `websocket::server::tokio_tungstenite` and the QUIC server): connection limits, and a handle to shut them down.
+
+use futures::{channel::oneshot, future};
+use log::{info, warn};
+use ractor::{ActorRef, concurrency::Duration};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    conduit::{CloseCode, CloseReason},
+    portal::PortalActorMessage,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+/// a handle to a running server. Dropping it does **not** stop the server, use `shutdown` for that.
+#[derive(Clone)]
+pub struct ServerHandle {
+    /// e.g. "WebSocket", only used for logging
+    kind: &'static str,
+    local_addr: SocketAddr,
+    shared: Arc<ServerShared>,
+}
+
+pub(crate) struct ServerShared {
+    stop_accepting: Mutex<Option<oneshot::Sender<()>>>,
+    connections: Mutex<Connections>,
+}
+
+#[derive(Default)]
+struct Connections {
+    shutting_down: bool,
+    next_id: u64,
+    active: HashMap<u64, (IpAddr, Option<ActorRef<PortalActorMessage>>)>,
+}
+
+impl ServerHandle {
+    pub(crate) fn new(
+        kind: &'static str,
+        local_addr: SocketAddr,
+        shared: Arc<ServerShared>,
+    ) -> Self {
+        Self {
+            kind,
+            local_addr,
+            shared,
+        }
+    }
+
+    /// the address the server is listening on. Useful when binding to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// the number of open connections, including connections which are still in the handshake.
+    pub fn connection_count(&self) -> usize {
+        self.shared.connections.lock().unwrap().active.len()
+    }
+
+    /// the portals of all open connections.
+    pub fn portals(&self) -> Vec<ActorRef<PortalActorMessage>> {
+        let connections = self.shared.connections.lock().unwrap();
+        connections
+            .active
+            .values()
+            .filter_map(|(_, portal)| portal.clone())
+            .collect()
+    }
+
+    /// stops accepting new connections, then closes all portals of this server with the given reason (and `CloseCode::GoingAway`)
+    /// and waits (up to `timeout` for all of them together) until they have stopped.
+    pub async fn shutdown(&self, reason: impl Into<String>, timeout: Duration) {
+        let reason = reason.into();
+        info!(
+            "Shutting down {} server on {}: {reason}",
+            self.kind, self.local_addr
+        );
+
+        if let Some(stop_accepting) = self.shared.stop_accepting.lock().unwrap().take() {
+            let _ = stop_accepting.send(());
+        }
+
+        let portals = {
+            let mut connections = self.shared.connections.lock().unwrap();
+            connections.shutting_down = true;
+            connections
+                .active
+                .values()
+                .filter_map(|(_, portal)| portal.clone())
+                .collect::<Vec<_>>()
+        };
+
+        for portal in &portals {
+            let _ = portal.send_message(PortalActorMessage::Disconnect(Some(CloseReason::new(
+                CloseCode::GoingAway,
+                reason.clone(),
+            ))));
+        }
+        // all portals close in parallel, so the timeout is for all of them together
+        let cells: Vec<_> = portals.iter().map(|portal| portal.get_cell()).collect();
+        let stopped = future::join_all(cells.iter().map(|cell| cell.wait(None)));
+        if ractor::concurrency::timeout(timeout, stopped)
+            .await
+            .is_err()
+        {
+            warn!(
+                "Not all portals of the {} server on {} stopped within {timeout:?}",
+                self.kind, self.local_addr
+            );
+        }
+    }
+}
+
+/// reserves a place for a connection in the server, it is released on drop.
+pub(crate) struct ConnectionSlot {
+    id: u64,
+    shared: Arc<ServerShared>,
+}
+
+impl ServerShared {
+    /// the receiver completes when the server should stop accepting connections.
+    pub(crate) fn new() -> (Arc<Self>, oneshot::Receiver<()>) {
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let shared = Arc::new(Self {
+            stop_accepting: Mutex::new(Some(stop_tx)),
+            connections: Mutex::new(Connections::default()),
+        });
+        (shared, stop_rx)
+    }
+
+    pub(crate) fn try_admit(
+        self: &Arc<Self>,
+        ip: IpAddr,
+        max_connections: Option<usize>,
+        max_connections_per_ip: Option<usize>,
+    ) -> Option<ConnectionSlot> {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.shutting_down {
+            return None;
+        }
+        if let Some(max) = max_connections
+            && connections.active.len() >= max
+        {
+            warn!("Refusing connection from {ip}: the server is at its limit of {max} connections");
+            return None;
+        }
+        if let Some(max) = max_connections_per_ip
+            && connections
+                .active
+                .values()
+                .filter(|(other, _)| *other == ip)
+                .count()
+                >= max
+        {
+            warn!("Refusing connection from {ip}: it already has {max} connections");
+            return None;
+        }
+
+        let id = connections.next_id;
+        connections.next_id += 1;
+        connections.active.insert(id, (ip, None));
+        Some(ConnectionSlot {
+            id,
+            shared: self.clone(),
+        })
+    }
+}
+
+impl ConnectionSlot {
+    /// returns false if the server is shutting down, in which case the portal must be closed.
+    fn set_portal(&self, portal: ActorRef<PortalActorMessage>) -> bool {
+        let mut connections = self.shared.connections.lock().unwrap();
+        if let Some((_, slot)) = connections.active.get_mut(&self.id) {
+            *slot = Some(portal);
+        }
+        !connections.shutting_down
+    }
+
+    /// registers the portal of the connection, and keeps the slot until the portal is closed.
+    pub(crate) async fn hold(self, portal: ActorRef<PortalActorMessage>) {
+        if !self.set_portal(portal.clone()) {
+            let _ = portal.send_message(PortalActorMessage::Disconnect(Some(CloseReason::new(
+                CloseCode::GoingAway,
+                "Server is shutting down",
+            ))));
+        }
+
+        let _ = portal.get_cell().wait(None).await;
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.shared
+            .connections
+            .lock()
+            .unwrap()
+            .active
+            .remove(&self.id);
+    }
+}
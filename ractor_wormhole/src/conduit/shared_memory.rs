@@ -0,0 +1,661 @@
+//! A conduit between two processes on the same host, over a memory-mapped file.
+//!
+//! The file contains two ring buffers, one per direction. Each ring is a byte pipe: the writer copies the frame into the ring
+//! and advances `head`, the reader copies it out and advances `tail`. Frames larger than the ring are streamed through it in pieces.
+//! A side which has nothing to do spins briefly, then sleeps on a futex in the shared memory until the other side wakes it.
+//!
+//! One side creates the file with `create`, the other side attaches to it with `connect`.
+//! Put the file on a tmpfs (e.g. `/dev/shm`), so the pages are never written back to disk. The file is not removed automatically.
+//!
+//! Each side holds a lock on one byte of the file (an open file description lock, which the kernel releases when the process dies),
+//! so a side can tell whether the other one is still alive, even if it crashed without closing the rings.
+//!
+//! Every frame is `len:u32 + kind:u8 + payload`.
+
+use std::{
+    fs::{File, OpenOptions},
+    os::fd::AsRawFd,
+    path::Path,
+    ptr,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::{SinkExt, StreamExt, channel::mpsc};
+use log::{error, info};
+use memmap2::MmapMut;
+use ractor::ActorRef;
+
+use crate::{
+    conduit::{
+        self, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+        ConnectionContext,
+    },
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+const FRAME_TEXT: u8 = 0;
+const FRAME_BINARY: u8 = 1;
+const FRAME_CLOSE: u8 = 2;
+const FRAME_CLOSE_WITH_REASON: u8 = 3;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"wormhole");
+
+/// the default size of each ring
+pub const DEFAULT_CAPACITY: usize = 4 * 1024 * 1024;
+
+/// how often a side checks the ring before it goes to sleep
+const SPIN_COUNT: u32 = 256;
+/// the sleeping side wakes up this often, to check the lock of the peer (see `Mapping::is_peer_alive`)
+const FUTEX_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// the largest frame which is accepted from the peer
+pub const MAX_FRAME_SIZE: usize = 64 << 20;
+
+/// how many frames are buffered between the portal and the thread which copies them into (or out of) the ring
+const CHANNEL_SIZE: usize = 64;
+
+// -------------------------------------------------------------------------------------------------------
+
+#[repr(C, align(64))]
+struct CachePadded<T>(T);
+
+#[repr(C)]
+struct FileHeader {
+    magic: CachePadded<AtomicU64>,
+    /// the size of each ring, a power of two
+    capacity: AtomicU64,
+    /// set by the side which connects, so a second one is rejected
+    connected: AtomicU32,
+}
+
+#[repr(C)]
+struct RingHeader {
+    /// bytes written so far, only advanced by the writer
+    head: CachePadded<AtomicU64>,
+    /// bytes read so far, only advanced by the reader
+    tail: CachePadded<AtomicU64>,
+    /// futex word, bumped by the writer after it advanced `head`
+    data_seq: CachePadded<AtomicU32>,
+    /// futex word, bumped by the reader after it advanced `tail`
+    space_seq: CachePadded<AtomicU32>,
+    /// set while the reader sleeps on `data_seq`
+    data_waiting: AtomicU32,
+    /// set while the writer sleeps on `space_seq`
+    space_waiting: AtomicU32,
+    /// set once either side is done with the ring. The reader still drains what is left.
+    closed: AtomicU32,
+}
+
+const fn align_up(value: usize) -> usize {
+    value.next_multiple_of(64)
+}
+
+const RING_HEADER_OFFSET: usize = align_up(size_of::<FileHeader>());
+const DATA_OFFSET: usize = align_up(RING_HEADER_OFFSET + 2 * size_of::<RingHeader>());
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the side which created the file holds the lock on the first byte, the side which connected on the second one
+const CREATOR: u64 = 0;
+const CONNECTOR: u64 = 1;
+
+/// the memory-mapped file, shared by both rings of one side.
+struct Mapping {
+    base: *mut u8,
+    _map: MmapMut,
+    /// keeps the lock of this side (see `lock`)
+    file: File,
+    side: u64,
+}
+
+// SAFETY: `base` points into the mapping, which lives as long as this struct.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn file_header(&self) -> &FileHeader {
+        // SAFETY: the mapping is larger than the header, page aligned, and the header only consists of atomics.
+        unsafe { &*(self.base as *const FileHeader) }
+    }
+
+    /// false once the other side has gone away, i.e. its lock on the file has been released.
+    /// Always true for the creating side, as long as nobody has connected.
+    fn is_peer_alive(&self) -> bool {
+        if self.side == CREATOR && self.file_header().connected.load(Ordering::SeqCst) == 0 {
+            return true;
+        }
+        let peer = match self.side {
+            CREATOR => CONNECTOR,
+            _ => CREATOR,
+        };
+        let mut lock = file_lock(libc::F_WRLCK, peer);
+        // SAFETY: `lock` is a valid flock struct, which the kernel fills in
+        let result = unsafe { libc::fcntl(self.file.as_raw_fd(), libc::F_OFD_GETLK, &mut lock) };
+        // if the check itself fails, the peer is assumed to be alive
+        result != 0 || lock.l_type != libc::F_UNLCK as libc::c_short
+    }
+
+    fn ring(self: &Arc<Self>, index: usize) -> Ring {
+        let capacity = self.file_header().capacity.load(Ordering::Acquire) as usize;
+        // SAFETY: the file is at least `DATA_OFFSET + 2 * capacity` bytes long
+        unsafe {
+            Ring {
+                header: self
+                    .base
+                    .add(RING_HEADER_OFFSET + index * size_of::<RingHeader>())
+                    as *const RingHeader,
+                data: self.base.add(DATA_OFFSET + index * capacity),
+                capacity: capacity as u64,
+                mapping: self.clone(),
+            }
+        }
+    }
+}
+
+/// one direction of the conduit.
+struct Ring {
+    header: *const RingHeader,
+    data: *mut u8,
+    capacity: u64,
+    mapping: Arc<Mapping>,
+}
+
+// SAFETY: the pointers stay valid as long as the mapping is alive, which the ring keeps alive.
+// All shared state is accessed through atomics; the data is only written by the one writer, in the part which is free.
+unsafe impl Send for Ring {}
+
+impl Ring {
+    fn header(&self) -> &RingHeader {
+        unsafe { &*self.header }
+    }
+
+    fn close(&self) {
+        let header = self.header();
+        header.closed.store(1, Ordering::SeqCst);
+        header.data_seq.0.fetch_add(1, Ordering::SeqCst);
+        futex_wake(&header.data_seq.0);
+        header.space_seq.0.fetch_add(1, Ordering::SeqCst);
+        futex_wake(&header.space_seq.0);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.header().closed.load(Ordering::SeqCst) != 0
+    }
+
+    /// copies all bytes into the ring, waiting for the reader to make room as needed.
+    fn write_all(&self, mut bytes: &[u8]) -> Result<(), ConduitError> {
+        let header = self.header();
+        while !bytes.is_empty() {
+            if self.is_closed() {
+                return Err(anyhow::anyhow!("The ring is closed"));
+            }
+            let head = header.head.0.load(Ordering::Relaxed);
+            let free = self.wait(&header.space_seq.0, &header.space_waiting, || {
+                self.capacity - (head - header.tail.0.load(Ordering::Acquire))
+            })?;
+
+            let n = bytes.len().min(free as usize);
+            self.copy_in(head, &bytes[..n]);
+            header.head.0.store(head + n as u64, Ordering::SeqCst);
+            header.data_seq.0.fetch_add(1, Ordering::SeqCst);
+            if header.data_waiting.load(Ordering::SeqCst) != 0 {
+                futex_wake(&header.data_seq.0);
+            }
+            bytes = &bytes[n..];
+        }
+        Ok(())
+    }
+
+    /// fills the buffer from the ring, waiting for the writer as needed.
+    fn read_exact(&self, mut buffer: &mut [u8]) -> Result<(), ConduitError> {
+        let header = self.header();
+        while !buffer.is_empty() {
+            let tail = header.tail.0.load(Ordering::Relaxed);
+            let available = self.wait(&header.data_seq.0, &header.data_waiting, || {
+                header.head.0.load(Ordering::Acquire) - tail
+            })?;
+
+            let n = buffer.len().min(available as usize);
+            self.copy_out(tail, &mut buffer[..n]);
+            header.tail.0.store(tail + n as u64, Ordering::SeqCst);
+            header.space_seq.0.fetch_add(1, Ordering::SeqCst);
+            if header.space_waiting.load(Ordering::SeqCst) != 0 {
+                futex_wake(&header.space_seq.0);
+            }
+            buffer = &mut buffer[n..];
+        }
+        Ok(())
+    }
+
+    /// waits until `ready` returns a non-zero amount (of data or free space).
+    /// Fails once the ring is closed and there is nothing left.
+    fn wait(
+        &self,
+        seq: &AtomicU32,
+        waiting: &AtomicU32,
+        ready: impl Fn() -> u64,
+    ) -> Result<u64, ConduitError> {
+        for _ in 0..SPIN_COUNT {
+            match ready() {
+                0 => std::hint::spin_loop(),
+                n => return Ok(n),
+            }
+        }
+
+        loop {
+            let expected = seq.load(Ordering::SeqCst);
+            waiting.store(1, Ordering::SeqCst);
+            let n = ready();
+            if n == 0 && !self.is_closed() {
+                futex_wait(seq, expected, FUTEX_TIMEOUT);
+            }
+            waiting.store(0, Ordering::SeqCst);
+
+            if ready() == 0 && !self.is_closed() && !self.mapping.is_peer_alive() {
+                error!("The other side of the shared memory conduit is gone");
+                self.close();
+            }
+
+            match ready() {
+                0 if self.is_closed() => return Err(anyhow::anyhow!("The ring is closed")),
+                0 => continue,
+                n => return Ok(n),
+            }
+        }
+    }
+
+    fn copy_in(&self, position: u64, bytes: &[u8]) {
+        let offset = (position % self.capacity) as usize;
+        let first = bytes.len().min(self.capacity as usize - offset);
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), self.data.add(offset), first);
+            ptr::copy_nonoverlapping(bytes[first..].as_ptr(), self.data, bytes.len() - first);
+        }
+    }
+
+    fn copy_out(&self, position: u64, buffer: &mut [u8]) {
+        let offset = (position % self.capacity) as usize;
+        let first = buffer.len().min(self.capacity as usize - offset);
+        unsafe {
+            ptr::copy_nonoverlapping(self.data.add(offset), buffer.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(
+                self.data,
+                buffer[first..].as_mut_ptr(),
+                buffer.len() - first,
+            );
+        }
+    }
+
+    fn write_frame(&self, msg: &ConduitMessage) -> Result<(), ConduitError> {
+        let reason_bytes;
+        let (kind, payload) = match msg {
+            ConduitMessage::Text(text) => (FRAME_TEXT, text.as_bytes()),
+            ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => {
+                (FRAME_BINARY, data.as_slice())
+            }
+            ConduitMessage::Close(Some(reason)) => {
+                reason_bytes = reason.to_bytes();
+                (FRAME_CLOSE_WITH_REASON, reason_bytes.as_slice())
+            }
+            ConduitMessage::Close(None) => (FRAME_CLOSE, &[][..]),
+        };
+        let len = u32::try_from(payload.len())?;
+
+        let mut frame_header = [0u8; 5];
+        frame_header[..4].copy_from_slice(&len.to_le_bytes());
+        frame_header[4] = kind;
+        self.write_all(&frame_header)?;
+        self.write_all(payload)
+    }
+
+    fn read_frame(&self) -> Result<ConduitMessage, ConduitError> {
+        let mut frame_header = [0u8; 5];
+        self.read_exact(&mut frame_header)?;
+        let len = u32::from_le_bytes(frame_header[..4].try_into()?) as usize;
+        if len > MAX_FRAME_SIZE {
+            return Err(anyhow::anyhow!(
+                "The frame is too large: {len} bytes (at most {MAX_FRAME_SIZE})"
+            ));
+        }
+
+        // a frame larger than the ring arrives in pieces; the buffer only grows as they arrive,
+        // so a bogus length can't make us allocate more than the peer actually writes
+        let mut payload = Vec::with_capacity(len.min(self.capacity as usize));
+        while payload.len() < len {
+            let start = payload.len();
+            let n = (len - start).min(self.capacity as usize);
+            payload.resize(start + n, 0);
+            self.read_exact(&mut payload[start..])?;
+        }
+
+        match frame_header[4] {
+            FRAME_TEXT => Ok(ConduitMessage::Text(String::from_utf8(payload)?)),
+            FRAME_BINARY => Ok(ConduitMessage::Binary(payload)),
+            FRAME_CLOSE => Ok(ConduitMessage::Close(None)),
+            FRAME_CLOSE_WITH_REASON => Ok(ConduitMessage::Close(Some(CloseReason::from_bytes(
+                &payload,
+            )?))),
+            other => Err(anyhow::anyhow!("Unknown frame kind {other}")),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// sleeps until the word is woken, as long as it still has the expected value. Works across processes.
+fn futex_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+    let timeout = libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            &timeout as *const libc::timespec,
+            ptr::null::<u32>(),
+            0,
+        );
+    }
+}
+
+fn futex_wake(word: &AtomicU32) {
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAKE,
+            i32::MAX,
+            ptr::null::<libc::timespec>(),
+            ptr::null::<u32>(),
+            0,
+        );
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// creates the file (replacing an existing one) and opens a portal on it. The other side calls `connect`.
+/// The capacity of each ring is rounded up to a power of two.
+pub async fn create(
+    nexus: ActorRef<NexusActorMessage>,
+    path: impl AsRef<Path>,
+    capacity: usize,
+) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
+    let identifier = format!("shm://{}", path.as_ref().display());
+    let (sink, source) = create_conduit(path, capacity)?;
+    conduit::from_sink_source_with_context(
+        nexus,
+        identifier,
+        ConnectionContext::default(),
+        sink,
+        source,
+    )
+    .await
+}
+
+/// attaches to a file created by `create` and opens a portal on it.
+pub async fn connect(
+    nexus: ActorRef<NexusActorMessage>,
+    path: impl AsRef<Path>,
+) -> Result<ActorRef<PortalActorMessage>, ConduitError> {
+    let identifier = format!("shm://{}", path.as_ref().display());
+    let (sink, source) = connect_conduit(path)?;
+    conduit::from_sink_source_with_context(
+        nexus,
+        identifier,
+        ConnectionContext::default(),
+        sink,
+        source,
+    )
+    .await
+}
+
+pub fn create_conduit(
+    path: impl AsRef<Path>,
+    capacity: usize,
+) -> Result<(ConduitSink, ConduitSource), ConduitError> {
+    let capacity = capacity.max(64).next_power_of_two();
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.set_len((DATA_OFFSET + 2 * capacity) as u64)?;
+    lock(&file, CREATOR)?;
+
+    let mapping = map(file, CREATOR)?;
+    let header = mapping.file_header();
+    header.capacity.store(capacity as u64, Ordering::Release);
+    // the rest of the file is zeroed, which is an empty ring
+    header.magic.0.store(MAGIC, Ordering::Release);
+
+    Ok(map_rings_to_conduit(mapping.ring(0), mapping.ring(1)))
+}
+
+pub fn connect_conduit(
+    path: impl AsRef<Path>,
+) -> Result<(ConduitSink, ConduitSource), ConduitError> {
+    let file = OpenOptions::new().read(true).write(true).open(path)?;
+    if (file.metadata()?.len() as usize) < DATA_OFFSET {
+        return Err(anyhow::anyhow!(
+            "The file is too small for a shared memory conduit"
+        ));
+    }
+
+    let len = file.metadata()?.len() as usize;
+    let mapping = map(file, CONNECTOR)?;
+    let header = mapping.file_header();
+    if header.magic.0.load(Ordering::Acquire) != MAGIC {
+        return Err(anyhow::anyhow!("The file is not a shared memory conduit"));
+    }
+    let capacity = header.capacity.load(Ordering::Acquire) as usize;
+    if len < DATA_OFFSET + 2 * capacity {
+        return Err(anyhow::anyhow!("The file is too small for its capacity"));
+    }
+    // taken before `connected` is set, so the other side never sees a connected peer without a lock
+    lock(&mapping.file, CONNECTOR)?;
+    if header
+        .connected
+        .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err(anyhow::anyhow!("Another process is already connected"));
+    }
+
+    Ok(map_rings_to_conduit(mapping.ring(1), mapping.ring(0)))
+}
+
+fn map(file: File, side: u64) -> Result<Arc<Mapping>, ConduitError> {
+    // SAFETY: the file is shared with the other process on purpose; it is only accessed through the atomics in the headers,
+    // and each part of the data is only accessed by one side at a time.
+    let mut map = unsafe { MmapMut::map_mut(&file)? };
+    Ok(Arc::new(Mapping {
+        base: map.as_mut_ptr(),
+        _map: map,
+        file,
+        side,
+    }))
+}
+
+/// locks the byte of the side, for as long as the file is open
+fn lock(file: &File, side: u64) -> Result<(), ConduitError> {
+    let lock = file_lock(libc::F_WRLCK, side);
+    // SAFETY: `lock` is a valid flock struct
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_OFD_SETLK, &lock) } != 0 {
+        return Err(anyhow::anyhow!(
+            "Error locking the shared memory file: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+fn file_lock(kind: libc::c_int, side: u64) -> libc::flock {
+    // SAFETY: flock is a plain C struct, all zeroes is valid (and `l_pid` must be 0 for open file description locks)
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = kind as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    lock.l_start = side as libc::off_t;
+    lock.l_len = 1;
+    lock
+}
+
+/// spawns one thread which copies the outgoing frames into the ring, and one which copies the incoming frames out of it.
+/// The threads block on the futex, so they must not run on the async runtime.
+fn map_rings_to_conduit(outgoing: Ring, incoming: Ring) -> (ConduitSink, ConduitSource) {
+    let (sink_tx, mut sink_rx) = mpsc::channel::<ConduitMessage>(CHANNEL_SIZE);
+    let (mut source_tx, source_rx) = mpsc::channel(CHANNEL_SIZE);
+
+    std::thread::spawn(move || {
+        while let Some(msg) = futures::executor::block_on(sink_rx.next()) {
+            let is_close = matches!(msg, ConduitMessage::Close(_));
+            if let Err(err) = outgoing.write_frame(&msg) {
+                error!("Error writing to shared memory: {err}");
+                break;
+            }
+            if is_close {
+                info!("Closing shared memory conduit");
+                break;
+            }
+        }
+        outgoing.close();
+    });
+
+    std::thread::spawn(move || {
+        loop {
+            let msg = match incoming.read_frame() {
+                // the other side is gone without sending a close
+                Err(_) if incoming.is_closed() => break,
+                msg => msg,
+            };
+            let done = !matches!(msg, Ok(ConduitMessage::Text(_) | ConduitMessage::Binary(_)));
+            // the portal is gone
+            if futures::executor::block_on(source_tx.send(msg)).is_err() || done {
+                break;
+            }
+        }
+        incoming.close();
+    });
+
+    let sink: ConduitSink = Box::pin(sink_tx.sink_map_err(ConduitError::from));
+    let source: ConduitSource = Box::pin(source_rx);
+    (sink, source)
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::conduit::CloseCode;
+
+    #[test]
+    fn test_frames_larger_than_the_ring() -> Result<(), ConduitError> {
+        let path = std::env::temp_dir().join(format!("wormhole-ring-{}", rand::random::<u64>()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.set_len((DATA_OFFSET + 2 * 64) as u64)?;
+        let mapping = map(file, CREATOR)?;
+        mapping.file_header().capacity.store(64, Ordering::Release);
+        std::fs::remove_file(&path)?;
+
+        let (writer, reader) = (mapping.ring(0), mapping.ring(0));
+        let messages: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; 37 * i as usize]).collect();
+
+        let expected = messages.clone();
+        let writer = std::thread::spawn(move || {
+            for msg in messages {
+                writer.write_frame(&ConduitMessage::Binary(msg))?;
+            }
+            writer.write_frame(&ConduitMessage::Close(Some(CloseReason::new(
+                CloseCode::Normal,
+                "done",
+            ))))?;
+            writer.close();
+            Ok::<_, ConduitError>(())
+        });
+
+        for msg in expected {
+            assert!(matches!(reader.read_frame()?, ConduitMessage::Binary(data) if data == msg));
+        }
+        assert!(
+            matches!(reader.read_frame()?, ConduitMessage::Close(Some(reason)) if reason == CloseReason::new(CloseCode::Normal, "done"))
+        );
+        // everything has been read, so the closed ring ends
+        assert!(reader.read_frame().is_err());
+
+        writer.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_peer_which_is_gone() -> Result<(), ConduitError> {
+        let path = std::env::temp_dir().join(format!("wormhole-peer-{}", rand::random::<u64>()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.set_len((DATA_OFFSET + 2 * 64) as u64)?;
+        lock(&file, CREATOR)?;
+        let creator = map(file, CREATOR)?;
+        creator.file_header().capacity.store(64, Ordering::Release);
+        assert!(creator.is_peer_alive());
+
+        // the other side connects, and dies without closing the rings
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        lock(&file, CONNECTOR)?;
+        let connector = map(file, CONNECTOR)?;
+        connector.file_header().connected.store(1, Ordering::SeqCst);
+        assert!(creator.is_peer_alive());
+        assert!(connector.is_peer_alive());
+        drop(connector);
+        std::fs::remove_file(&path)?;
+        assert!(!creator.is_peer_alive());
+
+        let reader = creator.ring(1);
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || tx.send(reader.read_frame().is_err()));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_length_is_checked() -> Result<(), ConduitError> {
+        let path = std::env::temp_dir().join(format!("wormhole-len-{}", rand::random::<u64>()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.set_len((DATA_OFFSET + 2 * 64) as u64)?;
+        let mapping = map(file, CREATOR)?;
+        mapping.file_header().capacity.store(64, Ordering::Release);
+        std::fs::remove_file(&path)?;
+
+        let ring = mapping.ring(0);
+        let mut frame_header = [0u8; 5];
+        frame_header[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        frame_header[4] = FRAME_BINARY;
+        ring.write_all(&frame_header)?;
+        assert!(ring.read_frame().is_err());
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
-#![cfg(target_arch = "wasm32")]
+//! A websocket client using ewebsock. This is the client used in the browser (wasm),
+//! but ewebsock also supports native targets (feature `websocket_client_ewebsock`), which is used for testing.
+//!
+//! Close reasons are not transported: ewebsock neither sends nor reports the code and reason of a close frame,
+//! so the connection is closed without one and every close of the server arrives as `ConduitMessage::Close(None)`.
+//! A layer which sends the close in-band (like the `NoiseLayer`) still carries the reason.
 
 use std::{
+    future::Future,
     ops::ControlFlow,
-    sync::{Arc, Mutex, mpsc::Receiver},
-    time::Duration,
+    sync::{Arc, Mutex},
 };
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -14,94 +19,67 @@ use tokio_with_wasm::alias as tokio;
 
 use anyhow::anyhow;
 use ewebsock::{WsEvent, WsMessage, WsSender};
-use futures::{SinkExt, StreamExt};
-use log::{error, info};
-use ractor::{ActorRef, ActorStatus};
+use log::{error, info, warn};
+use ractor::ActorRef;
 use ractor_wormhole::{
-    conduit::{self, ConduitError, ConduitMessage, ConduitSink, ConduitSource},
-    nexus::{NexusActorMessage, start_nexus},
-    portal::{Portal, PortalActorMessage},
-    util::{ActorRef_Ask, FnActor},
+    conduit::{
+        self, ConduitError, ConduitMessage, ConduitSink, ConduitSource, ConnectionContext,
+        layer::ConduitLayers,
+        reconnect::{ConnectionState, ReconnectConfig, ReconnectHandle, start_reconnecting},
+    },
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+    util::MaybeSend,
 };
 
-use tokio::sync::mpsc::UnboundedReceiver;
-
-use futures::{
-    Sink,
-    task::{Context, Poll},
-};
-use std::pin::Pin;
-
-// Define a struct to wrap WsSender and implement Sink
-struct WsSenderSink {
-    sender: ActorRef<ConduitMessage>,
-}
-
-impl Sink<ConduitMessage> for WsSenderSink {
-    type Error = ConduitError;
-
-    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        if self.sender.get_status() == ActorStatus::Running {
-            Poll::Ready(Ok(()))
-        } else {
-            Poll::Ready(Err(anyhow!("Disconnected")))
-        }
-    }
-
-    fn start_send(self: Pin<&mut Self>, item: ConduitMessage) -> Result<(), Self::Error> {
-        self.sender.send_message(item);
-        Ok(())
-    }
-
-    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
-    }
-
-    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.sender
-            .send_message(ConduitMessage::Close(Some("Closing the Sink".to_string())));
-        Poll::Ready(Ok(()))
-    }
-}
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
 // note: WsSender is `struct { tx: Option<std::sync::mpsc::Sender<WsMessage>>, }`
+//  on wasm, it holds a js WebSocket and is not `Send`, so it is owned by a task, and the sink forwards to that task.
 // note: ConduitSink is `Pin<Box<dyn Sink<ConduitMessage, Error = ConduitError> + Send>>`
 pub async fn adapt_WsSender_to_Conduit(sender: WsSender) -> Result<ConduitSink, ConduitError> {
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<ConduitMessage>();
 
-    tokio::task::spawn(async move {
+    ractor::concurrency::spawn(async move {
         let mut sender = sender;
         while let Some(msg) = rx.recv().await {
-            sender.send(msg);
-        }
-        sender.close();
-    });
-
-    let (actor_ref, _handle) = FnActor::start_fn(async move |mut ctx| {
-        while let Some(msg) = ctx.rx.recv().await {
             match msg {
-                ConduitMessage::Text(text) => tx.send(WsMessage::Text(text.to_string())).unwrap(),
-                ConduitMessage::Binary(data) => tx.send(WsMessage::Binary(data.to_vec())).unwrap(),
-                ConduitMessage::Close(close_frame) => {
-                    info!("Closing the WebSocket connection: {close_frame:?}");
-                    ctx.actor_ref.stop(None);
+                ConduitMessage::Text(text) => sender.send(WsMessage::Text(text)),
+                ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => {
+                    sender.send(WsMessage::Binary(data))
+                }
+                ConduitMessage::Close(reason) => {
+                    match reason {
+                        Some(reason) => warn!(
+                            "Closing the WebSocket connection, without the reason (ewebsock can't send it): {reason}"
+                        ),
+                        None => info!("Closing the WebSocket connection"),
+                    }
+                    break;
                 }
             }
         }
-    })
-    .await?;
+        sender.close();
+    });
 
-    // Create and box the sink implementation
-    let sink = WsSenderSink { sender: actor_ref };
+    let sink = futures::sink::unfold(tx, |tx: UnboundedSender<ConduitMessage>, msg| async move {
+        tx.send(msg)
+            .map_err(|_| anyhow!("The WebSocket connection is closed"))?;
+        Ok::<_, ConduitError>(tx)
+    });
     Ok(Box::pin(sink))
 }
 
 // note: ConduitSource is `Pin<Box<dyn Stream<Item = Result<ConduitMessage, ConduitError>> + Send>>`
-pub fn adapt_tokio_receiver_to_Conduit(rx: UnboundedReceiver<ConduitMessage>) -> ConduitSource {
+pub fn adapt_tokio_receiver_to_Conduit(
+    rx: UnboundedReceiver<Result<ConduitMessage, ConduitError>>,
+) -> ConduitSource {
     // Create a stream from the receiver
-    let stream = futures::stream::unfold(rx, |mut rx| async move {
-        rx.recv().await.map(|msg| (Ok(msg), rx))
-    });
+    let stream =
+        futures::stream::unfold(
+            rx,
+            |mut rx| async move { rx.recv().await.map(|msg| (msg, rx)) },
+        );
 
     // Box and pin the stream
     Box::pin(stream)
@@ -111,64 +89,122 @@ pub async fn connect_to_server(
     nexus: ActorRef<NexusActorMessage>,
     url: String,
 ) -> Result<ActorRef<PortalActorMessage>, anyhow::Error> {
-    info!("Connecting to WebSocket server at: {url}");
+    connect_to_server_with_layers(nexus, url, ConduitLayers::new()).await
+}
 
-    let (opened_tx, opened_rx) = tokio::sync::oneshot::channel();
+/// like `connect_to_server`, but wraps the conduit in the layers (see `conduit::layer`).
+pub async fn connect_to_server_with_layers(
+    nexus: ActorRef<NexusActorMessage>,
+    url: String,
+    layers: ConduitLayers,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error> {
+    info!("Connecting to WebSocket server at: {url}");
 
-    let (_internal_tx, ws_rx) = tokio::sync::mpsc::unbounded_channel();
+    // resolves once the connection is opened, or failed to open.
+    //  If ewebsock drops the handler without reporting either, the sender is dropped, so this can't hang.
+    let (opened_tx, opened_rx) = futures::channel::oneshot::channel::<Result<(), String>>();
     let opened_tx = Arc::new(Mutex::new(Some(opened_tx)));
+
+    let (internal_tx, ws_rx) = tokio::sync::mpsc::unbounded_channel();
     let handler: Box<dyn Send + Fn(WsEvent) -> ControlFlow<()>> = Box::new(move |evt| {
+        let pending_open = || opened_tx.lock().unwrap().take();
         match evt {
             WsEvent::Opened => {
-                if let Some(opened_tx) = opened_tx.lock().unwrap().take() {
-                    let _ = opened_tx.send(());
-                };
                 info!("WebSocket connection opened");
+                if let Some(opened_tx) = pending_open() {
+                    let _ = opened_tx.send(Ok(()));
+                }
+                ControlFlow::Continue(())
             }
             WsEvent::Message(ws_message) => {
-                match ws_message {
-                    WsMessage::Text(text) => {
-                        _internal_tx.send(ConduitMessage::Text(text.to_string()));
-                    }
-                    WsMessage::Binary(bin) => {
-                        _internal_tx.send(ConduitMessage::Binary(bin));
-                    }
-                    _ => {}
+                let msg = match ws_message {
+                    WsMessage::Text(text) => ConduitMessage::Text(text),
+                    WsMessage::Binary(bin) => ConduitMessage::Binary(bin),
+                    _ => return ControlFlow::Continue(()),
                 };
+                // the portal is gone, there is no point in receiving more messages
+                match internal_tx.send(Ok(msg)) {
+                    Ok(()) => ControlFlow::Continue(()),
+                    Err(_) => ControlFlow::Break(()),
+                }
             }
-            WsEvent::Error(_) => {
-                error!("WebSocket error");
+            WsEvent::Error(err) => {
+                error!("WebSocket error: {err}");
+                match pending_open() {
+                    Some(opened_tx) => {
+                        let _ = opened_tx.send(Err(err));
+                    }
+                    None => {
+                        let _ = internal_tx.send(Err(anyhow!("WebSocket error: {err}")));
+                    }
+                }
+                ControlFlow::Break(())
             }
+            // ewebsock doesn't report the code and reason of the close frame
             WsEvent::Closed => {
                 info!("WebSocket connection closed");
+                match pending_open() {
+                    Some(opened_tx) => {
+                        let _ = opened_tx.send(Err("Connection closed".to_string()));
+                    }
+                    None => {
+                        let _ = internal_tx.send(Ok(ConduitMessage::Close(None)));
+                    }
+                }
+                ControlFlow::Break(())
             }
         }
-        ControlFlow::Continue(())
     });
     let ws_tx = ewebsock::ws_connect(url.clone(), ewebsock::Options::default(), handler)
-        .map_err(|err| anyhow!(err))?;
+        .map_err(|err| anyhow!("Failed to connect to WebSocket server at {url}: {err}"))?;
 
     // Wait for the connection to be opened
-    opened_rx.await.unwrap();
+    match opened_rx.await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => {
+            return Err(anyhow!(
+                "Failed to connect to WebSocket server at {url}: {err}"
+            ));
+        }
+        Err(_) => {
+            return Err(anyhow!(
+                "Failed to connect to WebSocket server at {url}: the connection was dropped"
+            ));
+        }
+    }
 
-    let ws_tx = adapt_WsSender_to_Conduit(ws_tx).await.unwrap();
+    let ws_tx = adapt_WsSender_to_Conduit(ws_tx).await?;
     let ws_rx = adapt_tokio_receiver_to_Conduit(ws_rx);
 
     // Register the portal with the nexus actor
-    let portal_identifier = url.to_string();
-    let portal = nexus
-        .ask(
-            |rpc| NexusActorMessage::Connected(portal_identifier.clone(), ws_tx, rpc),
-            None,
-        )
-        .await?;
-
-    info!("Portal actor started for: {url}");
-
-    let portal_actor_copy = portal.clone();
-    tokio::spawn(async move {
-        conduit::receive_loop(ws_rx, portal_identifier, portal_actor_copy).await
-    });
+    conduit::from_sink_source_with_layers(
+        nexus,
+        url,
+        ConnectionContext::default(),
+        ws_tx,
+        ws_rx,
+        &layers,
+    )
+    .await
+}
 
-    Ok(portal)
+/// connects to the server and keeps reconnecting (with exponential backoff) whenever the connection is lost.
+/// `bootstrap` is run on every new portal, see `conduit::reconnect::start_reconnecting`.
+pub fn connect_to_server_with_reconnect<B, BFut>(
+    nexus: ActorRef<NexusActorMessage>,
+    url: String,
+    bootstrap: B,
+    config: ReconnectConfig,
+    on_state: Option<ActorRef<ConnectionState>>,
+) -> ReconnectHandle
+where
+    B: Fn(ActorRef<PortalActorMessage>) -> BFut + MaybeSend + 'static,
+    BFut: Future<Output = Result<(), anyhow::Error>> + MaybeSend,
+{
+    start_reconnecting(
+        move || connect_to_server(nexus.clone(), url.clone()),
+        bootstrap,
+        config,
+        on_state,
+    )
 }
@@ -1,4 +1,7 @@
-#[cfg(target_arch = "wasm32")]
+#[cfg(any(
+    all(target_arch = "wasm32", feature = "websocket_client_wasm"),
+    all(not(target_arch = "wasm32"), feature = "websocket_client_ewebsock")
+))]
 pub mod ewebsock;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), feature = "websocket_client"))]
 pub mod tokio_tungstenite;
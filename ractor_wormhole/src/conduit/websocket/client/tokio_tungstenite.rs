@@ -1,18 +1,37 @@
-use futures::{SinkExt, StreamExt, future};
 use log::{error, info};
 use ractor::ActorRef;
+use std::{future::Future, net::SocketAddr};
+use tokio::net::TcpStream;
 use tokio_tungstenite::{
-    WebSocketStream, connect_async, tungstenite::client::IntoClientRequest,
-    tungstenite::protocol::Message,
+    Connector, MaybeTlsStream, connect_async_tls_with_config,
+    tungstenite::client::IntoClientRequest,
 };
 
 use ractor_wormhole::{
-    conduit::{ConduitError, ConduitMessage, ConduitSink, ConduitSource},
+    conduit::{
+        ConnectionContext,
+        layer::ConduitLayers,
+        reconnect::{ConnectionState, ReconnectConfig, ReconnectHandle, start_reconnecting},
+    },
     nexus::NexusActorMessage,
     portal::PortalActorMessage,
 };
 
-use crate::{conduit, util::ActorRef_Ask};
+pub use crate::conduit::websocket::mapping::{map_conduit_to_ws, map_ws_to_conduit};
+
+#[cfg(feature = "websocket_tls")]
+use crate::conduit::websocket::tls::ClientTlsConfig;
+
+use crate::conduit;
+
+#[derive(Default)]
+pub struct ClientConfig {
+    /// the layers the conduit is wrapped in, see `conduit::layer`.
+    pub layers: ConduitLayers,
+    /// connect to a `wss://` server using rustls with this config, instead of the default native-tls.
+    #[cfg(feature = "websocket_tls")]
+    pub tls: Option<ClientTlsConfig>,
+}
 
 pub async fn connect_to_server<R>(
     nexus: ActorRef<NexusActorMessage>,
@@ -21,12 +40,73 @@ pub async fn connect_to_server<R>(
 where
     R: IntoClientRequest + Unpin,
 {
+    connect_to_server_with_config(nexus, request, ClientConfig::default()).await
+}
+
+/// connects to a `wss://` server, using rustls with the given config.
+/// This allows to trust a private CA, or to authenticate with a client certificate.
+#[cfg(feature = "websocket_tls")]
+pub async fn connect_to_server_tls<R>(
+    nexus: ActorRef<NexusActorMessage>,
+    request: R,
+    tls: ClientTlsConfig,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error>
+where
+    R: IntoClientRequest + Unpin,
+{
+    let config = ClientConfig {
+        tls: Some(tls),
+        ..Default::default()
+    };
+    connect_to_server_with_config(nexus, request, config).await
+}
+
+/// connects to the server and keeps reconnecting (with exponential backoff) whenever the connection is lost.
+/// `client_config` is called for every attempt (e.g. `ClientConfig::default`), see `connect_to_server_with_config`.
+/// `bootstrap` is run on every new portal, see `conduit::reconnect::start_reconnecting`.
+pub fn connect_to_server_with_reconnect<C, B, BFut>(
+    nexus: ActorRef<NexusActorMessage>,
+    url: String,
+    client_config: C,
+    bootstrap: B,
+    config: ReconnectConfig,
+    on_state: Option<ActorRef<ConnectionState>>,
+) -> ReconnectHandle
+where
+    C: Fn() -> ClientConfig + Send + 'static,
+    B: Fn(ActorRef<PortalActorMessage>) -> BFut + Send + 'static,
+    BFut: Future<Output = Result<(), anyhow::Error>> + Send,
+{
+    start_reconnecting(
+        move || connect_to_server_with_config(nexus.clone(), url.clone(), client_config()),
+        bootstrap,
+        config,
+        on_state,
+    )
+}
+
+pub async fn connect_to_server_with_config<R>(
+    nexus: ActorRef<NexusActorMessage>,
+    request: R,
+    config: ClientConfig,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error>
+where
+    R: IntoClientRequest + Unpin,
+{
+    #[cfg(feature = "websocket_tls")]
+    let connector = match &config.tls {
+        Some(tls) => Some(Connector::Rustls(tls.build()?)),
+        None => None,
+    };
+    #[cfg(not(feature = "websocket_tls"))]
+    let connector: Option<Connector> = None;
+
     let r = request.into_client_request()?;
     let uri = r.uri().clone();
     info!("Connecting to WebSocket server at: {uri}");
 
     // Connect to the WebSocket server
-    let (ws_stream, _) = match connect_async(r).await {
+    let (ws_stream, _) = match connect_async_tls_with_config(r, None, false, connector).await {
         Ok(conn) => {
             info!("WebSocket connection established to: {uri}");
             conn
@@ -37,85 +117,36 @@ where
         }
     };
 
-    // Split the WebSocket stream
-    let (tx, rx) = ws_stream.split();
-
-    // map the tungstenite messages to ConduitMessage
-    let rx = map_ws_to_conduit(rx);
+    let context = ConnectionContext {
+        remote_addr: peer_addr(ws_stream.get_ref()),
+        ..Default::default()
+    };
 
-    // map the ConduitMessage to tungstenite messages
-    let tx = map_conduit_to_ws(tx);
+    // Split the WebSocket stream and map the tungstenite messages from and to ConduitMessage
+    let (tx, rx) = crate::conduit::websocket::mapping::split_websocket(ws_stream);
 
     // Register the portal with the nexus actor
-    let portal_identifier = uri.to_string();
-    let portal = nexus
-        .ask(
-            |rpc| NexusActorMessage::Connected(portal_identifier.clone(), tx, rpc),
-            None,
-        )
-        .await?;
+    let portal = conduit::from_sink_source_with_layers(
+        nexus,
+        uri.to_string(),
+        context,
+        tx,
+        rx,
+        &config.layers,
+    )
+    .await?;
 
     info!("Portal actor started for: {uri}");
 
-    let portal_actor_copy = portal.clone();
-    ractor::concurrency::spawn(async move {
-        conduit::receive_loop(rx, portal_identifier, portal_actor_copy).await
-    });
-
     Ok(portal)
 }
 
-// ---------------------------------------------------------------------------------
-
-/// Maps a WebSocket stream to a ConduitSource by transforming WebSocket Messages to ConduitMessages
-pub fn map_ws_to_conduit<T>(
-    source: futures::stream::SplitStream<WebSocketStream<T>>,
-) -> ConduitSource
-where
-    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
-{
-    let source = source.filter_map(|element| {
-        let output = match element {
-            Ok(msg) => {
-                let msg = match msg {
-                    Message::Text(text) => Some(ConduitMessage::Text(text.to_string())),
-                    Message::Binary(bin) => Some(ConduitMessage::Binary(bin.into())),
-                    Message::Close(Some(reason)) => Some(ConduitMessage::Close(Some(format!(
-                        "Close code: {:?}, reason: {}",
-                        reason.code, reason.reason
-                    )))),
-                    Message::Close(None) => Some(ConduitMessage::Close(None)),
-                    _unhandled => None,
-                };
-
-                Ok(msg)
-            }
-            Err(e) => Err(ConduitError::from(e)),
-        };
-
-        match output {
-            Ok(Some(msg)) => future::ready(Some(Ok(msg))),
-            Ok(None) => future::ready(None),
-            Err(e) => future::ready(Some(Err(e))),
-        }
-    });
-    Box::pin(source)
-}
-
-/// Maps a WebSocket sink to a ConduitSink by transforming ConduitMessages to WebSocket Messages
-pub fn map_conduit_to_ws<S>(
-    sink: futures::stream::SplitSink<WebSocketStream<S>, Message>,
-) -> ConduitSink
-where
-    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
-{
-    let sink = sink.with(|element: ConduitMessage| async {
-        let msg = match element {
-            ConduitMessage::Text(text) => Message::text(text),
-            ConduitMessage::Binary(bin) => Message::binary(bin),
-            ConduitMessage::Close(_) => Message::Close(None),
-        };
-        Ok(msg)
-    });
-    Box::pin(sink)
+fn peer_addr(stream: &MaybeTlsStream<TcpStream>) -> Option<SocketAddr> {
+    match stream {
+        MaybeTlsStream::Plain(stream) => stream.peer_addr().ok(),
+        #[cfg(feature = "websocket_tls")]
+        MaybeTlsStream::Rustls(stream) => stream.get_ref().0.peer_addr().ok(),
+        MaybeTlsStream::NativeTls(stream) => stream.get_ref().get_ref().get_ref().peer_addr().ok(),
+        _ => None,
+    }
 }
@@ -0,0 +1,139 @@
+//! The mapping between tungstenite websocket messages and `ConduitMessage`s, shared by the client and all servers.
+
+use futures::{SinkExt, StreamExt, future};
+use log::warn;
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::protocol::{CloseFrame, Message, frame::coding},
+};
+
+use crate::conduit::{
+    CloseCode, CloseReason, ConduitError, ConduitMessage, ConduitSink, ConduitSource,
+};
+
+/// splits the websocket into a sink and a source.
+pub fn split_websocket<S>(ws_stream: WebSocketStream<S>) -> (ConduitSink, ConduitSource)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = ws_stream.split();
+    (map_conduit_to_ws(tx), map_ws_to_conduit(rx))
+}
+
+/// Maps a WebSocket sink to a ConduitSink by transforming ConduitMessages to WebSocket Messages
+pub fn map_conduit_to_ws<S>(
+    sink: futures::stream::SplitSink<WebSocketStream<S>, Message>,
+) -> ConduitSink
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let sink = sink.with(|element: ConduitMessage| async {
+        let msg = match element {
+            ConduitMessage::Text(text) => Message::text(text),
+            ConduitMessage::Binary(bin) | ConduitMessage::Bulk(bin) => Message::binary(bin),
+            ConduitMessage::Close(reason) => Message::Close(reason.and_then(to_close_frame)),
+        };
+        Ok(msg)
+    });
+    Box::pin(sink)
+}
+
+/// Maps a WebSocket stream to a ConduitSource by transforming WebSocket Messages to ConduitMessages
+pub fn map_ws_to_conduit<T>(
+    source: futures::stream::SplitStream<WebSocketStream<T>>,
+) -> ConduitSource
+where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let source = source.filter_map(|element| {
+        let output = match element {
+            Ok(msg) => {
+                let msg = match msg {
+                    Message::Text(text) => Some(ConduitMessage::Text(text.to_string())),
+                    Message::Binary(bin) => Some(ConduitMessage::Binary(bin.into())),
+                    Message::Close(Some(frame)) => {
+                        Some(ConduitMessage::Close(Some(from_close_frame(frame))))
+                    }
+                    Message::Close(None) => Some(ConduitMessage::Close(None)),
+                    _unhandled => None,
+                };
+
+                Ok(msg)
+            }
+            Err(e) => Err(ConduitError::from(e)),
+        };
+
+        match output {
+            Ok(Some(msg)) => future::ready(Some(Ok(msg))),
+            Ok(None) => future::ready(None),
+            Err(e) => future::ready(Some(Err(e))),
+        }
+    });
+    Box::pin(source)
+}
+
+/// the longest reason which fits into a close frame (a control frame carries at most 125 bytes, 2 of them are the code)
+const MAX_CLOSE_REASON: usize = 123;
+
+/// the close code and reason as a websocket close frame. The reason is cut to `MAX_CLOSE_REASON` bytes.
+/// Codes which must not be sent (e.g. 1005, 1006 and 1015, which only describe a close locally) give no frame,
+/// so the websocket is closed without a code.
+pub fn to_close_frame(reason: CloseReason) -> Option<CloseFrame> {
+    let code = coding::CloseCode::from(reason.code.to_u16());
+    if !code.is_allowed() {
+        warn!("Close code {code} can't be sent, closing without a code");
+        return None;
+    }
+
+    let mut reason = reason.reason;
+    if reason.len() > MAX_CLOSE_REASON {
+        let mut end = MAX_CLOSE_REASON;
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        reason.truncate(end);
+    }
+    Some(CloseFrame {
+        code,
+        reason: reason.into(),
+    })
+}
+
+pub fn from_close_frame(frame: CloseFrame) -> CloseReason {
+    CloseReason {
+        code: CloseCode::from_u16(frame.code.into()),
+        reason: frame.reason.to_string(),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_close_reason_is_cut_on_a_char_boundary() {
+        // 'ä' is two bytes, so byte 123 is in the middle of one
+        let reason = CloseReason::new(CloseCode::Normal, "ä".repeat(100));
+        let frame = to_close_frame(reason).expect("a normal close");
+        assert_eq!(frame.reason.len(), 122);
+        assert!(frame.reason.chars().all(|c| c == 'ä'));
+
+        let reason = CloseReason::new(CloseCode::Normal, "bye");
+        assert_eq!(
+            to_close_frame(reason).map(|frame| frame.reason.to_string()),
+            Some("bye".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reserved_close_codes_are_not_sent() {
+        for code in [1005, 1006, 1015, 999] {
+            let reason = CloseReason::new(CloseCode::Other(code), "reserved");
+            assert!(to_close_frame(reason).is_none(), "{code}");
+        }
+        let reason = CloseReason::new(CloseCode::Other(4000), "application");
+        assert!(to_close_frame(reason).is_some());
+    }
+}
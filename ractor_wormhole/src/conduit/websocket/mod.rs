@@ -1,5 +1,18 @@
-#[cfg(any(feature = "websocket_client", feature = "websocket_client_wasm"))]
+#[cfg(any(
+    feature = "websocket_client",
+    feature = "websocket_client_wasm",
+    feature = "websocket_client_ewebsock"
+))]
 pub mod client;
 
 #[cfg(feature = "websocket_server")]
 pub mod server;
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "websocket_client", feature = "websocket_server")
+))]
+pub mod mapping;
+
+#[cfg(all(feature = "websocket_tls", not(target_arch = "wasm32")))]
+pub mod tls;
@@ -0,0 +1,79 @@
+//! Integration into an axum router.
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .route("/ws", get(websocket::server::axum::websocket_handler))
+//!     .with_state(nexus);
+//!
+//! // serve with connect info, so the remote address is passed to the nexus
+//! axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+//! ```
+//!
+//! If the router has a different state, use the `PortalUpgrade` extractor instead.
+
+use std::net::SocketAddr;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, FromRequest, Request, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use log::error;
+use ractor::ActorRef;
+
+use ractor_wormhole::{conduit::layer::ConduitLayers, nexus::NexusActorMessage};
+
+/// extracts a websocket upgrade request, which can then be turned into a portal with `PortalUpgrade::into_portal`.
+pub struct PortalUpgrade {
+    request: Request,
+}
+
+impl<S: Send + Sync> FromRequest<S> for PortalUpgrade {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        if !super::hyper::is_upgrade_request(&request) {
+            return Err((StatusCode::BAD_REQUEST, "Expected a websocket upgrade").into_response());
+        }
+        Ok(Self { request })
+    }
+}
+
+impl PortalUpgrade {
+    /// completes the upgrade and opens a portal on the nexus.
+    /// The path, the headers and (if the router is served with connect info) the remote address
+    /// are passed to the nexus in the `ConnectionContext`.
+    pub fn into_portal(self, nexus: ActorRef<NexusActorMessage>) -> Response {
+        self.into_portal_with_layers(nexus, ConduitLayers::new())
+    }
+
+    /// like `into_portal`, but wraps the conduit in the layers (see `conduit::layer`).
+    pub fn into_portal_with_layers(
+        self,
+        nexus: ActorRef<NexusActorMessage>,
+        layers: ConduitLayers,
+    ) -> Response {
+        let mut request = self.request;
+        let remote_addr = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+
+        match super::hyper::upgrade_with_layers(nexus, &mut request, remote_addr, layers) {
+            Ok(response) => response.map(Body::new),
+            Err(err) => {
+                error!("Error upgrading request to websocket: {err}");
+                (StatusCode::BAD_REQUEST, "Invalid websocket upgrade").into_response()
+            }
+        }
+    }
+}
+
+/// an axum handler for routers whose state is the nexus.
+pub async fn websocket_handler(
+    State(nexus): State<ActorRef<NexusActorMessage>>,
+    upgrade: PortalUpgrade,
+) -> Response {
+    upgrade.into_portal(nexus)
+}
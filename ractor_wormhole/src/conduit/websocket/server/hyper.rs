@@ -0,0 +1,149 @@
+//! Integration into an existing hyper server: upgrade a http request to a websocket and open a portal on it.
+//!
+//! ```ignore
+//! async fn handle(nexus: ActorRef<NexusActorMessage>, addr: SocketAddr, mut req: Request<Incoming>) -> ... {
+//!     if websocket::server::hyper::is_upgrade_request(&req) {
+//!         return websocket::server::hyper::upgrade(nexus, &mut req, Some(addr));
+//!     }
+//!     // ... handle all other requests
+//! }
+//! ```
+
+use std::{convert::Infallible, net::SocketAddr};
+
+use http_body_util::Full;
+use hyper::{
+    Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    service::Service,
+};
+use hyper_tungstenite::HyperWebsocket;
+use log::error;
+use ractor::ActorRef;
+
+use ractor_wormhole::{
+    conduit::{self, ConnectionContext, layer::ConduitLayers},
+    nexus::NexusActorMessage,
+    portal::PortalActorMessage,
+};
+
+use crate::conduit::websocket::mapping::split_websocket;
+
+pub use hyper_tungstenite;
+
+/// returns true if the request wants to be upgraded to a websocket.
+pub fn is_upgrade_request<B>(request: &Request<B>) -> bool {
+    hyper_tungstenite::is_upgrade_request(request)
+}
+
+/// upgrades the request to a websocket and opens a portal on it, once the upgrade has completed.
+/// The returned response (`101 Switching Protocols`) must be sent back to the client.
+///
+/// The path and headers of the request, and the remote address (if known), are passed to the nexus in the `ConnectionContext`.
+pub fn upgrade<B>(
+    nexus: ActorRef<NexusActorMessage>,
+    request: &mut Request<B>,
+    remote_addr: Option<SocketAddr>,
+) -> Result<Response<Full<Bytes>>, anyhow::Error> {
+    upgrade_with_layers(nexus, request, remote_addr, ConduitLayers::new())
+}
+
+/// like `upgrade`, but wraps the conduit in the layers (see `conduit::layer`).
+pub fn upgrade_with_layers<B>(
+    nexus: ActorRef<NexusActorMessage>,
+    request: &mut Request<B>,
+    remote_addr: Option<SocketAddr>,
+    layers: ConduitLayers,
+) -> Result<Response<Full<Bytes>>, anyhow::Error> {
+    let mut context = ConnectionContext {
+        remote_addr,
+        ..Default::default()
+    };
+    context.add_request_metadata(request);
+
+    let (response, websocket) = hyper_tungstenite::upgrade(request, None)?;
+
+    // the upgrade only completes after the response has been sent, so this must not be awaited inline.
+    ractor::concurrency::spawn(async move {
+        if let Err(err) = serve_websocket(nexus, websocket, context, layers).await {
+            error!("Error in websocket connection: {err}");
+        }
+    });
+
+    Ok(response)
+}
+
+/// waits for the upgrade to complete, then opens a portal on the websocket, wrapped in the layers.
+pub async fn serve_websocket(
+    nexus: ActorRef<NexusActorMessage>,
+    websocket: HyperWebsocket,
+    context: ConnectionContext,
+    layers: ConduitLayers,
+) -> Result<ActorRef<PortalActorMessage>, anyhow::Error> {
+    let websocket = websocket.await?;
+    let (tx, rx) = split_websocket(websocket);
+
+    let identifier = context.portal_identifier("ws");
+    conduit::from_sink_source_with_layers(nexus, identifier, context, tx, rx, &layers).await
+}
+
+/// a hyper service which upgrades every websocket request to a portal, and rejects all other requests.
+///
+/// Serve it with `hyper::server::conn::http1::Builder::serve_connection(io, service).with_upgrades()`.
+pub fn websocket_service(
+    nexus: ActorRef<NexusActorMessage>,
+    remote_addr: Option<SocketAddr>,
+) -> impl Service<
+    Request<Incoming>,
+    Response = Response<Full<Bytes>>,
+    Error = Infallible,
+    Future: Send + 'static,
+> + Clone
++ Send
++ 'static {
+    websocket_service_with_layers(nexus, remote_addr, ConduitLayers::new())
+}
+
+/// like `websocket_service`, but wraps every conduit in the layers (see `conduit::layer`).
+pub fn websocket_service_with_layers(
+    nexus: ActorRef<NexusActorMessage>,
+    remote_addr: Option<SocketAddr>,
+    layers: ConduitLayers,
+) -> impl Service<
+    Request<Incoming>,
+    Response = Response<Full<Bytes>>,
+    Error = Infallible,
+    Future: Send + 'static,
+> + Clone
++ Send
++ 'static {
+    hyper::service::service_fn(move |mut request: Request<Incoming>| {
+        let nexus = nexus.clone();
+        let layers = layers.clone();
+        async move {
+            if !is_upgrade_request(&request) {
+                return Ok(response(
+                    StatusCode::BAD_REQUEST,
+                    "Expected a websocket upgrade",
+                ));
+            }
+
+            match upgrade_with_layers(nexus, &mut request, remote_addr, layers) {
+                Ok(response) => Ok(response),
+                Err(err) => {
+                    error!("Error upgrading request to websocket: {err}");
+                    Ok(response(
+                        StatusCode::BAD_REQUEST,
+                        "Invalid websocket upgrade",
+                    ))
+                }
+            }
+        }
+    })
+}
+
+fn response(status: StatusCode, body: &'static str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::from(body));
+    *response.status_mut() = status;
+    response
+}
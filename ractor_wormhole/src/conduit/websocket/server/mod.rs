@@ -1 +1,7 @@
 pub mod tokio_tungstenite;
+
+#[cfg(feature = "websocket_hyper")]
+pub mod hyper;
+
+#[cfg(feature = "websocket_axum")]
+pub mod axum;
@@ -1,47 +1,261 @@
 //mod server_http;
 
-use futures::{SinkExt, StreamExt, future};
-use log::{error, info};
-use ractor::ActorRef;
-use std::net::SocketAddr;
+use futures::future;
+use log::{error, info, warn};
+use ractor::{ActorRef, concurrency::Duration};
+use std::{net::SocketAddr, sync::Arc};
 use tokio::net::{TcpListener, TcpStream};
-use tokio_tungstenite::{WebSocketStream, tungstenite::protocol::Message};
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{
+        handshake::server::{Request, Response},
+        protocol::WebSocketConfig,
+    },
+};
 
 use ractor_wormhole::{
-    conduit::{ConduitError, ConduitMessage, ConduitSink, ConduitSource},
+    conduit::{
+        ConnectionContext,
+        layer::{Conduit, ConduitLayers},
+    },
     nexus::NexusActorMessage,
 };
 
-use crate::conduit;
+#[cfg(feature = "websocket_tls")]
+use crate::conduit::websocket::tls::ServerTlsConfig;
+#[cfg(feature = "websocket_tls")]
+use tokio_rustls::TlsAcceptor;
+
+use crate::conduit::{
+    self,
+    server::{ConnectionSlot, ServerShared},
+    websocket::mapping::split_websocket,
+};
+
+pub use crate::conduit::server::ServerHandle;
+pub use crate::conduit::websocket::mapping::{map_conduit_to_ws, map_ws_to_conduit};
+
+// -------------------------------------------------------------------------------------------------------
+
+pub struct ServerConfig {
+    /// the maximum number of concurrent connections (including connections which are still in the handshake).
+    /// Further connections are dropped immediately.
+    pub max_connections: Option<usize>,
+    /// the maximum number of concurrent connections from a single IP address.
+    pub max_connections_per_ip: Option<usize>,
+    /// connections which haven't completed the (TLS and) websocket handshake after this time are dropped.
+    pub handshake_timeout: Duration,
+    /// the maximum size of a websocket message, after reassembling its frames.
+    pub max_message_size: Option<usize>,
+    /// the maximum size of a single websocket frame.
+    pub max_frame_size: Option<usize>,
+    /// if set, the server only accepts TLS connections (`wss://`).
+    #[cfg(feature = "websocket_tls")]
+    pub tls: Option<ServerTlsConfig>,
+    /// the layers every conduit is wrapped in, see `conduit::layer`.
+    pub layers: ConduitLayers,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            max_connections_per_ip: None,
+            handshake_timeout: Duration::from_secs(10),
+            max_message_size: Some(64 << 20),
+            max_frame_size: Some(16 << 20),
+            #[cfg(feature = "websocket_tls")]
+            tls: None,
+            layers: ConduitLayers::new(),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
 
 /// starts a pure websocket server (using tokio_tungstenite) on the specific bind address.
 pub async fn start_server(
     nexus: ActorRef<NexusActorMessage>,
     bind: SocketAddr,
-) -> Result<(), anyhow::Error> {
+) -> Result<ServerHandle, anyhow::Error> {
+    start_server_with_config(nexus, bind, ServerConfig::default()).await
+}
+
+/// starts a websocket server secured by TLS (`wss://`) on the specific bind address.
+/// If the config requires client certificates, the verified chain is passed to the nexus in the `ConnectionContext`.
+#[cfg(feature = "websocket_tls")]
+pub async fn start_server_tls(
+    nexus: ActorRef<NexusActorMessage>,
+    bind: SocketAddr,
+    tls: ServerTlsConfig,
+) -> Result<ServerHandle, anyhow::Error> {
+    let config = ServerConfig {
+        tls: Some(tls),
+        ..Default::default()
+    };
+    start_server_with_config(nexus, bind, config).await
+}
+
+pub async fn start_server_with_config(
+    nexus: ActorRef<NexusActorMessage>,
+    bind: SocketAddr,
+    config: ServerConfig,
+) -> Result<ServerHandle, anyhow::Error> {
+    #[cfg(feature = "websocket_tls")]
+    let acceptor = match &config.tls {
+        Some(tls) => Some(TlsAcceptor::from(tls.build()?)),
+        None => None,
+    };
+    #[cfg(not(feature = "websocket_tls"))]
+    let acceptor: Option<()> = None;
+
     // Create a TCP listener
     let listener = TcpListener::bind(&bind).await?;
-    info!("WebSocket server listening on: {bind}");
+    let local_addr = listener.local_addr()?;
+    info!("WebSocket server listening on: {local_addr}");
+
+    let (shared, mut stop_rx) = ServerShared::new();
 
     // Accept connections
-    let nexus_copy = nexus.clone();
+    let config = Arc::new(config);
+    let shared_copy = shared.clone();
     tokio::spawn(async move {
-        while let Ok((stream, addr)) = listener.accept().await {
+        loop {
+            let accepted = match future::select(Box::pin(listener.accept()), &mut stop_rx).await {
+                future::Either::Left((accepted, _)) => accepted,
+                // shutdown was requested. Note: the sender is kept alive by `shared_copy`, it can't be cancelled.
+                future::Either::Right(_) => break,
+            };
+            let (stream, addr) = match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("Error accepting connection: {e}");
+                    continue;
+                }
+            };
+
             info!("New connection from: {addr}");
-            let _ = handle_connection(stream, addr, nexus_copy.clone()).await;
+            let Some(slot) = shared_copy.try_admit(
+                addr.ip(),
+                config.max_connections,
+                config.max_connections_per_ip,
+            ) else {
+                continue;
+            };
+
+            // handshake each connection in its own task, so a slow client doesn't block the others
+            tokio::spawn(serve_connection(
+                stream,
+                addr,
+                nexus.clone(),
+                config.clone(),
+                acceptor.clone(),
+                slot,
+            ));
         }
+        info!("WebSocket server on {local_addr} stopped accepting connections");
     });
 
-    Ok(())
+    Ok(ServerHandle::new("WebSocket", local_addr, shared))
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    addr: SocketAddr,
+    nexus: ActorRef<NexusActorMessage>,
+    config: Arc<ServerConfig>,
+    #[cfg(feature = "websocket_tls")] acceptor: Option<TlsAcceptor>,
+    #[cfg(not(feature = "websocket_tls"))] acceptor: Option<()>,
+    slot: ConnectionSlot,
+) {
+    let ws_config = WebSocketConfig::default()
+        .max_message_size(config.max_message_size)
+        .max_frame_size(config.max_frame_size);
+
+    // the handshakes of the layers (e.g. encryption) count towards the handshake timeout, too
+    let handshake = async {
+        let mut context = ConnectionContext {
+            remote_addr: Some(addr),
+            ..Default::default()
+        };
+
+        let (portal_identifier, (sink, source)) = match acceptor {
+            #[cfg(feature = "websocket_tls")]
+            Some(acceptor) => {
+                let tls_stream = acceptor.accept(stream).await?;
+                context.peer_certificates = tls_stream
+                    .get_ref()
+                    .1
+                    .peer_certificates()
+                    .map(|certs| certs.iter().map(|cert| cert.to_vec()).collect())
+                    .unwrap_or_default();
+                let ws_stream = accept_websocket(tls_stream, &mut context, ws_config).await?;
+                (format!("wss://{addr}"), split_websocket(ws_stream))
+            }
+            _ => {
+                let ws_stream = accept_websocket(stream, &mut context, ws_config).await?;
+                (format!("ws://{addr}"), split_websocket(ws_stream))
+            }
+        };
+
+        let conduit = config
+            .layers
+            .apply(Conduit {
+                sink,
+                source,
+                context,
+            })
+            .await?;
+        Ok::<_, anyhow::Error>((portal_identifier, conduit))
+    };
+
+    let (portal_identifier, conduit) =
+        match ractor::concurrency::timeout(config.handshake_timeout, handshake).await {
+            Ok(Ok(conduit)) => conduit,
+            Ok(Err(e)) => {
+                error!("Error during handshake with {addr}: {e}");
+                return;
+            }
+            Err(_) => {
+                warn!("Handshake with {addr} timed out");
+                return;
+            }
+        };
+
+    info!("WebSocket connection established with: {portal_identifier}");
+
+    let portal = match conduit::from_sink_source_with_context(
+        nexus,
+        portal_identifier,
+        conduit.context,
+        conduit.sink,
+        conduit.source,
+    )
+    .await
+    {
+        Ok(portal) => portal,
+        Err(err) => {
+            error!("Error creating portal: {err}");
+            return;
+        }
+    };
+
+    slot.hold(portal).await;
 }
 
+/// upgrades a single connection, without any limits.
+/// This is useful if you accept the connections yourself; `start_server` handles each connection in its own task.
 pub async fn handle_connection(
     stream: TcpStream,
     addr: SocketAddr,
     nexus: ActorRef<NexusActorMessage>,
 ) {
-    // Upgrade the TCP connection to a WebSocket connection
-    let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+    let mut context = ConnectionContext {
+        remote_addr: Some(addr),
+        ..Default::default()
+    };
+
+    let ws_stream = match accept_websocket(stream, &mut context, WebSocketConfig::default()).await {
         Ok(ws_stream) => ws_stream,
         Err(e) => {
             error!("Error during WebSocket handshake: {e}");
@@ -51,62 +265,33 @@ pub async fn handle_connection(
 
     info!("WebSocket connection established with: {addr}");
 
-    let (tx, rx) = ws_stream.split();
-
-    let rx = map_ws_to_conduit(rx);
-    let tx = map_conduit_to_ws(tx);
-
+    let (tx, rx) = split_websocket(ws_stream);
     let portal_identifier = format!("ws://{addr}");
-    if let Err(err) = conduit::from_sink_source(nexus, portal_identifier, tx, rx).await {
+    if let Err(err) =
+        conduit::from_sink_source_with_context(nexus, portal_identifier, context, tx, rx).await
+    {
         error!("Error creating portal: {err}");
     }
 }
 
-// ---------------------------------------------------------------------------------
-
-pub fn map_conduit_to_ws<S>(
-    sink: futures::stream::SplitSink<WebSocketStream<S>, Message>,
-) -> ConduitSink
-where
-    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
-{
-    let sink = sink.with(|element: ConduitMessage| async {
-        let msg = match element {
-            ConduitMessage::Text(text) => Message::text(text),
-            ConduitMessage::Binary(bin) => Message::binary(bin),
-            ConduitMessage::Close(_) => Message::Close(None),
-        };
-        Ok(msg)
-    });
-    Box::pin(sink)
-}
-
-pub fn map_ws_to_conduit<T>(
-    source: futures::stream::SplitStream<WebSocketStream<T>>,
-) -> ConduitSource
+/// upgrades the connection to a websocket, remembering the path and headers of the request in the context.
+// the error type of the handshake callback is defined by tungstenite
+#[allow(clippy::result_large_err)]
+async fn accept_websocket<S>(
+    stream: S,
+    context: &mut ConnectionContext,
+    config: WebSocketConfig,
+) -> Result<WebSocketStream<S>, tokio_tungstenite::tungstenite::Error>
 where
-    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
 {
-    let source = source.filter_map(|element| {
-        let output = match element {
-            Ok(msg) => {
-                let msg = match msg {
-                    Message::Text(text) => Some(ConduitMessage::Text(text.to_string())),
-                    Message::Binary(bin) => Some(ConduitMessage::Binary(bin.into())),
-                    Message::Close(_) => Some(ConduitMessage::Close(None)),
-                    _ => None,
-                };
-
-                Ok(msg)
-            }
-            Err(e) => Err(ConduitError::from(e)),
-        };
-
-        match output {
-            Ok(Some(msg)) => future::ready(Some(Ok(msg))),
-            Ok(None) => future::ready(None),
-            Err(e) => future::ready(Some(Err(e))),
-        }
-    });
-    Box::pin(source)
+    tokio_tungstenite::accept_hdr_async_with_config(
+        stream,
+        |request: &Request, response: Response| {
+            context.add_request_metadata(request);
+            Ok(response)
+        },
+        Some(config),
+    )
+    .await
 }
@@ -0,0 +1,162 @@
+//! TLS (rustls) configuration for the websocket server and client.
+//!
+//! The server can optionally require clients to authenticate with a certificate (mutual TLS).
+//! The verified certificate chain is passed to the nexus in the `ConnectionContext`,
+//! so the application can decide what the remote side is allowed to do.
+
+use std::{path::Path, sync::Arc};
+
+use rustls::{
+    ClientConfig, RootCertStore, ServerConfig,
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::WebPkiClientVerifier,
+};
+
+pub use rustls;
+
+// -------------------------------------------------------------------------------------------------------
+
+/// reads all certificates from a PEM file.
+pub fn load_certificates(
+    path: impl AsRef<Path>,
+) -> Result<Vec<CertificateDer<'static>>, anyhow::Error> {
+    Ok(CertificateDer::pem_file_iter(path)?.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// reads the first private key (PKCS#1, PKCS#8 or SEC1) from a PEM file.
+pub fn load_private_key(path: impl AsRef<Path>) -> Result<PrivateKeyDer<'static>, anyhow::Error> {
+    Ok(PrivateKeyDer::from_pem_file(path)?)
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn root_store(certificates: &[CertificateDer<'static>]) -> Result<RootCertStore, anyhow::Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in certificates {
+        roots.add(cert.clone())?;
+    }
+    Ok(roots)
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+pub struct ServerTlsConfig {
+    /// the certificate chain of the server, leaf certificate first
+    pub cert_chain: Vec<CertificateDer<'static>>,
+    pub key: PrivateKeyDer<'static>,
+    /// if set, every client must present a certificate signed by one of these CAs.
+    pub client_ca_certificates: Option<Vec<CertificateDer<'static>>>,
+}
+
+impl ServerTlsConfig {
+    pub fn new(cert_chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Self {
+        Self {
+            cert_chain,
+            key,
+            client_ca_certificates: None,
+        }
+    }
+
+    pub fn from_pem_files(
+        cert_chain: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+    ) -> Result<Self, anyhow::Error> {
+        Ok(Self::new(
+            load_certificates(cert_chain)?,
+            load_private_key(key)?,
+        ))
+    }
+
+    /// require clients to authenticate with a certificate signed by one of the given CAs.
+    pub fn with_client_authentication(
+        mut self,
+        ca_certificates: Vec<CertificateDer<'static>>,
+    ) -> Self {
+        self.client_ca_certificates = Some(ca_certificates);
+        self
+    }
+
+    pub fn build(&self) -> Result<Arc<ServerConfig>, anyhow::Error> {
+        let provider = crypto_provider();
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+
+        let builder = match &self.client_ca_certificates {
+            Some(ca_certificates) => {
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(root_store(ca_certificates)?),
+                    provider,
+                )
+                .build()?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let config = builder.with_single_cert(self.cert_chain.clone(), self.key.clone_key())?;
+        Ok(Arc::new(config))
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+pub struct ClientTlsConfig {
+    /// additional trusted root certificates, e.g. a private CA or a self-signed server certificate
+    pub root_certificates: Vec<CertificateDer<'static>>,
+    /// also trust the Mozilla root certificates (bundled in `webpki-roots`)
+    pub use_webpki_roots: bool,
+    /// the certificate chain and private key used to authenticate against the server (mutual TLS)
+    pub client_certificate: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+}
+
+impl Default for ClientTlsConfig {
+    fn default() -> Self {
+        Self {
+            root_certificates: Vec::new(),
+            use_webpki_roots: true,
+            client_certificate: None,
+        }
+    }
+}
+
+impl ClientTlsConfig {
+    /// only trust the given root certificates, not the public CAs.
+    pub fn with_root_certificates(root_certificates: Vec<CertificateDer<'static>>) -> Self {
+        Self {
+            root_certificates,
+            use_webpki_roots: false,
+            client_certificate: None,
+        }
+    }
+
+    pub fn with_client_certificate(
+        mut self,
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Self {
+        self.client_certificate = Some((cert_chain, key));
+        self
+    }
+
+    pub fn build(&self) -> Result<Arc<ClientConfig>, anyhow::Error> {
+        let mut roots = root_store(&self.root_certificates)?;
+        if self.use_webpki_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+
+        let builder = ClientConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+
+        let config = match &self.client_certificate {
+            Some((cert_chain, key)) => {
+                builder.with_client_auth_cert(cert_chain.clone(), key.clone_key())?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        Ok(Arc::new(config))
+    }
+}
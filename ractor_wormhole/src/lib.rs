@@ -3,7 +3,6 @@
 #![feature(negative_impls)]
 #![feature(min_specialization)]
 #![feature(macro_metavar_expr_concat)]
-#![feature(concat_idents)]
 
 pub mod conduit;
 pub mod nexus;
@@ -3,12 +3,12 @@ use async_trait::async_trait;
 use log::info;
 use ractor::{
     Actor, ActorCell, ActorId, ActorProcessingErr, ActorRef, RpcReplyPort, SupervisionEvent,
-    concurrency::{Duration, JoinHandle},
+    concurrency::JoinHandle,
 };
 use std::collections::HashMap;
 
 use crate::{
-    conduit::ConduitSink,
+    conduit::{ConduitSink, ConnectionContext},
     portal::{
         BoxedRematerializer, ConduitID, LocalPortalId, OpaqueActorId, PortalActor, PortalActorArgs,
         PortalActorMessage, PortalConfig,
@@ -28,6 +28,7 @@ use crate::{
 pub enum NexusActorMessage {
     Connected(
         String,
+        ConnectionContext,
         ConduitSink,
         RpcReplyPort<ActorRef<PortalActorMessage>>,
     ),
@@ -58,10 +59,14 @@ pub struct NexusActorState {
 pub struct OnActorConnectedMessage {
     pub identifier: String,
     pub actor_ref: ActorRef<PortalActorMessage>,
+    /// information about the remote side, e.g. the verified client certificate
+    pub context: ConnectionContext,
 }
 
 pub struct NexusActorArgs {
     pub on_client_connected: Option<ActorRef<OnActorConnectedMessage>>,
+    /// the configuration of all portals of this nexus
+    pub portal_config: PortalConfig,
 }
 
 // Nexus actor implementation
@@ -91,7 +96,7 @@ impl Actor for NexusActor {
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
         match message {
-            NexusActorMessage::Connected(identifier, ws_stream, reply) => {
+            NexusActorMessage::Connected(identifier, context, ws_stream, reply) => {
                 info!("New WebSocket connection from: {identifier}");
 
                 // Create a new portal actor
@@ -103,7 +108,9 @@ impl Actor for NexusActor {
                         sender: ws_stream,
                         local_id: LocalPortalId(rand::random()),
                         config: PortalConfig {
-                            default_rpc_port_timeout: Duration::from_secs(120),
+                            binary_introduction: state.args.portal_config.binary_introduction
+                                || context.binary_introduction,
+                            ..state.args.portal_config.clone()
                         },
                         parent: myself.clone(),
                     },
@@ -121,6 +128,7 @@ impl Actor for NexusActor {
                     callback.send_message(OnActorConnectedMessage {
                         identifier,
                         actor_ref: actor_ref.clone(),
+                        context,
                     })?;
                 }
 
@@ -196,12 +204,22 @@ impl Actor for NexusActor {
 pub async fn start_nexus(
     name: Option<String>,
     on_client_connected: Option<ActorRef<OnActorConnectedMessage>>,
+) -> Result<ActorRef<NexusActorMessage>, ractor::ActorProcessingErr> {
+    start_nexus_with_config(name, on_client_connected, PortalConfig::default()).await
+}
+
+/// like `start_nexus`, but with a non-default configuration for the portals.
+pub async fn start_nexus_with_config(
+    name: Option<String>,
+    on_client_connected: Option<ActorRef<OnActorConnectedMessage>>,
+    portal_config: PortalConfig,
 ) -> Result<ActorRef<NexusActorMessage>, ractor::ActorProcessingErr> {
     let (nexus_ref, _handle) = NexusActor::spawn(
         Some(name.unwrap_or(String::from("nexus"))),
         NexusActor,
         NexusActorArgs {
             on_client_connected,
+            portal_config,
         },
     )
     .await?;
@@ -1,14 +1,20 @@
 use async_trait::async_trait;
-use futures::SinkExt;
 use log::{error, info};
 use ractor::{
     Actor, ActorCell, ActorProcessingErr, ActorRef, RpcReplyPort, SupervisionEvent, actor,
     concurrency::Duration,
 };
-use std::{collections::HashMap, fmt::Display, pin::Pin};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+    pin::Pin,
+};
 
 use crate::{
-    conduit::{ConduitMessage, ConduitSink},
+    conduit::{
+        CloseReason, ConduitMessage, ConduitSink,
+        priority::{BatchConfig, LaneWeights, Priority, PriorityOutbox},
+    },
     nexus::{NexusActorMessage, RemoteActorId},
     transmaterialization::{
         ContextTransmaterializable, GetRematerializer, TransmaterializationContext,
@@ -52,6 +58,26 @@ impl Display for ConduitID {
 #[derive(Clone, Debug, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
 pub struct PortalConfig {
     pub default_rpc_port_timeout: Duration,
+    /// send the compact binary `Introduction` instead of json, see `ConnectionContext::binary_introduction`
+    pub binary_introduction: bool,
+    /// the share of the conduit each priority lane gets while several lanes have frames queued
+    pub lane_weights: LaneWeights,
+    /// how many frames each priority lane may hold before the portal waits for the conduit
+    pub lane_capacity: usize,
+    /// coalesce small outgoing frames, see `BatchConfig`. Off by default.
+    pub batching: Option<BatchConfig>,
+}
+
+impl Default for PortalConfig {
+    fn default() -> Self {
+        Self {
+            default_rpc_port_timeout: Duration::from_secs(120),
+            binary_introduction: false,
+            lane_weights: LaneWeights::default(),
+            lane_capacity: 64,
+            batching: None,
+        }
+    }
 }
 
 // -------------------------------------------------------------------------------------------------------
@@ -87,7 +113,7 @@ impl From<ActorRequestError> for anyhow::Error {
 
 /// these are the raw messages that are actually sent over the wire.
 /// Note that these are sent **after** the initial handshake.
-/// (the initial handshake is a json serialized `Introduction`)
+/// (the initial handshake is a json or binary serialized `Introduction`)
 #[derive(Debug, bincode::Encode, bincode::Decode)]
 pub enum CrossPortalMessage {
     RequestActorByName(CrossPortalMessageId, String),
@@ -104,6 +130,32 @@ pub enum CrossPortalMessage {
     SendMessage(RemoteActorId, Box<[u8]>),
 
     ActorExited(RemoteActorId),
+
+    /// like `SendMessage`, but the target is the proxy of a `RpcReplyPort`.
+    /// It is handled identically on the receiving side, but lets the conduit treat replies as control traffic.
+    SendReply(RemoteActorId, Box<[u8]>),
+
+    /// several immaterialized messages which were sent together, see `BatchConfig`.
+    /// They are handled in order, as if they had been received one by one.
+    Batch(Vec<Box<[u8]>>),
+}
+
+impl CrossPortalMessage {
+    /// immaterializes the message into a frame for the conduit.
+    /// Application messages become a `ConduitMessage::Bulk`, so conduits can separate them from the control traffic.
+    pub fn to_conduit_message(&self) -> NexusResult<ConduitMessage> {
+        let bytes = self.immaterialize()?;
+        Ok(match self {
+            CrossPortalMessage::SendMessage(..) => ConduitMessage::Bulk(bytes),
+            CrossPortalMessage::RequestActorByName(..)
+            | CrossPortalMessage::ResponseActorByName(..)
+            | CrossPortalMessage::ResponseActorById(..)
+            | CrossPortalMessage::ActorExited(..)
+            | CrossPortalMessage::SendReply(..) => ConduitMessage::Binary(bytes),
+            // the frames are already immaterialized, so the outbox decides (see `BatchConfig`)
+            CrossPortalMessage::Batch(..) => ConduitMessage::Binary(bytes),
+        })
+    }
 }
 
 // Portal
@@ -155,10 +207,14 @@ pub enum PortalActorMessage {
     // data received from websocket
     Text(String),
     Binary(Vec<u8>),
-    Close,
+    /// the conduit was closed, by the remote side (with its reason) or because of an error.
+    Close(Option<CloseReason>),
+
+    /// closes the conduit (sending the reason to the remote side) and stops the portal.
+    Disconnect(Option<CloseReason>),
 
-    ImmaterializeMessage(RemoteActorId, TransmitMessageF),
-    TransmitMessage(RemoteActorId, Vec<u8>),
+    ImmaterializeMessage(RemoteActorId, Priority, TransmitMessageF),
+    TransmitMessage(RemoteActorId, Priority, Vec<u8>),
 
     /// publish a local actor under a known name, making it available to the remote side of the portal.
     /// On the remote side, it can be looked up by name.
@@ -181,12 +237,18 @@ pub enum PortalActorMessage {
 
     RegisterProxyForRemoteActor(RemoteActorId, ActorCell),
 
+    /// marks a remote actor as the proxy of a `RpcReplyPort`, so messages to it are sent as `CrossPortalMessage::SendReply`.
+    RegisterReplyTarget(RemoteActorId),
+
     /// looks up an actor by name on the **remote** side of the portal. Returns None if no actor was registered under that name.
     QueryNamedRemoteActor(String, RpcReplyPort<NexusResult<RemoteActorId>>),
 
     /// waits until the portal is fully opened
     WaitForHandshake(RpcReplyPort<()>),
 
+    /// waits until the portal is closed, and returns the reason of the close
+    WaitForClose(RpcReplyPort<Option<CloseReason>>),
+
     LocalActorExited(ractor::ActorId),
 }
 
@@ -203,6 +265,16 @@ pub trait Portal {
         remote_actor_id: RemoteActorId,
     ) -> NexusResult<ActorRef<T>>;
 
+    /// like `instantiate_proxy_for_remote_actor`, but all messages sent through the proxy use the given lane,
+    /// regardless of the `priority` of the message.
+    async fn instantiate_proxy_for_remote_actor_with_priority<
+        T: ContextTransmaterializable + ractor::Message + Send + Sync + std::fmt::Debug,
+    >(
+        &self,
+        remote_actor_id: RemoteActorId,
+        priority: Priority,
+    ) -> NexusResult<ActorRef<T>>;
+
     async fn publish_named_actor<T: ContextTransmaterializable + ractor::Message + Send + Sync>(
         &self,
         name: String,
@@ -210,6 +282,10 @@ pub trait Portal {
     ) -> NexusResult<()>;
 
     async fn wait_for_opened(&self, timeout: Duration) -> NexusResult<()>;
+
+    /// waits until the portal is closed, and returns the reason: the one sent by the remote side, or the one passed to `Disconnect`.
+    /// It is `None` if the conduit failed or was closed without a reason. Fails if the portal is already stopped.
+    async fn wait_for_closed(&self) -> NexusResult<Option<CloseReason>>;
 }
 
 #[async_trait]
@@ -220,49 +296,17 @@ impl Portal for ActorRef<PortalActorMessage> {
         &self,
         remote_actor_id: RemoteActorId,
     ) -> NexusResult<ActorRef<T>> {
-        let portal_ref = self.clone();
-
-        let (proxy_actor_ref, _handle) =
-            FnActor::<T>::start_fn_linked(self.get_cell(), async move |mut ctx| {
-                let type_str = std::any::type_name::<T>();
-
-                info!("Proxy actor started {type_str}");
-
-                while let Some(msg) = ctx.rx.recv().await {
-                    info!("Proxy actor received msg: {msg:#?} [{type_str}]");
-                    let remote_id = remote_actor_id;
-
-                    let f: TransmitMessageF = Box::new(move |ctx| {
-                        info!("f inside Proxy Actor was called: {msg:#?} [{type_str}]");
-                        // Create a regular closure that returns a boxed and pinned future
-                        Box::pin(async move {
-                            let bytes: Vec<u8> =
-                                crate::transmaterialization::ContextTransmaterializable::immaterialize(
-                                    msg, &ctx,
-                                )
-                                .await?;
-                            Ok(bytes)
-                        })
-                    });
-
-                    if let Err(err) =
-                        portal_ref.send_message(PortalActorMessage::ImmaterializeMessage(remote_id, f))
-                    {
-                        error!("Failed to send message to portal: {err}");
-                    }
-
-                    info!(
-                        "Proxy actor sent WSPortalMessage::TransmitMessage to portal: {remote_id:#?} [{type_str}]"
-                    );
-                }
-            })
-            .await?;
+        instantiate_proxy(self, remote_actor_id, None).await
+    }
 
-        self.send_message(PortalActorMessage::RegisterProxyForRemoteActor(
-            remote_actor_id,
-            proxy_actor_ref.get_cell(),
-        ))?;
-        Ok(proxy_actor_ref)
+    async fn instantiate_proxy_for_remote_actor_with_priority<
+        T: ContextTransmaterializable + ractor::Message + Send + Sync + std::fmt::Debug,
+    >(
+        &self,
+        remote_actor_id: RemoteActorId,
+        priority: Priority,
+    ) -> NexusResult<ActorRef<T>> {
+        instantiate_proxy(self, remote_actor_id, Some(priority)).await
     }
 
     async fn publish_named_actor<T: ContextTransmaterializable + ractor::Message + Send + Sync>(
@@ -289,11 +333,101 @@ impl Portal for ActorRef<PortalActorMessage> {
 
         Ok(response)
     }
+
+    async fn wait_for_closed(&self) -> NexusResult<Option<CloseReason>> {
+        let response = self.ask(PortalActorMessage::WaitForClose, None).await?;
+
+        Ok(response)
+    }
+}
+
+/// starts a proxy actor which sends its messages to the remote actor, in the given lane or in the lane of each message.
+async fn instantiate_proxy<
+    T: ContextTransmaterializable + ractor::Message + Send + Sync + std::fmt::Debug,
+>(
+    portal: &ActorRef<PortalActorMessage>,
+    remote_actor_id: RemoteActorId,
+    priority: Option<Priority>,
+) -> NexusResult<ActorRef<T>> {
+    let portal_ref = portal.clone();
+
+    let (proxy_actor_ref, _handle) =
+        FnActor::<T>::start_fn_linked(portal.get_cell(), async move |mut ctx| {
+            let type_str = std::any::type_name::<T>();
+
+            info!("Proxy actor started {type_str}");
+
+            while let Some(msg) = ctx.rx.recv().await {
+                info!("Proxy actor received msg: {msg:#?} [{type_str}]");
+                let remote_id = remote_actor_id;
+                let priority = priority.unwrap_or_else(|| msg.priority());
+
+                let f: TransmitMessageF = Box::new(move |ctx| {
+                    info!("f inside Proxy Actor was called: {msg:#?} [{type_str}]");
+                    // Create a regular closure that returns a boxed and pinned future
+                    Box::pin(async move {
+                        let bytes: Vec<u8> =
+                            crate::transmaterialization::ContextTransmaterializable::immaterialize(
+                                msg, &ctx,
+                            )
+                            .await?;
+                        Ok(bytes)
+                    })
+                });
+
+                if let Err(err) =
+                    portal_ref.send_message(PortalActorMessage::ImmaterializeMessage(remote_id, priority, f))
+                {
+                    error!("Failed to send message to portal: {err}");
+                }
+
+                info!(
+                    "Proxy actor sent WSPortalMessage::TransmitMessage to portal: {remote_id:#?} [{type_str}]"
+                );
+            }
+        })
+        .await?;
+
+    portal.send_message(PortalActorMessage::RegisterProxyForRemoteActor(
+        remote_actor_id,
+        proxy_actor_ref.get_cell(),
+    ))?;
+    Ok(proxy_actor_ref)
 }
 
 // Portal actor
 pub struct PortalActor;
 
+/// how many exits of remote actors a portal remembers, see `ExitedRemoteActors`
+const REMEMBERED_EXITS: usize = 4096;
+
+/// the remote actors which exited most recently.
+/// Over a conduit which doesn't keep the order of the frames (like QUIC, where messages are sent on the bulk stream),
+/// a message which refers to an actor can arrive after the exit of the actor; its proxy is then stopped right away.
+#[derive(Default)]
+struct ExitedRemoteActors {
+    ids: HashSet<RemoteActorId>,
+    order: VecDeque<RemoteActorId>,
+}
+
+impl ExitedRemoteActors {
+    fn insert(&mut self, id: RemoteActorId) {
+        if !self.ids.insert(id) {
+            return;
+        }
+        self.order.push_back(id);
+        if self.order.len() > REMEMBERED_EXITS
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+    }
+
+    fn contains(&self, id: &RemoteActorId) -> bool {
+        self.ids.contains(id)
+    }
+}
+
 pub enum PortalConduitState {
     Opening {
         self_introduction: Introduction,
@@ -305,8 +439,9 @@ pub enum PortalConduitState {
     },
 }
 
-// note: the introduction is json serialized
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+// note: the introduction is json serialized, unless the conduit asked for the binary encoding.
+//  The receiving side accepts both.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
 pub struct Introduction {
     pub channel_id_contribution: uuid::Bytes,
     pub version: String,
@@ -314,17 +449,46 @@ pub struct Introduction {
     pub this_side_id: LocalPortalId,
 }
 
+impl Introduction {
+    /// prefixed to the binary introduction, so that garbage isn't mistaken for one.
+    const BINARY_MAGIC: [u8; 4] = *b"RWI1";
+
+    pub fn to_binary(&self) -> NexusResult<Vec<u8>> {
+        let mut bytes = Self::BINARY_MAGIC.to_vec();
+        bincode::encode_into_std_write(self, &mut bytes, bincode::config::standard())?;
+        Ok(bytes)
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> NexusResult<Self> {
+        let Some(payload) = bytes.strip_prefix(&Self::BINARY_MAGIC) else {
+            return Err(anyhow::anyhow!(
+                "Expected a binary introduction, but received {} bytes without the magic prefix",
+                bytes.len()
+            ));
+        };
+        let (introduction, _) = bincode::decode_from_slice(payload, bincode::config::standard())?;
+        Ok(introduction)
+    }
+}
+
 pub struct PortalActorState {
-    args: PortalActorArgs,
+    identifier: String,
+    local_id: LocalPortalId,
+    config: PortalConfig,
+    parent: ActorRef<NexusActorMessage>,
+    outbox: PriorityOutbox,
     channel_state: PortalConduitState,
     published_actors: HashMap<OpaqueActorId, (ActorCell, BoxedRematerializer)>,
     proxies_for_remote_actors: HashMap<RemoteActorId, ActorCell>,
+    exited_remote_actors: ExitedRemoteActors,
+    reply_targets: HashSet<RemoteActorId>,
     named_actors: HashMap<String, OpaqueActorId>,
 
     next_request_id: u64,
     open_requests: HashMap<CrossPortalMessageId, RpcReplyPort<NexusResult<RemoteActorId>>>,
 
     waiting_for_handshake: Vec<RpcReplyPort<()>>,
+    waiting_for_close: Vec<RpcReplyPort<Option<CloseReason>>>,
 }
 
 pub struct PortalActorArgs {
@@ -344,6 +508,201 @@ fn xor_arrays(a: [u8; 16], b: [u8; 16]) -> [u8; 16] {
 }
 
 impl PortalActor {
+    fn complete_handshake(&self, state: &mut PortalActorState, remote_introduction: Introduction) {
+        let PortalConduitState::Opening { self_introduction } = &state.channel_state else {
+            return;
+        };
+
+        info!(
+            "Received introduction from {}: {:?}",
+            state.identifier, remote_introduction
+        );
+        let channel_id = ConduitID(u128::from_le_bytes(xor_arrays(
+            self_introduction.channel_id_contribution,
+            remote_introduction.channel_id_contribution,
+        )));
+
+        info!("Handshake complete, channel_id: {channel_id}");
+
+        state.channel_state = PortalConduitState::Open {
+            // self_introduction: self_introduction.clone(),
+            // remote_introduction,
+            channel_id,
+        };
+
+        for x in state.waiting_for_handshake.drain(..) {
+            let _ = x.send(());
+        }
+    }
+
+    fn notify_closed(&self, state: &mut PortalActorState, reason: Option<CloseReason>) {
+        for x in state.waiting_for_close.drain(..) {
+            let _ = x.send(reason.clone());
+        }
+    }
+
+    /// handles a message from the remote portal, after the handshake.
+    async fn handle_cross_portal_message(
+        &self,
+        myself: ActorRef<PortalActorMessage>,
+        msg: CrossPortalMessage,
+        state: &mut PortalActorState,
+    ) -> Result<(), ActorProcessingErr> {
+        let PortalConduitState::Open { channel_id } = &state.channel_state else {
+            return Err(anyhow::anyhow!("Received a message before the handshake").into());
+        };
+        let channel_id = *channel_id;
+
+        match msg {
+            CrossPortalMessage::RequestActorByName(id, name) => {
+                // Look up the named actor in our local registry
+                let opaque_id = state.named_actors.get(&name).cloned();
+
+                let response = match opaque_id {
+                    Some(opaque_id) => {
+                        // Construct a RemoteActorId for the actor
+                        let remote_id = RemoteActorId {
+                            connection_key: channel_id,
+                            side: state.local_id,
+                            id: opaque_id,
+                        };
+
+                        Ok(remote_id)
+                    }
+                    None => {
+                        // we didn't find it in the portal registry, but not all is lost, it could be in the nexus registry.
+                        let nexus_actor = state.parent.clone();
+                        let actor_published_on_nexus = nexus_actor
+                            .ask(|rpc| NexusActorMessage::QueryNamedActor(name, rpc), None)
+                            .await?;
+
+                        if let Some((actor_cell, receiver)) = actor_published_on_nexus {
+                            let opaque_actor_id = self.publish_actor(
+                                myself,
+                                &mut state.published_actors,
+                                actor_cell,
+                                receiver,
+                            );
+
+                            // Construct a RemoteActorId for the actor
+                            let remote_id = RemoteActorId {
+                                connection_key: channel_id,
+                                side: state.local_id,
+                                id: opaque_actor_id,
+                            };
+
+                            Ok(remote_id)
+                        } else {
+                            Err(ActorRequestError::ActorNotFound)
+                        }
+                    }
+                };
+
+                // Send response back
+                let response_msg = CrossPortalMessage::ResponseActorByName(id, response);
+                let data = bincode::encode_to_vec(response_msg, bincode::config::standard())?;
+                state
+                    .outbox
+                    .send(Priority::Control, ConduitMessage::Binary(data))
+                    .await?;
+            }
+
+            // CrossNexusMessage::RequestActorById(id, opaque_id) => {
+            //     // Look up the actor in our registry
+            //     let published_actor = state.published_actors.get(&opaque_id);
+
+            //     let response = match published_actor {
+            //         Some(actor_id) => {
+            //             // Construct a RemoteActorId for the actor
+            //             let remote_id = RemoteActorId {
+            //                 connection_key: channel_id,
+            //                 side: state.local_id,
+            //                 id: opaque_id,
+            //             };
+
+            //             Ok(remote_id)
+            //         }
+            //         None => Err(ActorRequestError::ActorNotFound),
+            //     };
+
+            //     // Send response back
+            //     let response_msg =
+            //         CrossNexusMessage::ResponseActorById(id, response);
+            //     let data = bincode::encode_to_vec(
+            //         response_msg,
+            //         bincode::config::standard(),
+            //     )?;
+            //     state.args.sender.send(RawMessage::Binary(data)).await?;
+            //     state.args.sender.flush().await?;
+            // }
+            CrossPortalMessage::ResponseActorByName(id, response) => {
+                // Handle response to our earlier request
+                if let Some(reply_port) = state.open_requests.remove(&id) {
+                    let mapped: NexusResult<RemoteActorId> = response.map_err(|err| err.into());
+                    reply_port.send(mapped)?;
+                } else {
+                    error!("Received response for unknown request ID: {id}");
+                }
+            }
+
+            CrossPortalMessage::ResponseActorById(id, response) => {
+                // Handle response to our earlier request
+                if let Some(reply_port) = state.open_requests.remove(&id) {
+                    let mapped: NexusResult<RemoteActorId> = response.map_err(|err| err.into());
+                    reply_port.send(mapped)?;
+                } else {
+                    error!("Received response for unknown request ID: {id}");
+                }
+            }
+
+            CrossPortalMessage::SendMessage(target_id, data)
+            | CrossPortalMessage::SendReply(target_id, data) => {
+                // Find the local actor from the remote target
+                if let Some((local_actor_cell, receiver)) =
+                    state.published_actors.get(&target_id.id)
+                {
+                    receiver
+                        .rematerialize(
+                            local_actor_cell.clone(),
+                            &data,
+                            TransmaterializationContext {
+                                connection: myself.clone(),
+                                default_rpc_port_timeout: state.config.default_rpc_port_timeout,
+                            },
+                        )
+                        .await?;
+                } else {
+                    error!(
+                        "Remote actor ID {} not found in published actors",
+                        target_id.id
+                    );
+                }
+            }
+
+            CrossPortalMessage::ActorExited(remote_actor_id) => {
+                // the proxy of a RpcReplyPort exits once it has forwarded the reply, or when it timed out
+                state.reply_targets.remove(&remote_actor_id);
+                state.exited_remote_actors.insert(remote_actor_id);
+
+                let Some(actor_cell) = state.proxies_for_remote_actors.remove(&remote_actor_id)
+                else {
+                    // no proxy yet, maybe the message which refers to the actor is still on its way
+                    info!("Received ActorExited for unknown remote actor: {remote_actor_id:?}");
+                    return Ok(());
+                };
+
+                actor_cell.stop(Some(
+                    "Proxy for remote actor is being shutdown because the real actor exited".into(),
+                ));
+            }
+
+            CrossPortalMessage::Batch(_) => {
+                error!("Received a nested batch from {}", state.identifier);
+            }
+        }
+        Ok(())
+    }
+
     fn publish_actor(
         &self,
         myself: ActorRef<PortalActorMessage>,
@@ -396,8 +755,8 @@ impl Actor for PortalActor {
 
     async fn pre_start(
         &self,
-        _myself: ActorRef<Self::Msg>,
-        mut args: Self::Arguments,
+        myself: ActorRef<Self::Msg>,
+        args: Self::Arguments,
     ) -> Result<Self::State, ActorProcessingErr> {
         const MOTIVATIONAL_MESSAGES: [&str; 5] = [
             "Lookin' good!",
@@ -415,22 +774,41 @@ impl Actor for PortalActor {
             info_text: msg.to_string(),
             this_side_id: args.local_id,
         };
-        let text = serde_json::to_string_pretty(&introduction)?;
+        let message = if args.config.binary_introduction {
+            ConduitMessage::Binary(introduction.to_binary()?)
+        } else {
+            ConduitMessage::Text(serde_json::to_string_pretty(&introduction)?)
+        };
 
-        args.sender.send(ConduitMessage::Text(text)).await?;
-        args.sender.flush().await?;
+        let outbox = PriorityOutbox::spawn(
+            args.sender,
+            args.config.lane_weights,
+            args.config.lane_capacity,
+            args.config.batching,
+            myself,
+            args.identifier.clone(),
+        );
+        // the peer expects the introduction as a frame of its own
+        outbox.send_unbatched(Priority::Control, message).await?;
 
         Ok(PortalActorState {
-            args,
+            identifier: args.identifier,
+            local_id: args.local_id,
+            config: args.config,
+            parent: args.parent,
+            outbox,
             channel_state: PortalConduitState::Opening {
                 self_introduction: introduction,
             },
             published_actors: HashMap::new(),
             proxies_for_remote_actors: HashMap::new(),
+            exited_remote_actors: ExitedRemoteActors::default(),
+            reply_targets: HashSet::new(),
             named_actors: HashMap::new(),
             open_requests: HashMap::new(),
             next_request_id: 1,
             waiting_for_handshake: Vec::new(),
+            waiting_for_close: Vec::new(),
         })
     }
 
@@ -442,34 +820,12 @@ impl Actor for PortalActor {
     ) -> Result<(), ActorProcessingErr> {
         match message {
             PortalActorMessage::Text(text) => {
-                info!(
-                    "Received text message from {}: {}",
-                    state.args.identifier, text
-                );
+                info!("Received text message from {}: {}", state.identifier, text);
 
                 match &state.channel_state {
-                    PortalConduitState::Opening { self_introduction } => {
+                    PortalConduitState::Opening { .. } => {
                         let remote_introduction: Introduction = serde_json::from_str(&text)?;
-                        info!(
-                            "Received introduction from {}: {:?}",
-                            state.args.identifier, remote_introduction
-                        );
-                        let channel_id = ConduitID(u128::from_le_bytes(xor_arrays(
-                            self_introduction.channel_id_contribution,
-                            remote_introduction.channel_id_contribution,
-                        )));
-
-                        info!("Handshake complete, channel_id: {channel_id}");
-
-                        state.channel_state = PortalConduitState::Open {
-                            // self_introduction: self_introduction.clone(),
-                            // remote_introduction,
-                            channel_id,
-                        };
-
-                        for x in state.waiting_for_handshake.drain(..) {
-                            let _ = x.send(());
-                        }
+                        self.complete_handshake(state, remote_introduction);
                     }
                     PortalConduitState::Open { .. } => {
                         panic!("Received text message after handshake: {text}");
@@ -479,177 +835,61 @@ impl Actor for PortalActor {
             PortalActorMessage::Binary(data) => {
                 info!(
                     "Received binary message from {}: {} bytes",
-                    state.args.identifier,
+                    state.identifier,
                     data.len()
                 );
 
                 match &state.channel_state {
                     PortalConduitState::Opening { .. } => {
-                        panic!(
-                            "Received binary message before handshake: {} bytes",
-                            data.len()
-                        );
+                        // before the handshake, the only binary message is a binary introduction
+                        let remote_introduction = Introduction::from_binary(&data)?;
+                        self.complete_handshake(state, remote_introduction);
                     }
-                    PortalConduitState::Open { channel_id, .. } => {
+                    PortalConduitState::Open { .. } => {
                         let msg = CrossPortalMessage::rematerialize(&data)?;
-                        info!("Received message from {}: {:?}", state.args.identifier, msg);
+                        info!("Received message from {}: {:?}", state.identifier, msg);
 
                         match msg {
-                            CrossPortalMessage::RequestActorByName(id, name) => {
-                                // Look up the named actor in our local registry
-                                let opaque_id = state.named_actors.get(&name).cloned();
-
-                                let response = match opaque_id {
-                                    Some(opaque_id) => {
-                                        // Construct a RemoteActorId for the actor
-                                        let remote_id = RemoteActorId {
-                                            connection_key: *channel_id,
-                                            side: state.args.local_id,
-                                            id: opaque_id,
-                                        };
-
-                                        Ok(remote_id)
-                                    }
-                                    None => {
-                                        // we didn't find it in the portal registry, but not all is lost, it could be in the nexus registry.
-                                        let nexus_actor = state.args.parent.clone();
-                                        let actor_published_on_nexus = nexus_actor
-                                            .ask(
-                                                |rpc| NexusActorMessage::QueryNamedActor(name, rpc),
-                                                None,
-                                            )
-                                            .await?;
-
-                                        if let Some((actor_cell, receiver)) =
-                                            actor_published_on_nexus
-                                        {
-                                            let opaque_actor_id = self.publish_actor(
-                                                myself,
-                                                &mut state.published_actors,
-                                                actor_cell,
-                                                receiver,
-                                            );
-
-                                            // Construct a RemoteActorId for the actor
-                                            let remote_id = RemoteActorId {
-                                                connection_key: *channel_id,
-                                                side: state.args.local_id,
-                                                id: opaque_actor_id,
-                                            };
-
-                                            Ok(remote_id)
-                                        } else {
-                                            Err(ActorRequestError::ActorNotFound)
-                                        }
-                                    }
-                                };
-
-                                // Send response back
-                                let response_msg =
-                                    CrossPortalMessage::ResponseActorByName(id, response);
-                                let data = bincode::encode_to_vec(
-                                    response_msg,
-                                    bincode::config::standard(),
-                                )?;
-                                state.args.sender.send(ConduitMessage::Binary(data)).await?;
-                                state.args.sender.flush().await?;
-                            }
-
-                            // CrossNexusMessage::RequestActorById(id, opaque_id) => {
-                            //     // Look up the actor in our registry
-                            //     let published_actor = state.published_actors.get(&opaque_id);
-
-                            //     let response = match published_actor {
-                            //         Some(actor_id) => {
-                            //             // Construct a RemoteActorId for the actor
-                            //             let remote_id = RemoteActorId {
-                            //                 connection_key: *channel_id,
-                            //                 side: state.args.local_id,
-                            //                 id: opaque_id,
-                            //             };
-
-                            //             Ok(remote_id)
-                            //         }
-                            //         None => Err(ActorRequestError::ActorNotFound),
-                            //     };
-
-                            //     // Send response back
-                            //     let response_msg =
-                            //         CrossNexusMessage::ResponseActorById(id, response);
-                            //     let data = bincode::encode_to_vec(
-                            //         response_msg,
-                            //         bincode::config::standard(),
-                            //     )?;
-                            //     state.args.sender.send(RawMessage::Binary(data)).await?;
-                            //     state.args.sender.flush().await?;
-                            // }
-                            CrossPortalMessage::ResponseActorByName(id, response) => {
-                                // Handle response to our earlier request
-                                if let Some(reply_port) = state.open_requests.remove(&id) {
-                                    let mapped: NexusResult<RemoteActorId> =
-                                        response.map_err(|err| err.into());
-                                    reply_port.send(mapped)?;
-                                } else {
-                                    error!("Received response for unknown request ID: {id}");
-                                }
-                            }
-
-                            CrossPortalMessage::ResponseActorById(id, response) => {
-                                // Handle response to our earlier request
-                                if let Some(reply_port) = state.open_requests.remove(&id) {
-                                    let mapped: NexusResult<RemoteActorId> =
-                                        response.map_err(|err| err.into());
-                                    reply_port.send(mapped)?;
-                                } else {
-                                    error!("Received response for unknown request ID: {id}");
-                                }
-                            }
-
-                            CrossPortalMessage::SendMessage(target_id, data) => {
-                                // Find the local actor from the remote target
-                                if let Some((local_actor_cell, receiver)) =
-                                    state.published_actors.get(&target_id.id)
-                                {
-                                    receiver
-                                        .rematerialize(
-                                            local_actor_cell.clone(),
-                                            &data,
-                                            TransmaterializationContext {
-                                                connection: myself.clone(),
-                                                default_rpc_port_timeout: state
-                                                    .args
-                                                    .config
-                                                    .default_rpc_port_timeout,
-                                            },
-                                        )
+                            CrossPortalMessage::Batch(frames) => {
+                                for frame in frames {
+                                    let msg = CrossPortalMessage::rematerialize(&frame)?;
+                                    self.handle_cross_portal_message(myself.clone(), msg, state)
                                         .await?;
-                                } else {
-                                    error!(
-                                        "Remote actor ID {} not found in published actors",
-                                        target_id.id
-                                    );
                                 }
                             }
-
-                            CrossPortalMessage::ActorExited(remote_actor_id) => {
-                                let Some(actor_cell) =
-                                    state.proxies_for_remote_actors.remove(&remote_actor_id)
-                                else {
-                                    error!(
-                                        "Received ActorExited for unknown remote actor: {remote_actor_id:?}"
-                                    );
-                                    return Ok(());
-                                };
-
-                                actor_cell.stop(Some("Proxy for remote actor is being shutdown because the real actor exited".into()));
-                            }
+                            msg => self.handle_cross_portal_message(myself, msg, state).await?,
                         }
                     }
                 }
             }
-            PortalActorMessage::Close => {
-                info!("Closing portal to {}", state.args.identifier);
-                myself.stop(Some("Portal closed".into()));
+            PortalActorMessage::Close(reason) => {
+                info!("Closing portal to {}: {reason:?}", state.identifier);
+                let stop_reason = reason
+                    .as_ref()
+                    .map_or_else(|| "Portal closed".to_string(), CloseReason::to_string);
+                self.notify_closed(state, reason);
+                myself.stop(Some(stop_reason));
+            }
+            PortalActorMessage::Disconnect(reason) => {
+                info!("Disconnecting portal to {}: {reason:?}", state.identifier);
+                // the close is sent after the frames which are already queued
+                if let Err(err) = state
+                    .outbox
+                    .send(Priority::Control, ConduitMessage::Close(reason.clone()))
+                    .await
+                {
+                    error!("Error sending close to {}: {err}", state.identifier);
+                }
+                let stop_reason = reason
+                    .as_ref()
+                    .map_or_else(|| "Portal disconnected".to_string(), CloseReason::to_string);
+                self.notify_closed(state, reason);
+                myself.stop(Some(stop_reason));
+            }
+
+            PortalActorMessage::WaitForClose(reply) => {
+                state.waiting_for_close.push(reply);
+                return Ok(());
             }
 
             PortalActorMessage::WaitForHandshake(reply) => {
@@ -685,7 +925,7 @@ impl Actor for PortalActor {
                     if let PortalConduitState::Open { channel_id } = &state.channel_state {
                         let remote_actor_id = RemoteActorId {
                             connection_key: *channel_id,
-                            side: state.args.local_id,
+                            side: state.local_id,
                             id: opaque_actor_id,
                         };
                         rpc.send(Some(remote_actor_id))?;
@@ -713,7 +953,7 @@ impl Actor for PortalActor {
 
                     let remote_actor_id = RemoteActorId {
                         connection_key: *channel_id,
-                        side: state.args.local_id,
+                        side: state.local_id,
                         id: opaque_actor_id,
                     };
 
@@ -735,14 +975,12 @@ impl Actor for PortalActor {
                 let request = CrossPortalMessage::RequestActorByName(request_id, name);
                 let bytes = request.immaterialize()?;
                 state
-                    .args
-                    .sender
-                    .send(ConduitMessage::Binary(bytes))
+                    .outbox
+                    .send(Priority::Control, ConduitMessage::Binary(bytes))
                     .await?;
-                state.args.sender.flush().await?;
             }
 
-            PortalActorMessage::ImmaterializeMessage(target, msg_f) => {
+            PortalActorMessage::ImmaterializeMessage(target, priority, msg_f) => {
                 let PortalConduitState::Open { .. } = &state.channel_state else {
                     error!("TransmitMessage called before handshake");
                     return Ok(());
@@ -756,7 +994,7 @@ impl Actor for PortalActor {
                 // );
 
                 let myself_copy = myself.clone();
-                let default_rpc_port_timeout = state.args.config.default_rpc_port_timeout;
+                let default_rpc_port_timeout = state.config.default_rpc_port_timeout;
                 let target_copy = target;
                 ractor::concurrency::spawn(async move {
                     let bytes = msg_f(TransmaterializationContext {
@@ -767,25 +1005,36 @@ impl Actor for PortalActor {
                     .unwrap(); // todo: fix unwrap
                     // info!("Serialized! Now sending ...");
 
-                    let _ = myself_copy
-                        .send_message(PortalActorMessage::TransmitMessage(target_copy, bytes));
+                    let _ = myself_copy.send_message(PortalActorMessage::TransmitMessage(
+                        target_copy,
+                        priority,
+                        bytes,
+                    ));
                 });
             }
 
-            PortalActorMessage::TransmitMessage(target, bytes) => {
+            PortalActorMessage::TransmitMessage(target, priority, bytes) => {
                 let PortalConduitState::Open { .. } = &state.channel_state else {
                     error!("TransmitMessage called before handshake");
                     return Ok(());
                 };
 
-                let request = CrossPortalMessage::SendMessage(target, bytes.into_boxed_slice());
-                let bytes = request.immaterialize()?;
+                // a RpcReplyPort can only be used once
+                let (request, priority) = if state.reply_targets.remove(&target) {
+                    (
+                        CrossPortalMessage::SendReply(target, bytes.into_boxed_slice()),
+                        Priority::Reply,
+                    )
+                } else {
+                    (
+                        CrossPortalMessage::SendMessage(target, bytes.into_boxed_slice()),
+                        priority,
+                    )
+                };
                 state
-                    .args
-                    .sender
-                    .send(ConduitMessage::Binary(bytes))
+                    .outbox
+                    .send(priority, request.to_conduit_message()?)
                     .await?;
-                state.args.sender.flush().await?;
             }
 
             PortalActorMessage::RegisterProxyForRemoteActor(remote_actor_id, actor_cell) => {
@@ -794,11 +1043,26 @@ impl Actor for PortalActor {
                     return Ok(());
                 };
 
+                if state.exited_remote_actors.contains(&remote_actor_id) {
+                    info!(
+                        "Remote actor {remote_actor_id:?} has already exited, stopping its proxy"
+                    );
+                    actor_cell.stop(Some(
+                        "Proxy for remote actor is being shutdown because the real actor exited"
+                            .into(),
+                    ));
+                    return Ok(());
+                }
+
                 state
                     .proxies_for_remote_actors
                     .insert(remote_actor_id, actor_cell);
             }
 
+            PortalActorMessage::RegisterReplyTarget(remote_actor_id) => {
+                state.reply_targets.insert(remote_actor_id);
+            }
+
             PortalActorMessage::LocalActorExited(actor_id) => {
                 let PortalConduitState::Open { channel_id, .. } = &state.channel_state else {
                     error!("LocalActorExited called before handshake");
@@ -816,22 +1080,31 @@ impl Actor for PortalActor {
 
                 let msg = CrossPortalMessage::ActorExited(RemoteActorId {
                     connection_key: *channel_id,
-                    side: state.args.local_id,
+                    side: state.local_id,
                     id: *entry.0,
                 });
 
+                // a message which refers to the actor may still be queued in a lower lane
                 let bytes = msg.immaterialize()?;
                 state
-                    .args
-                    .sender
-                    .send(ConduitMessage::Binary(bytes))
+                    .outbox
+                    .send_after_queued(Priority::Control, ConduitMessage::Binary(bytes))
                     .await?;
-                state.args.sender.flush().await?;
             }
         }
         Ok(())
     }
 
+    async fn post_stop(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        // stopped without a close, e.g. by the nexus
+        self.notify_closed(state, None);
+        Ok(())
+    }
+
     async fn handle_supervisor_evt(
         &self,
         _myself: ActorRef<Self::Msg>,
@@ -856,3 +1129,43 @@ impl Actor for PortalActor {
         Ok(())
     }
 }
+
+// -------------------------------------------------------------------------------------------------------
+// Tests
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bulk_frames() -> Result<(), anyhow::Error> {
+        let remote_actor_id = RemoteActorId {
+            connection_key: ConduitID(1),
+            side: LocalPortalId(2),
+            id: OpaqueActorId(3),
+        };
+
+        let bulk = CrossPortalMessage::SendMessage(remote_actor_id, vec![1, 2, 3].into());
+        assert!(matches!(
+            bulk.to_conduit_message()?,
+            ConduitMessage::Bulk(bytes) if bytes == bulk.immaterialize()?
+        ));
+
+        let control = [
+            CrossPortalMessage::RequestActorByName(1, "hello".to_string()),
+            CrossPortalMessage::ResponseActorByName(1, Ok(remote_actor_id)),
+            CrossPortalMessage::ResponseActorById(1, Err(ActorRequestError::ActorNotFound)),
+            CrossPortalMessage::ActorExited(remote_actor_id),
+            CrossPortalMessage::SendReply(remote_actor_id, vec![1, 2, 3].into()),
+        ];
+        for msg in control {
+            assert!(matches!(
+                msg.to_conduit_message()?,
+                ConduitMessage::Binary(_)
+            ));
+        }
+
+        Ok(())
+    }
+}
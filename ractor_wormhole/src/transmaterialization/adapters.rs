@@ -0,0 +1,58 @@
+//! Adapters for existing serialization libraries, used for single fields of derived types.
+//!
+//! ```ignore
+//! #[derive(WormholeTransmaterializable)]
+//! pub struct Telemetry {
+//!     #[wormhole(with = PostcardAdapter)]
+//!     pub samples: Samples,
+//! }
+//! ```
+
+use super::TransmaterializationResult;
+#[cfg(feature = "bincode")]
+use super::transmaterialization_proxies::bincode_proxy;
+#[cfg(feature = "serde")]
+use super::transmaterialization_proxies::serde_proxy;
+
+/// (de)serializes values of type `T` with a serialization library, for `#[wormhole(with = adapter)]` fields.
+/// The adapter is a type (usually a unit struct), so one adapter can serve many types.
+///
+/// Adapters only handle plain data: `ActorRef`s and `RpcReplyPort`s must go through `ContextTransmaterializable`.
+pub trait SerializationAdapter<T> {
+    fn immaterialize(value: T) -> TransmaterializationResult<Vec<u8>>;
+
+    /// the data comes from the remote side, so invalid (or trailing) data must be an error, never a panic.
+    fn rematerialize(data: &[u8]) -> TransmaterializationResult<T>;
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// json, through serde. Used by `#[serde]` fields.
+#[cfg(feature = "serde")]
+pub struct SerdeAdapter;
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> SerializationAdapter<T> for SerdeAdapter {
+    fn immaterialize(value: T) -> TransmaterializationResult<Vec<u8>> {
+        serde_proxy::immaterialize(value)
+    }
+
+    fn rematerialize(data: &[u8]) -> TransmaterializationResult<T> {
+        serde_proxy::rematerialize(data)
+    }
+}
+
+/// bincode with the standard configuration. Used by `#[bincode]` fields.
+#[cfg(feature = "bincode")]
+pub struct BincodeAdapter;
+
+#[cfg(feature = "bincode")]
+impl<T: bincode::Encode + bincode::Decode<()>> SerializationAdapter<T> for BincodeAdapter {
+    fn immaterialize(value: T) -> TransmaterializationResult<Vec<u8>> {
+        bincode_proxy::immaterialize(value)
+    }
+
+    fn rematerialize(data: &[u8]) -> TransmaterializationResult<T> {
+        bincode_proxy::rematerialize(data)
+    }
+}
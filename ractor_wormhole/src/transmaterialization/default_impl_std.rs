@@ -0,0 +1,466 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+    num::{
+        NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize, NonZeroU8,
+        NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize,
+    },
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+
+use super::{
+    ContextTransmaterializable, PlainFns, PlainTransmaterializable, TransmaterializationContext,
+    TransmaterializationResult,
+    plain::element_fns,
+    util::require_buffer_size,
+    wire::{self, FieldFrame, WireCursor},
+};
+use crate::impl_context_transmaterializable_via_plain;
+
+// -------------------------------------------------------------------------------------------------------
+
+// Library provided implementations for the rest of the standard library (and `uuid::Uuid`, `bytes::Bytes`).
+//
+// Notes:
+//   * Collections are sent exactly like a `Vec` of their elements, and maps like a `Vec<(K, V)>`.
+//     So a `HashMap` can be received as a `BTreeMap` or a `Vec<(K, V)>`, and the other way around.
+//     Like `Vec<u8>`, the byte collections `VecDeque<u8>` and `[u8; N]` are sent as one blob (and so are compatible with it);
+//     other collections of `u8` (e.g. a `BTreeSet<u8>`) are sent element by element.
+//   * Collections of plain elements are plain, the others (e.g. a `HashMap<String, ActorRef<M>>`)
+//     (im)materialize their elements through the context.
+//   * `Box<T>` and `Arc<T>` are sent like `T`.
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the serialization scheme for a collection is the one of a Vec: length:u64 + n * [element_size:u64 + element_bytes]
+fn immaterialize_sequence<T>(
+    items: impl ExactSizeIterator<Item = T>,
+    element: PlainFns<T>,
+    buffer: &mut Vec<u8>,
+) -> TransmaterializationResult<()> {
+    buffer.extend_from_slice(&(items.len() as u64).to_le_bytes());
+    for item in items {
+        let frame = FieldFrame::begin(buffer);
+        (element.immaterialize_into)(item, buffer)?;
+        frame.end(buffer);
+    }
+    Ok(())
+}
+
+async fn immaterialize_sequence_with_context<T: ContextTransmaterializable + Send>(
+    items: impl ExactSizeIterator<Item = T> + Send,
+    ctx: &TransmaterializationContext,
+    buffer: &mut Vec<u8>,
+) -> TransmaterializationResult<()> {
+    buffer.extend_from_slice(&(items.len() as u64).to_le_bytes());
+    for item in items {
+        let frame = FieldFrame::begin(buffer);
+        item.immaterialize_into(ctx, buffer).await?;
+        frame.end(buffer);
+    }
+    Ok(())
+}
+
+/// the element fields and their count, which must match the length in the header
+fn read_sequence(data: &[u8]) -> TransmaterializationResult<Vec<&[u8]>> {
+    let mut cursor = WireCursor::new(data);
+    let count = cursor.read_u64()?;
+
+    // every element takes at least 8 bytes, don't trust the count beyond that
+    let mut fields = Vec::with_capacity((count as usize).min(cursor.remaining().len() / 8));
+    while !cursor.is_empty() {
+        fields.push(cursor.read_field()?);
+    }
+
+    if fields.len() as u64 != count {
+        return Err(anyhow::anyhow!(
+            "Expected {count} elements, but the data contains {}",
+            fields.len()
+        ));
+    }
+    Ok(fields)
+}
+
+fn rematerialize_sequence<T>(
+    data: &[u8],
+    element: PlainFns<T>,
+) -> TransmaterializationResult<Vec<T>> {
+    read_sequence(data)?
+        .into_iter()
+        .map(element.rematerialize)
+        .collect()
+}
+
+async fn rematerialize_sequence_with_context<T: ContextTransmaterializable>(
+    ctx: &TransmaterializationContext,
+    data: &[u8],
+) -> TransmaterializationResult<Vec<T>> {
+    let fields = read_sequence(data)?;
+    let mut items = Vec::with_capacity(fields.len());
+    for field in fields {
+        items.push(T::rematerialize(ctx, field).await?);
+    }
+    Ok(items)
+}
+
+fn collect<T, C: FromIterator<T>>(items: Vec<T>) -> TransmaterializationResult<C> {
+    Ok(items.into_iter().collect())
+}
+
+fn collect_array<T, const N: usize>(items: Vec<T>) -> TransmaterializationResult<[T; N]> {
+    let len = items.len();
+    items
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Expected an array of {N} elements, got {len}"))
+}
+
+/// implements both traits for a collection of `$element`s, which is sent like a `Vec<$element>`.
+/// `$from_vec` creates the collection from the rematerialized elements.
+macro_rules! impl_transmaterializable_for_collection {
+    ([$($generics:tt)*] $type:ty, $element:ty, $from_vec:path, where [$($bounds:tt)*]) => {
+        impl<$($generics)*> PlainTransmaterializable for $type
+        where
+            $element: PlainTransmaterializable,
+            $($bounds)*
+        {
+            default fn immaterialize_plain_into(
+                self,
+                buffer: &mut Vec<u8>,
+            ) -> TransmaterializationResult<()> {
+                immaterialize_sequence(self.into_iter(), PlainFns::of(), buffer)
+            }
+
+            default fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+                $from_vec(rematerialize_sequence(data, PlainFns::of())?)
+            }
+        }
+
+        #[async_trait]
+        impl<$($generics)*> ContextTransmaterializable for $type
+        where
+            $element: ContextTransmaterializable + Send + Sync + 'static,
+            $type: Send + 'static,
+            $($bounds)*
+        {
+            default fn plain() -> Option<PlainFns<Self>> {
+                <$element>::plain()?;
+                Some(PlainFns {
+                    immaterialize_into: |collection, buffer| {
+                        immaterialize_sequence(
+                            collection.into_iter(),
+                            element_fns::<$element>()?,
+                            buffer,
+                        )
+                    },
+                    rematerialize: |data| {
+                        $from_vec(rematerialize_sequence(data, element_fns::<$element>()?)?)
+                    },
+                })
+            }
+
+            default async fn immaterialize(
+                self,
+                ctx: &TransmaterializationContext,
+            ) -> TransmaterializationResult<Vec<u8>> {
+                let mut buffer = Vec::new();
+                self.immaterialize_into(ctx, &mut buffer).await?;
+                Ok(buffer)
+            }
+
+            default async fn immaterialize_into(
+                self,
+                ctx: &TransmaterializationContext,
+                buffer: &mut Vec<u8>,
+            ) -> TransmaterializationResult<()> {
+                if let Some(plain) = Self::plain() {
+                    return (plain.immaterialize_into)(self, buffer);
+                }
+                immaterialize_sequence_with_context(self.into_iter(), ctx, buffer).await
+            }
+
+            default async fn rematerialize(
+                ctx: &TransmaterializationContext,
+                data: &[u8],
+            ) -> TransmaterializationResult<Self> {
+                if let Some(plain) = Self::plain() {
+                    return (plain.rematerialize)(data);
+                }
+                $from_vec(rematerialize_sequence_with_context(ctx, data).await?)
+            }
+        }
+    };
+}
+
+impl_transmaterializable_for_collection!([T] VecDeque<T>, T, collect, where []);
+impl_transmaterializable_for_collection!([T, S] HashSet<T, S>, T, collect, where [T: Eq + Hash, S: BuildHasher + Default]);
+impl_transmaterializable_for_collection!([T] BTreeSet<T>, T, collect, where [T: Ord]);
+impl_transmaterializable_for_collection!([K, V, S] HashMap<K, V, S>, (K, V), collect, where [K: Eq + Hash + Send, V: Send, S: BuildHasher + Default]);
+impl_transmaterializable_for_collection!([K, V] BTreeMap<K, V>, (K, V), collect, where [K: Ord + Send, V: Send]);
+impl_transmaterializable_for_collection!([T, const N: usize] [T; N], T, collect_array, where []);
+
+// like `Vec<u8>`, byte collections are sent as one blob instead of element by element
+
+impl PlainTransmaterializable for VecDeque<u8> {
+    fn immaterialize_plain_into(mut self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        wire::write_field(buffer, self.make_contiguous());
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        Ok(Vec::<u8>::rematerialize_plain(data)?.into())
+    }
+}
+
+impl_context_transmaterializable_via_plain!(VecDeque<u8>);
+
+impl<const N: usize> PlainTransmaterializable for [u8; N] {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        wire::write_field(buffer, &self);
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        let mut cursor = WireCursor::new(data);
+        let bytes = cursor.read_field()?;
+        cursor.finish()?;
+        bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Expected an array of {N} bytes, got {}", bytes.len()))
+    }
+}
+
+impl_context_transmaterializable_via_plain!([const N: usize] [u8; N]);
+
+// -------------------------------------------------------------------------------------------------------
+
+/// implements both traits for a smart pointer, which is sent like its content.
+macro_rules! impl_transmaterializable_for_pointer {
+    ($pointer:ident, $into_inner:expr, where [$($bounds:tt)*]) => {
+        impl<T: PlainTransmaterializable + $($bounds)*> PlainTransmaterializable for $pointer<T> {
+            fn immaterialize_plain_into(
+                self,
+                buffer: &mut Vec<u8>,
+            ) -> TransmaterializationResult<()> {
+                ($into_inner)(self).immaterialize_plain_into(buffer)
+            }
+
+            fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+                Ok($pointer::new(T::rematerialize_plain(data)?))
+            }
+        }
+
+        #[async_trait]
+        impl<T: ContextTransmaterializable + Send + Sync + 'static + $($bounds)*> ContextTransmaterializable
+            for $pointer<T>
+        {
+            fn plain() -> Option<PlainFns<Self>> {
+                T::plain()?;
+                Some(PlainFns {
+                    immaterialize_into: |pointer, buffer| {
+                        (element_fns::<T>()?.immaterialize_into)(($into_inner)(pointer), buffer)
+                    },
+                    rematerialize: |data| Ok($pointer::new((element_fns::<T>()?.rematerialize)(data)?)),
+                })
+            }
+
+            async fn immaterialize(
+                self,
+                ctx: &TransmaterializationContext,
+            ) -> TransmaterializationResult<Vec<u8>> {
+                ($into_inner)(self).immaterialize(ctx).await
+            }
+
+            async fn immaterialize_into(
+                self,
+                ctx: &TransmaterializationContext,
+                buffer: &mut Vec<u8>,
+            ) -> TransmaterializationResult<()> {
+                ($into_inner)(self).immaterialize_into(ctx, buffer).await
+            }
+
+            async fn rematerialize(
+                ctx: &TransmaterializationContext,
+                data: &[u8],
+            ) -> TransmaterializationResult<Self> {
+                Ok($pointer::new(T::rematerialize(ctx, data).await?))
+            }
+        }
+    };
+}
+
+impl_transmaterializable_for_pointer!(Box, |boxed: Box<T>| *boxed, where []);
+// a shared `Arc` is cloned
+impl_transmaterializable_for_pointer!(Arc, Arc::unwrap_or_clone, where [Clone]);
+
+// -------------------------------------------------------------------------------------------------------
+
+/// sent like a `String`
+impl PlainTransmaterializable for Box<str> {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        wire::write_field(buffer, self.as_bytes());
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        Ok(String::rematerialize_plain(data)?.into_boxed_str())
+    }
+}
+
+impl_context_transmaterializable_via_plain!(Box<str>);
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the serialization scheme for a char is: code_point:u32
+impl PlainTransmaterializable for char {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        buffer.extend_from_slice(&u32::from(self).to_le_bytes());
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        let code_point = u32::rematerialize_plain(data)?;
+        char::from_u32(code_point).ok_or_else(|| anyhow::anyhow!("Invalid char: {code_point:#x}"))
+    }
+}
+
+impl_context_transmaterializable_via_plain!(char);
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the serialization scheme for a Duration is: seconds:u64 + nanoseconds:u32
+impl PlainTransmaterializable for Duration {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        buffer.extend_from_slice(&self.as_secs().to_le_bytes());
+        buffer.extend_from_slice(&self.subsec_nanos().to_le_bytes());
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        require_buffer_size(data, 12)?;
+        let secs = u64::from_le_bytes(data[0..8].try_into()?);
+        let nanos = u32::from_le_bytes(data[8..12].try_into()?);
+        if nanos >= 1_000_000_000 {
+            return Err(anyhow::anyhow!("Invalid Duration: {nanos} nanoseconds"));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+impl_context_transmaterializable_via_plain!(Duration);
+
+/// the serialization scheme for a SystemTime is: seconds:i64 + nanoseconds:u32 since the unix epoch,
+/// where the nanoseconds are always added (so times before the epoch have negative seconds).
+impl PlainTransmaterializable for SystemTime {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        let (secs, nanos) = match self.duration_since(UNIX_EPOCH) {
+            Ok(after) => (i64::try_from(after.as_secs())?, after.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                let secs = i64::try_from(before.as_secs())?;
+                match before.subsec_nanos() {
+                    0 => (-secs, 0),
+                    nanos => (-secs - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        buffer.extend_from_slice(&secs.to_le_bytes());
+        buffer.extend_from_slice(&nanos.to_le_bytes());
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        require_buffer_size(data, 12)?;
+        let secs = i64::from_le_bytes(data[0..8].try_into()?);
+        let nanos = u32::from_le_bytes(data[8..12].try_into()?);
+        if nanos >= 1_000_000_000 {
+            return Err(anyhow::anyhow!("Invalid SystemTime: {nanos} nanoseconds"));
+        }
+
+        let whole_secs = match secs >= 0 {
+            true => UNIX_EPOCH.checked_add(Duration::from_secs(secs.unsigned_abs())),
+            false => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+        };
+        whole_secs
+            .and_then(|time| time.checked_add(Duration::from_nanos(u64::from(nanos))))
+            .ok_or_else(|| anyhow::anyhow!("SystemTime out of range: {secs}s {nanos}ns"))
+    }
+}
+
+impl_context_transmaterializable_via_plain!(SystemTime);
+
+// -------------------------------------------------------------------------------------------------------
+
+/// the serialization scheme for a Uuid is: its 16 bytes
+impl PlainTransmaterializable for uuid::Uuid {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        buffer.extend_from_slice(self.as_bytes());
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        Ok(uuid::Uuid::from_slice(data)?)
+    }
+}
+
+impl_context_transmaterializable_via_plain!(uuid::Uuid);
+
+/// sent like a `Vec<u8>`
+impl PlainTransmaterializable for bytes::Bytes {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        wire::write_field(buffer, &self);
+        Ok(())
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        let mut cursor = WireCursor::new(data);
+        let bytes = cursor.read_field()?;
+        cursor.finish()?;
+        Ok(bytes::Bytes::copy_from_slice(bytes))
+    }
+}
+
+impl_context_transmaterializable_via_plain!(bytes::Bytes);
+
+// -------------------------------------------------------------------------------------------------------
+
+/// NonZero integers are sent like the integer, zero is an error.
+macro_rules! impl_plain_transmaterializable_for_non_zero {
+    ($($type:ty),+) => {
+        $(
+            impl PlainTransmaterializable for $type {
+                fn immaterialize_plain_into(
+                    self,
+                    buffer: &mut Vec<u8>,
+                ) -> TransmaterializationResult<()> {
+                    self.get().immaterialize_plain_into(buffer)
+                }
+
+                fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+                    <$type>::new(PlainTransmaterializable::rematerialize_plain(data)?).ok_or_else(
+                        || anyhow::anyhow!("Invalid {}: 0", std::any::type_name::<$type>()),
+                    )
+                }
+            }
+
+            impl_context_transmaterializable_via_plain!($type);
+        )+
+    };
+}
+
+impl_plain_transmaterializable_for_non_zero!(
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    NonZeroU128,
+    NonZeroUsize,
+    NonZeroI8,
+    NonZeroI16,
+    NonZeroI32,
+    NonZeroI64,
+    NonZeroI128,
+    NonZeroIsize
+);
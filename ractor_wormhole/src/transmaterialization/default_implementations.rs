@@ -2,9 +2,12 @@ use async_trait::async_trait;
 use ractor::{ActorRef, RpcReplyPort};
 
 use super::{
-    util::{require_buffer_size, require_min_buffer_size},
+    plain::element_fns,
+    util::require_buffer_size,
+    wire::{FieldFrame, WireCursor},
     *,
 };
+use crate::impl_context_transmaterializable_via_plain;
 
 use static_assertions::{const_assert, const_assert_eq};
 
@@ -17,6 +20,10 @@ use static_assertions::{const_assert, const_assert_eq};
 //   * For dynamically sized data, where the length is not known in advance,
 //      we need to validate at the end of the deserialization routine that all data has been consumed.
 //   * We serialize numbers as little endian, strings as utf-8.
+//   * Containers write their elements with `immaterialize_into`, directly into their own buffer.
+//     Their `immaterialize` only creates the buffer.
+//   * Everything except `ActorRef` and `RpcReplyPort` is plain data, see `plain.rs`. Containers of plain elements
+//     are plain as well, and don't create a future per element.
 
 // -------------------------------------------------------------------------------------------------------
 
@@ -60,424 +67,547 @@ impl<T: ContextTransmaterializable + Send + Sync + 'static + std::fmt::Debug>
 // -------------------------------------------------------------------------------------------------------
 
 /// the serialization scheme for a Vec is: header: length:u64 + n * [element_size:u64 + element_bytes]
+fn immaterialize_vec<T>(
+    vec: Vec<T>,
+    element: PlainFns<T>,
+    buffer: &mut Vec<u8>,
+) -> TransmaterializationResult<()> {
+    buffer.extend_from_slice(&(vec.len() as u64).to_le_bytes());
+    for value in vec {
+        let frame = FieldFrame::begin(buffer);
+        (element.immaterialize_into)(value, buffer)?;
+        frame.end(buffer);
+    }
+    Ok(())
+}
+
+fn rematerialize_vec<T>(data: &[u8], element: PlainFns<T>) -> TransmaterializationResult<Vec<T>> {
+    let mut cursor = WireCursor::new(data);
+    let count = cursor.read_u64()? as usize;
+
+    // every element takes at least 8 bytes, don't trust the count beyond that
+    let mut buffer = Vec::with_capacity(count.min(cursor.remaining().len() / 8));
+
+    while !cursor.is_empty() {
+        buffer.push((element.rematerialize)(cursor.read_field()?)?);
+    }
+
+    Ok(buffer)
+}
+
+impl<T: PlainTransmaterializable> PlainTransmaterializable for Vec<T> {
+    default fn immaterialize_plain_into(
+        self,
+        buffer: &mut Vec<u8>,
+    ) -> TransmaterializationResult<()> {
+        immaterialize_vec(self, PlainFns::of(), buffer)
+    }
+
+    default fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        rematerialize_vec(data, PlainFns::of())
+    }
+}
+
 #[async_trait]
 impl<T: ContextTransmaterializable + Send + Sync + 'static> ContextTransmaterializable for Vec<T> {
+    default fn plain() -> Option<PlainFns<Self>> {
+        T::plain()?;
+        Some(PlainFns {
+            immaterialize_into: |vec, buffer| immaterialize_vec(vec, element_fns::<T>()?, buffer),
+            rematerialize: |data| rematerialize_vec(data, element_fns::<T>()?),
+        })
+    }
+
     default async fn immaterialize(
         self,
         ctx: &TransmaterializationContext,
     ) -> TransmaterializationResult<Vec<u8>> {
         let mut buffer = Vec::with_capacity(8 + self.len() * 8);
+        self.immaterialize_into(ctx, &mut buffer).await?;
+        Ok(buffer)
+    }
+
+    default async fn immaterialize_into(
+        self,
+        ctx: &TransmaterializationContext,
+        buffer: &mut Vec<u8>,
+    ) -> TransmaterializationResult<()> {
+        if let Some(element) = T::plain() {
+            return immaterialize_vec(self, element, buffer);
+        }
+
         let count = self.len() as u64;
         buffer.extend_from_slice(&count.to_le_bytes());
 
         for element in self {
-            let element_bytes = element.immaterialize(ctx).await?;
-            let length: u64 = element_bytes.len() as u64;
-            buffer.extend_from_slice(&length.to_le_bytes());
-            buffer.extend_from_slice(&element_bytes);
+            let frame = FieldFrame::begin(buffer);
+            element.immaterialize_into(ctx, buffer).await?;
+            frame.end(buffer);
         }
 
-        Ok(buffer)
+        Ok(())
     }
 
     default async fn rematerialize(
         ctx: &TransmaterializationContext,
         data: &[u8],
     ) -> TransmaterializationResult<Self> {
-        let mut offset = 0;
-        require_min_buffer_size(data, 8)?;
-        let count = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-        offset += 8;
-
-        let mut buffer = Vec::with_capacity(count);
-
-        while offset < data.len() {
-            require_min_buffer_size(data, offset + 8)?;
-            let length = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-            offset += 8;
-            require_min_buffer_size(data, offset + length)?;
-            let element_data = &data[offset..offset + length];
-            buffer.push(T::rematerialize(ctx, element_data).await?);
-            offset += length;
+        if let Some(element) = T::plain() {
+            return rematerialize_vec(data, element);
+        }
+
+        let mut cursor = WireCursor::new(data);
+        let count = cursor.read_u64()? as usize;
+
+        // every element takes at least 8 bytes, don't trust the count beyond that
+        let mut buffer = Vec::with_capacity(count.min(cursor.remaining().len() / 8));
+
+        while !cursor.is_empty() {
+            buffer.push(T::rematerialize(ctx, cursor.read_field()?).await?);
         }
 
-        require_buffer_size(data, offset)?;
         Ok(buffer)
     }
 }
 
 // -------------------------------------------------------------------------------------------------------
 
-#[async_trait]
-impl ContextTransmaterializable for Vec<u8> {
-    async fn immaterialize(
-        self,
-        _ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(8 + self.len());
-        let length = self.len() as u64;
-        buffer.extend_from_slice(&length.to_le_bytes());
-        buffer.extend_from_slice(&self);
-        Ok(buffer)
+impl PlainTransmaterializable for Vec<u8> {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        wire::write_field(buffer, &self);
+        Ok(())
     }
 
-    async fn rematerialize(
-        _ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
-        let length = u64::from_le_bytes(data[0..8].try_into()?) as usize;
-        require_buffer_size(data, 8 + length)?;
-        Ok(data[8..8 + length].to_vec())
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        let mut cursor = WireCursor::new(data);
+        let bytes = cursor.read_field()?;
+        cursor.finish()?;
+        Ok(bytes.to_vec())
     }
 }
 
+impl_context_transmaterializable_via_plain!(Vec<u8>);
+
 // -------------------------------------------------------------------------------------------------------
 
-#[async_trait]
-impl ContextTransmaterializable for String {
-    async fn immaterialize(
-        self,
-        _ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(8 + self.len());
-        let length = self.len() as u64;
-        buffer.extend_from_slice(&length.to_le_bytes());
-        buffer.extend_from_slice(self.as_bytes());
-        Ok(buffer)
+impl PlainTransmaterializable for String {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        wire::write_field(buffer, self.as_bytes());
+        Ok(())
     }
 
-    async fn rematerialize(
-        _ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
-        let length = u64::from_le_bytes(data[0..8].try_into()?) as usize;
-        require_buffer_size(data, 8 + length)?;
-        let string_data = &data[8..8 + length];
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        let mut cursor = WireCursor::new(data);
+        let string_data = cursor.read_field()?;
+        cursor.finish()?;
         Ok(String::from_utf8(string_data.to_vec())?)
     }
 }
 
+impl_context_transmaterializable_via_plain!(String);
+
 // -------------------------------------------------------------------------------------------------------
 
-/// Macro to implement ContextTransmaterializable for numeric types
-macro_rules! impl_context_transmaterializable_for_le_bytes {
+/// Macro to implement PlainTransmaterializable (and ContextTransmaterializable) for numeric types
+macro_rules! impl_plain_transmaterializable_for_le_bytes {
     ($type:ty, $size:expr) => {
-        #[async_trait]
-        impl ContextTransmaterializable for $type {
-            default async fn immaterialize(
+        impl PlainTransmaterializable for $type {
+            fn immaterialize_plain_into(
                 self,
-                _ctx: &TransmaterializationContext,
-            ) -> TransmaterializationResult<Vec<u8>> {
-                let mut buffer = Vec::with_capacity($size);
+                buffer: &mut Vec<u8>,
+            ) -> TransmaterializationResult<()> {
                 buffer.extend_from_slice(&self.to_le_bytes());
-                Ok(buffer)
+                Ok(())
             }
 
-            default async fn rematerialize(
-                _ctx: &TransmaterializationContext,
-                data: &[u8],
-            ) -> TransmaterializationResult<Self> {
+            fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
                 require_buffer_size(data, $size)?;
                 Ok(<$type>::from_le_bytes(data[0..$size].try_into()?))
             }
         }
+
+        impl_context_transmaterializable_via_plain!($type);
     };
 }
 
-macro_rules! impl_context_transmaterializable_for_numeric {
+macro_rules! impl_plain_transmaterializable_for_numeric {
     ($type:ty) => {
-        impl_context_transmaterializable_for_le_bytes!($type, std::mem::size_of::<$type>());
+        impl_plain_transmaterializable_for_le_bytes!($type, std::mem::size_of::<$type>());
     };
 }
 
-macro_rules! impl_context_transmaterializable_for_integer {
+macro_rules! impl_plain_transmaterializable_for_integer {
     ($type:ty) => {
         const_assert_eq!(std::mem::size_of::<$type>(), <$type>::BITS as usize / 8);
-        impl_context_transmaterializable_for_numeric!($type);
+        impl_plain_transmaterializable_for_numeric!($type);
     };
 }
 
-macro_rules! impl_context_transmaterializable_for_float {
+macro_rules! impl_plain_transmaterializable_for_float {
     ($type:ty) => {
-        impl_context_transmaterializable_for_numeric!($type);
+        impl_plain_transmaterializable_for_numeric!($type);
     };
 }
 
 // Implement for all integer types
-impl_context_transmaterializable_for_integer!(u8);
-impl_context_transmaterializable_for_integer!(u16);
-impl_context_transmaterializable_for_integer!(u32);
-impl_context_transmaterializable_for_integer!(u64);
-impl_context_transmaterializable_for_integer!(u128);
-impl_context_transmaterializable_for_integer!(i8);
-impl_context_transmaterializable_for_integer!(i16);
-impl_context_transmaterializable_for_integer!(i32);
-impl_context_transmaterializable_for_integer!(i64);
-impl_context_transmaterializable_for_integer!(i128);
+impl_plain_transmaterializable_for_integer!(u8);
+impl_plain_transmaterializable_for_integer!(u16);
+impl_plain_transmaterializable_for_integer!(u32);
+impl_plain_transmaterializable_for_integer!(u64);
+impl_plain_transmaterializable_for_integer!(u128);
+impl_plain_transmaterializable_for_integer!(i8);
+impl_plain_transmaterializable_for_integer!(i16);
+impl_plain_transmaterializable_for_integer!(i32);
+impl_plain_transmaterializable_for_integer!(i64);
+impl_plain_transmaterializable_for_integer!(i128);
 
 // Implement for floating point types
 const_assert_eq!(std::mem::size_of::<f32>(), 4);
-impl_context_transmaterializable_for_float!(f32);
+impl_plain_transmaterializable_for_float!(f32);
 const_assert_eq!(std::mem::size_of::<f64>(), 8);
-impl_context_transmaterializable_for_float!(f64);
+impl_plain_transmaterializable_for_float!(f64);
 
 // -------------------------------------------------------------------------------------------------------
 
 const_assert!(std::mem::size_of::<usize>() <= 8);
 
-#[async_trait]
-impl ContextTransmaterializable for usize {
-    async fn immaterialize(
-        self,
-        _ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(8);
+impl PlainTransmaterializable for usize {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
         // note: serialize as u64 so it's platform independent
         buffer.extend_from_slice(&(self as u64).to_le_bytes());
-        Ok(buffer)
+        Ok(())
     }
 
-    async fn rematerialize(
-        _ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
         require_buffer_size(data, 8)?;
         Ok(u64::from_le_bytes(data[0..8].try_into()?) as usize)
     }
 }
 
+impl_context_transmaterializable_via_plain!(usize);
+
 // -------------------------------------------------------------------------------------------------------
 
 const_assert!(std::mem::size_of::<isize>() <= 8);
 
-#[async_trait]
-impl ContextTransmaterializable for isize {
-    async fn immaterialize(
-        self,
-        _ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(8);
+impl PlainTransmaterializable for isize {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
         // note: serialize as i64 so it's platform independent
         buffer.extend_from_slice(&(self as i64).to_le_bytes());
-        Ok(buffer)
+        Ok(())
     }
 
-    async fn rematerialize(
-        _ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
         require_buffer_size(data, 8)?;
         Ok(i64::from_le_bytes(data[0..8].try_into()?) as isize)
     }
 }
 
+impl_context_transmaterializable_via_plain!(isize);
+
 // -------------------------------------------------------------------------------------------------------
 
-#[async_trait]
-impl ContextTransmaterializable for bool {
-    async fn immaterialize(
-        self,
-        _ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(1);
-        buffer.extend_from_slice(if self { &[1] } else { &[0] });
-        Ok(buffer)
+impl PlainTransmaterializable for bool {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        buffer.push(self as u8);
+        Ok(())
     }
 
-    async fn rematerialize(
-        _ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
         require_buffer_size(data, 1)?;
-        if data[0] == 0 {
-            return Ok(false);
-        } else if data[0] == 1 {
-            return Ok(true);
-        } else {
-            return Err(anyhow::anyhow!("Invalid boolean value: {}", data[0]));
+        match data[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(anyhow::anyhow!("Invalid boolean value: {}", other)),
         }
     }
 }
 
+impl_context_transmaterializable_via_plain!(bool);
+
 // -------------------------------------------------------------------------------------------------------
 
-#[async_trait]
-impl ContextTransmaterializable for () {
-    async fn immaterialize(
-        self,
-        _ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let buffer = Vec::with_capacity(0);
-        Ok(buffer)
+impl PlainTransmaterializable for () {
+    fn immaterialize_plain_into(self, _buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        Ok(())
     }
 
-    async fn rematerialize(
-        _ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
         require_buffer_size(data, 0)?;
         Ok(())
     }
 }
 
+impl_context_transmaterializable_via_plain!(());
+
 // -------------------------------------------------------------------------------------------------------
 
+/// the serialization scheme for a Result is: discriminant:u8 (0 = Ok, 1 = Err) + length:u64 + value_bytes
+fn rematerialize_result_header(data: &[u8]) -> TransmaterializationResult<(bool, &[u8])> {
+    let mut cursor = WireCursor::new(data);
+    let discriminant = cursor.read_u8()?;
+    let inner_data = cursor.read_field()?;
+    cursor.finish()?; // Ensure all data is consumed
+
+    match discriminant {
+        0u8 => Ok((true, inner_data)),
+        1u8 => Ok((false, inner_data)),
+        _ => Err(anyhow::anyhow!(
+            "Invalid discriminant for Result: {}. Expected 0 for Ok or 1 for Err.",
+            discriminant
+        )),
+    }
+}
+
+fn immaterialize_result<T, E>(
+    result: Result<T, E>,
+    ok: PlainFns<T>,
+    err: PlainFns<E>,
+    buffer: &mut Vec<u8>,
+) -> TransmaterializationResult<()> {
+    let frame;
+    match result {
+        Ok(value) => {
+            buffer.push(0u8);
+            frame = FieldFrame::begin(buffer);
+            (ok.immaterialize_into)(value, buffer)?;
+        }
+        Err(error) => {
+            buffer.push(1u8);
+            frame = FieldFrame::begin(buffer);
+            (err.immaterialize_into)(error, buffer)?;
+        }
+    }
+    frame.end(buffer);
+    Ok(())
+}
+
+fn rematerialize_result<T, E>(
+    data: &[u8],
+    ok: PlainFns<T>,
+    err: PlainFns<E>,
+) -> TransmaterializationResult<Result<T, E>> {
+    match rematerialize_result_header(data)? {
+        (true, inner_data) => Ok(Ok((ok.rematerialize)(inner_data)?)),
+        (false, inner_data) => Ok(Err((err.rematerialize)(inner_data)?)),
+    }
+}
+
+impl<T: PlainTransmaterializable, E: PlainTransmaterializable> PlainTransmaterializable
+    for Result<T, E>
+{
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        immaterialize_result(self, PlainFns::of(), PlainFns::of(), buffer)
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        rematerialize_result(data, PlainFns::of(), PlainFns::of())
+    }
+}
+
 #[async_trait]
 impl<T, E> ContextTransmaterializable for Result<T, E>
 where
     T: ContextTransmaterializable + Send + Sync + 'static,
     E: ContextTransmaterializable + Send + Sync + 'static,
 {
+    fn plain() -> Option<PlainFns<Self>> {
+        T::plain()?;
+        E::plain()?;
+        Some(PlainFns {
+            immaterialize_into: |result, buffer| {
+                immaterialize_result(result, element_fns::<T>()?, element_fns::<E>()?, buffer)
+            },
+            rematerialize: |data| {
+                rematerialize_result(data, element_fns::<T>()?, element_fns::<E>()?)
+            },
+        })
+    }
+
     async fn immaterialize(
         self,
         ctx: &TransmaterializationContext,
     ) -> TransmaterializationResult<Vec<u8>> {
-        match self {
-            Ok(value) => {
-                let mut buffer = Vec::with_capacity(9);
-                buffer.extend_from_slice(&[0u8]);
+        let mut buffer = Vec::with_capacity(9);
+        self.immaterialize_into(ctx, &mut buffer).await?;
+        Ok(buffer)
+    }
 
-                let value_bytes = value.immaterialize(ctx).await?;
-                let length: u64 = value_bytes.len() as u64;
-                buffer.extend_from_slice(&length.to_le_bytes());
-                buffer.extend_from_slice(&value_bytes);
+    async fn immaterialize_into(
+        self,
+        ctx: &TransmaterializationContext,
+        buffer: &mut Vec<u8>,
+    ) -> TransmaterializationResult<()> {
+        if let Some(plain) = Self::plain() {
+            return (plain.immaterialize_into)(self, buffer);
+        }
 
-                Ok(buffer)
+        match self {
+            Ok(value) => {
+                buffer.push(0u8);
+                let frame = FieldFrame::begin(buffer);
+                value.immaterialize_into(ctx, buffer).await?;
+                frame.end(buffer);
             }
             Err(err) => {
-                let mut buffer = Vec::with_capacity(9);
-                buffer.extend_from_slice(&[1u8]);
-                let err_bytes = err.immaterialize(ctx).await?;
-                let length: u64 = err_bytes.len() as u64;
-                buffer.extend_from_slice(&length.to_le_bytes());
-                buffer.extend_from_slice(&err_bytes);
-                Ok(buffer)
+                buffer.push(1u8);
+                let frame = FieldFrame::begin(buffer);
+                err.immaterialize_into(ctx, buffer).await?;
+                frame.end(buffer);
             }
         }
+        Ok(())
     }
 
     async fn rematerialize(
         ctx: &TransmaterializationContext,
         data: &[u8],
     ) -> TransmaterializationResult<Self> {
-        require_min_buffer_size(data, 9)?; // discriminant(1) + length(8)
-        let discriminant = data[0];
-        let mut offset = 1;
+        if let Some(plain) = Self::plain() {
+            return (plain.rematerialize)(data);
+        }
 
-        let length = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-        offset += 8;
+        match rematerialize_result_header(data)? {
+            (true, inner_data) => Ok(Ok(T::rematerialize(ctx, inner_data).await?)),
+            (false, inner_data) => Ok(Err(E::rematerialize(ctx, inner_data).await?)),
+        }
+    }
+}
 
-        require_min_buffer_size(data, offset + length)?; // Ensure there are bytes for data
-        let inner_data = &data[offset..offset + length];
-        offset += length;
+// -------------------------------------------------------------------------------------------------------
 
-        require_buffer_size(data, offset)?; // Ensure all data is consumed
+/// the serialization scheme for an Option is: discriminant:u8 (0 = None, 1 = Some) + [length:u64 + value_bytes]
+fn rematerialize_option_header(data: &[u8]) -> TransmaterializationResult<Option<&[u8]>> {
+    let mut cursor = WireCursor::new(data);
+    let discriminant = cursor.read_u8()?;
 
-        match discriminant {
-            0u8 => {
-                let value = T::rematerialize(ctx, inner_data).await?;
-                Ok(Ok(value))
-            }
-            1u8 => {
-                let err_val = E::rematerialize(ctx, inner_data).await?;
-                Ok(Err(err_val))
-            }
-            _ => Err(anyhow::anyhow!(
-                "Invalid discriminant for Result: {}. Expected 0 for Ok or 1 for Err.",
-                discriminant
-            )),
+    match discriminant {
+        0u8 => {
+            cursor.finish()?; // Ensure all data is consumed for None
+            Ok(None)
+        }
+        1u8 => {
+            let inner_data = cursor.read_field()?;
+            cursor.finish()?; // Ensure all data is consumed
+            Ok(Some(inner_data))
         }
+        _ => Err(anyhow::anyhow!(
+            "Invalid discriminant for Option: {}. Expected 0 for None or 1 for Some.",
+            discriminant
+        )),
     }
 }
 
-// -------------------------------------------------------------------------------------------------------
+fn immaterialize_option<T>(
+    option: Option<T>,
+    element: PlainFns<T>,
+    buffer: &mut Vec<u8>,
+) -> TransmaterializationResult<()> {
+    match option {
+        Some(value) => {
+            buffer.push(1u8);
+            let frame = FieldFrame::begin(buffer);
+            (element.immaterialize_into)(value, buffer)?;
+            frame.end(buffer);
+        }
+        None => buffer.push(0u8),
+    }
+    Ok(())
+}
+
+fn rematerialize_option<T>(
+    data: &[u8],
+    element: PlainFns<T>,
+) -> TransmaterializationResult<Option<T>> {
+    rematerialize_option_header(data)?
+        .map(element.rematerialize)
+        .transpose()
+}
+
+impl<T: PlainTransmaterializable> PlainTransmaterializable for Option<T> {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        immaterialize_option(self, PlainFns::of(), buffer)
+    }
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        rematerialize_option(data, PlainFns::of())
+    }
+}
 
 #[async_trait]
 impl<T> ContextTransmaterializable for Option<T>
 where
     T: ContextTransmaterializable + Send + Sync + 'static,
 {
+    fn plain() -> Option<PlainFns<Self>> {
+        T::plain()?;
+        Some(PlainFns {
+            immaterialize_into: |option, buffer| {
+                immaterialize_option(option, element_fns::<T>()?, buffer)
+            },
+            rematerialize: |data| rematerialize_option(data, element_fns::<T>()?),
+        })
+    }
+
     async fn immaterialize(
         self,
         ctx: &TransmaterializationContext,
     ) -> TransmaterializationResult<Vec<u8>> {
+        // Capacity: 1 (discriminant) + 8 (length), the value is added to that
+        let mut buffer = Vec::with_capacity(1 + 8);
+        self.immaterialize_into(ctx, &mut buffer).await?;
+        Ok(buffer)
+    }
+
+    async fn immaterialize_into(
+        self,
+        ctx: &TransmaterializationContext,
+        buffer: &mut Vec<u8>,
+    ) -> TransmaterializationResult<()> {
+        if let Some(plain) = Self::plain() {
+            return (plain.immaterialize_into)(self, buffer);
+        }
+
         match self {
             Some(value) => {
-                // Capacity: 1 (discriminant) + 8 (length) + value_bytes.len()
-                // Initial capacity for discriminant and length.
-                let mut buffer = Vec::with_capacity(1 + 8);
                 buffer.push(1u8);
-
-                let value_bytes = value.immaterialize(ctx).await?;
-                let length: u64 = value_bytes.len() as u64;
-                buffer.extend_from_slice(&length.to_le_bytes());
-                buffer.extend_from_slice(&value_bytes);
-
-                Ok(buffer)
-            }
-            None => {
-                // Capacity: 1 (discriminant)
-                let mut buffer = Vec::with_capacity(1);
-                buffer.push(0u8);
-                Ok(buffer)
+                let frame = FieldFrame::begin(buffer);
+                value.immaterialize_into(ctx, buffer).await?;
+                frame.end(buffer);
             }
+            None => buffer.push(0u8),
         }
+        Ok(())
     }
 
     async fn rematerialize(
         ctx: &TransmaterializationContext,
         data: &[u8],
     ) -> TransmaterializationResult<Self> {
-        require_min_buffer_size(data, 1)?;
-        let discriminant = data[0];
-        let mut offset = 1;
-
-        match discriminant {
-            0u8 => {
-                require_buffer_size(data, offset)?; // Ensure all data is consumed for None
-                Ok(None)
-            }
-            1u8 => {
-                require_min_buffer_size(data, offset + 8)?; // Ensure there are bytes for length
-                let length = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-                offset += 8;
-
-                require_min_buffer_size(data, offset + length)?; // Ensure there are bytes for data
-                let inner_data = &data[offset..offset + length];
-                offset += length;
-
-                require_buffer_size(data, offset)?; // Ensure all data is consumed
+        if let Some(plain) = Self::plain() {
+            return (plain.rematerialize)(data);
+        }
 
-                let value = T::rematerialize(ctx, inner_data).await?;
-                Ok(Some(value))
-            }
-            _ => Err(anyhow::anyhow!(
-                "Invalid discriminant for Option: {}. Expected 0 for None or 1 for Some.",
-                discriminant
-            )),
+        match rematerialize_option_header(data)? {
+            Some(inner_data) => Ok(Some(T::rematerialize(ctx, inner_data).await?)),
+            None => Ok(None),
         }
     }
 }
 
 // -------------------------------------------------------------------------------------------------------
 
-#[async_trait]
-impl ContextTransmaterializable for anyhow::Error {
-    async fn immaterialize(
-        self,
-        ctx: &TransmaterializationContext,
-    ) -> TransmaterializationResult<Vec<u8>> {
-        let error_string = format!("{}", self);
-        error_string.immaterialize(ctx).await
+impl PlainTransmaterializable for anyhow::Error {
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+        format!("{}", self).immaterialize_plain_into(buffer)
     }
 
-    async fn rematerialize(
-        ctx: &TransmaterializationContext,
-        data: &[u8],
-    ) -> TransmaterializationResult<Self> {
-        let error_message = String::rematerialize(ctx, data).await?;
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+        let error_message = String::rematerialize_plain(data)?;
         Ok(anyhow::anyhow!(error_message))
     }
 }
 
+impl_context_transmaterializable_via_plain!(anyhow::Error);
+
 // -------------------------------------------------------------------------------------------------------
@@ -1,15 +1,28 @@
+pub mod adapters;
+mod default_impl_std;
 mod default_impl_tupl;
 mod default_implementations;
 pub mod internal_serializations;
+mod plain;
 mod rpc_proxy;
 mod util;
+pub mod wire;
+#[cfg(any(feature = "serde", feature = "bincode"))]
+mod wrappers;
 
+pub use adapters::SerializationAdapter;
 use internal_serializations::SimpleByteTransmaterializable;
+pub use plain::{PlainFns, PlainTransmaterializable};
 pub use rpc_proxy::*;
+#[cfg(feature = "bincode")]
+pub use wrappers::Bincode;
+#[cfg(feature = "serde")]
+pub use wrappers::Serde;
 
 // -------------------------------------------------------------------------------------------------------
 
 use crate::{
+    conduit::priority::Priority,
     nexus::RemoteActorId,
     portal::{BoxedRematerializer, MsgRematerializer, NexusResult, Portal, PortalActorMessage},
     util::ActorRef_Ask,
@@ -204,6 +217,9 @@ impl TransmaterializationContext {
             .instantiate_proxy_for_remote_actor(remote_actor_ref)
             .await?;
 
+        self.connection
+            .send_message(PortalActorMessage::RegisterReplyTarget(remote_actor_ref))?;
+
         let actor_ref = actor_cell;
 
         let rpc_port = rpc_reply_port_from_actor_ref(actor_ref, timeout);
@@ -246,6 +262,39 @@ pub trait ContextTransmaterializable {
     ) -> TransmaterializationResult<Self>
     where
         Self: Sized;
+
+    /// appends the immaterialized value to `buffer`, the same bytes which `immaterialize` returns.
+    ///
+    /// Containers (derived types, `Vec<T>`, tuples, `Option<T>` and `Result<T, E>`) write their elements with this,
+    /// so a nested message is written into a single buffer instead of being allocated and copied once per level
+    /// (see `wire::FieldFrame`). The default implementation copies the result of `immaterialize`.
+    async fn immaterialize_into(
+        self,
+        ctx: &TransmaterializationContext,
+        buffer: &mut Vec<u8>,
+    ) -> TransmaterializationResult<()>
+    where
+        Self: Sized,
+    {
+        let bytes = self.immaterialize(ctx).await?;
+        buffer.extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// the synchronous implementation, if the type is plain data (see `PlainTransmaterializable`).
+    /// Containers use it to (im)materialize their elements without a future per element.
+    fn plain() -> Option<PlainFns<Self>>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// the lane in which the message is sent, see `conduit::priority`.
+    /// The derive macro implements it for `#[wormhole(priority = ...)]`.
+    fn priority(&self) -> Priority {
+        Priority::Normal
+    }
 }
 
 // -------------------------------------------------------------------------------------------------------
@@ -258,39 +307,39 @@ pub mod transmaterialization_proxies {
     #[cfg(any(feature = "serde", feature = "bincode"))]
     use super::*;
 
+    /// json through serde, see `adapters::SerdeAdapter`
     #[cfg(feature = "serde")]
     pub mod serde_proxy {
 
         use super::*;
 
         pub fn immaterialize<T: serde::Serialize>(data: T) -> TransmaterializationResult<Vec<u8>> {
-            let json = serde_json::to_vec(&data)?;
-            Ok(json)
+            Ok(serde_json::to_vec(&data)?)
         }
 
         pub fn rematerialize<T: serde::de::DeserializeOwned>(
             data: &[u8],
         ) -> TransmaterializationResult<T> {
-            let deserialized = serde_json::from_slice(data)?;
-            Ok(deserialized)
+            Ok(serde_json::from_slice(data)?)
         }
     }
 
+    /// bincode with the standard configuration, see `adapters::BincodeAdapter`
     #[cfg(feature = "bincode")]
     pub mod bincode_proxy {
 
         use super::*;
 
         pub fn immaterialize<T: bincode::Encode>(data: T) -> TransmaterializationResult<Vec<u8>> {
-            let json = bincode::encode_to_vec(data, bincode::config::standard())?;
-            Ok(json)
+            Ok(bincode::encode_to_vec(data, bincode::config::standard())?)
         }
 
+        /// the data comes from the remote side, so trailing bytes are an error.
         pub fn rematerialize<T: bincode::Decode<()>>(data: &[u8]) -> TransmaterializationResult<T> {
-            let (deserialized, consumed) =
+            let (value, consumed) =
                 bincode::decode_from_slice::<T, _>(data, bincode::config::standard())?;
-            assert!(consumed == data.len());
-            Ok(deserialized)
+            require_buffer_size(data, consumed)?;
+            Ok(value)
         }
     }
 }
@@ -0,0 +1,111 @@
+//! Synchronous (im)materialization of plain data, i.e. types without `ActorRef`s and `RpcReplyPort`s, which don't need
+//! the `TransmaterializationContext`.
+//!
+//! `ContextTransmaterializable` is an `#[async_trait]`, so every call allocates a boxed future. Plain types implement
+//! `PlainTransmaterializable` as well, and return it from `ContextTransmaterializable::plain`. Containers like `Vec<T>`
+//! check `T::plain()`, and (im)materialize plain elements without any futures.
+//!
+//! A blanket `impl<T: PlainTransmaterializable> ContextTransmaterializable for T` would overlap with the impls for
+//! `Vec<T>`, `Option<T>`, ..., so the delegation is generated instead: by `impl_context_transmaterializable_via_plain!`
+//! and by `#[derive(WormholeTransmaterializable)]` with `#[wormhole(plain)]`.
+
+use super::{ContextTransmaterializable, TransmaterializationResult};
+
+// -------------------------------------------------------------------------------------------------------
+
+/// (im)materialization without the context, see the module documentation.
+/// The bytes must be the same as the ones of the `ContextTransmaterializable` implementation.
+pub trait PlainTransmaterializable: Sized {
+    /// appends the immaterialized value to `buffer`.
+    fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()>;
+
+    fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self>;
+
+    fn immaterialize_plain(self) -> TransmaterializationResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.immaterialize_plain_into(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// the functions of a `PlainTransmaterializable` implementation, returned by `ContextTransmaterializable::plain`.
+///
+/// Generic containers can't require `T: PlainTransmaterializable`, so they get the functions at runtime.
+pub struct PlainFns<T> {
+    pub immaterialize_into: fn(T, &mut Vec<u8>) -> TransmaterializationResult<()>,
+    pub rematerialize: fn(&[u8]) -> TransmaterializationResult<T>,
+}
+
+impl<T> Clone for PlainFns<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PlainFns<T> {}
+
+impl<T: PlainTransmaterializable> PlainFns<T> {
+    pub fn of() -> Self {
+        Self {
+            immaterialize_into: T::immaterialize_plain_into,
+            rematerialize: T::rematerialize_plain,
+        }
+    }
+}
+
+/// `T::plain()` inside the plain functions of a container, which are only handed out if it is `Some`.
+pub(crate) fn element_fns<T: ContextTransmaterializable>() -> TransmaterializationResult<PlainFns<T>>
+{
+    T::plain().ok_or_else(|| anyhow::anyhow!("{} is not plain data", std::any::type_name::<T>()))
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// implements `ContextTransmaterializable` for a type which implements `PlainTransmaterializable`,
+/// by delegating to it. generic parameters go in brackets in front of the type.
+///
+/// ```ignore
+/// impl PlainTransmaterializable for Celsius { ... }
+/// ractor_wormhole::impl_context_transmaterializable_via_plain!(Celsius);
+/// ractor_wormhole::impl_context_transmaterializable_via_plain!([const N: usize] Readings<N>);
+/// ```
+#[macro_export]
+macro_rules! impl_context_transmaterializable_via_plain {
+    ([$($generics:tt)*] $type:ty) => {
+        #[$crate::transmaterialization::transmaterialization_proxies::async_trait]
+        impl<$($generics)*> $crate::transmaterialization::ContextTransmaterializable for $type {
+            fn plain() -> Option<$crate::transmaterialization::PlainFns<Self>> {
+                Some($crate::transmaterialization::PlainFns::of())
+            }
+
+            async fn immaterialize(
+                self,
+                _ctx: &$crate::transmaterialization::TransmaterializationContext,
+            ) -> $crate::transmaterialization::TransmaterializationResult<Vec<u8>> {
+                $crate::transmaterialization::PlainTransmaterializable::immaterialize_plain(self)
+            }
+
+            async fn immaterialize_into(
+                self,
+                _ctx: &$crate::transmaterialization::TransmaterializationContext,
+                buffer: &mut Vec<u8>,
+            ) -> $crate::transmaterialization::TransmaterializationResult<()> {
+                $crate::transmaterialization::PlainTransmaterializable::immaterialize_plain_into(
+                    self, buffer,
+                )
+            }
+
+            async fn rematerialize(
+                _ctx: &$crate::transmaterialization::TransmaterializationContext,
+                data: &[u8],
+            ) -> $crate::transmaterialization::TransmaterializationResult<Self> {
+                <$type as $crate::transmaterialization::PlainTransmaterializable>::rematerialize_plain(
+                    data,
+                )
+            }
+        }
+    };
+    ($type:ty) => {
+        $crate::impl_context_transmaterializable_via_plain!([] $type);
+    };
+}
@@ -106,6 +106,8 @@ pub fn rpc_reply_port_from_actor_ref<T: Send + Sync + 'static>(
             }
             Err(err) => {
                 tracing::error!("Failed to receive message from RpcReplyPort: {}", err);
+                // the port was dropped without a reply, nothing will be sent through the proxy anymore
+                actor_ref.stop(Some("The RpcReplyPort was dropped".to_string()));
             }
         }
     });
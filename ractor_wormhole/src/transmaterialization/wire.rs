@@ -0,0 +1,255 @@
+//! Building blocks of the wire format, used by the library implementations and the derive macro.
+//!
+//! Nested values are written directly into the buffer of their parent: the length prefix of a field is reserved
+//! before the field is written, and filled in afterwards (see `FieldFrame`).
+//! Reading doesn't copy at all, the `WireCursor` hands out sub-slices of the received data.
+
+use super::TransmaterializationResult;
+
+// -------------------------------------------------------------------------------------------------------
+
+/// appends the value as an unsigned LEB128 varint: 7 bits per byte, the high bit is set on all but the last byte.
+pub fn write_varint(buffer: &mut Vec<u8>, value: u64) {
+    let mut bytes = [0u8; 10];
+    let len = encode_varint(value, &mut bytes);
+    buffer.extend_from_slice(&bytes[..len]);
+}
+
+fn encode_varint(mut value: u64, bytes: &mut [u8; 10]) -> usize {
+    let mut len = 0;
+    while value >= 0x80 {
+        bytes[len] = (value as u8) | 0x80;
+        value >>= 7;
+        len += 1;
+    }
+    bytes[len] = value as u8;
+    len + 1
+}
+
+/// appends a field with its length prefix: `len:u64 + bytes`.
+pub fn write_field(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+/// the `len:u64` prefix of a field which is written directly into the buffer.
+///
+/// `begin` reserves the prefix, `end` fills in the number of bytes which have been written since.
+#[must_use]
+pub struct FieldFrame {
+    start: usize,
+}
+
+impl FieldFrame {
+    pub fn begin(buffer: &mut Vec<u8>) -> Self {
+        let start = buffer.len();
+        buffer.extend_from_slice(&[0; 8]);
+        Self { start }
+    }
+
+    pub fn end(self, buffer: &mut [u8]) {
+        let len = (buffer.len() - self.start - 8) as u64;
+        buffer[self.start..self.start + 8].copy_from_slice(&len.to_le_bytes());
+    }
+}
+
+/// like `FieldFrame`, with a varint prefix for `#[wormhole(compact)]` types.
+///
+/// One byte is reserved, which is enough for fields up to 127 bytes; the field is moved if the prefix needs more.
+#[must_use]
+pub struct CompactFieldFrame {
+    start: usize,
+}
+
+impl CompactFieldFrame {
+    pub fn begin(buffer: &mut Vec<u8>) -> Self {
+        let start = buffer.len();
+        buffer.push(0);
+        Self { start }
+    }
+
+    pub fn end(self, buffer: &mut Vec<u8>) {
+        let mut prefix = [0u8; 10];
+        let len = encode_varint((buffer.len() - self.start - 1) as u64, &mut prefix);
+        match len {
+            1 => buffer[self.start] = prefix[0],
+            _ => {
+                buffer.splice(self.start..self.start + 1, prefix[..len].iter().copied());
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+/// reads the received data from front to back. All reads are bounds checked, malformed data is an error.
+pub struct WireCursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> WireCursor<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    /// true if all data has been read
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    /// the data which hasn't been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> TransmaterializationResult<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Reading {len} bytes at offset {} exceeds the buffer length {}",
+                    self.offset,
+                    self.data.len()
+                )
+            })?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> TransmaterializationResult<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u64(&mut self) -> TransmaterializationResult<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into()?))
+    }
+
+    /// reads an unsigned LEB128 varint, see `write_varint`.
+    pub fn read_varint(&mut self) -> TransmaterializationResult<u64> {
+        let start = self.offset;
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                break;
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow::anyhow!("Varint at offset {start} overflows a u64"))
+    }
+
+    /// reads the next field: `len:u64 + bytes`.
+    pub fn read_field(&mut self) -> TransmaterializationResult<&'a [u8]> {
+        let len = self.read_u64()?;
+        self.read_bytes(usize::try_from(len)?)
+    }
+
+    /// reads the next field of a compact type: `len:varint + bytes`.
+    pub fn read_compact_field(&mut self) -> TransmaterializationResult<&'a [u8]> {
+        let len = self.read_varint()?;
+        self.read_bytes(usize::try_from(len)?)
+    }
+
+    /// reads everything which is left
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let rest = self.remaining();
+        self.offset = self.data.len();
+        rest
+    }
+
+    /// all data must have been read.
+    pub fn finish(self) -> TransmaterializationResult<()> {
+        if !self.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Rematerialization did not consume all data! Buffer length: {}, consumed: {}",
+                self.data.len(),
+                self.offset
+            ));
+        }
+        Ok(())
+    }
+
+    /// the data after the known fields of a derived type must be complete fields, which were added by a newer version
+    /// of the type. They are ignored.
+    pub fn skip_unknown_fields(mut self) -> TransmaterializationResult<()> {
+        while !self.is_empty() {
+            self.read_field()?;
+        }
+        Ok(())
+    }
+
+    /// like `skip_unknown_fields`, for compact types.
+    pub fn skip_unknown_compact_fields(mut self) -> TransmaterializationResult<()> {
+        while !self.is_empty() {
+            self.read_compact_field()?;
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_roundtrip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut buffer = Vec::new();
+            write_varint(&mut buffer, value);
+            let mut cursor = WireCursor::new(&buffer);
+            assert_eq!(cursor.read_varint().unwrap(), value);
+            cursor.finish().unwrap();
+        }
+
+        // more than 64 bits
+        let mut cursor =
+            WireCursor::new(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]);
+        assert!(cursor.read_varint().is_err());
+    }
+
+    #[test]
+    fn test_frames_are_filled_in() {
+        for len in [0, 5, 127, 128, 20_000] {
+            let field = vec![0x5a; len];
+
+            let mut buffer = vec![1, 2, 3];
+            let frame = FieldFrame::begin(&mut buffer);
+            buffer.extend_from_slice(&field);
+            frame.end(&mut buffer);
+
+            let frame = CompactFieldFrame::begin(&mut buffer);
+            buffer.extend_from_slice(&field);
+            frame.end(&mut buffer);
+            buffer.push(4);
+
+            let mut cursor = WireCursor::new(&buffer);
+            assert_eq!(cursor.read_bytes(3).unwrap(), &[1, 2, 3]);
+            assert_eq!(cursor.read_field().unwrap(), field.as_slice());
+            assert_eq!(cursor.read_compact_field().unwrap(), field.as_slice());
+            assert_eq!(cursor.read_rest(), &[4]);
+            cursor.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reads_are_bounds_checked() {
+        let mut buffer = Vec::new();
+        write_field(&mut buffer, b"hello");
+
+        for len in 0..buffer.len() {
+            assert!(WireCursor::new(&buffer[..len]).read_field().is_err());
+        }
+        assert!(WireCursor::new(&buffer).finish().is_err());
+        assert!(WireCursor::new(&buffer[..3]).skip_unknown_fields().is_err());
+    }
+}
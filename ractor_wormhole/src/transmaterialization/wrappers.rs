@@ -0,0 +1,116 @@
+//! Wrappers which make any serde or bincode type a `ContextTransmaterializable`, so it can be sent as a message:
+//!
+//! ```ignore
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! pub struct Settings { /* ... */ }
+//!
+//! let proxy: ActorRef<Serde<Settings>> = portal.instantiate_proxy_for_remote_actor(id).await?;
+//! proxy.send_message(settings.into())?;
+//! ```
+
+use std::ops::{Deref, DerefMut};
+
+use async_trait::async_trait;
+
+#[cfg(feature = "bincode")]
+use super::adapters::BincodeAdapter;
+#[cfg(feature = "serde")]
+use super::adapters::SerdeAdapter;
+use super::{
+    ContextTransmaterializable, PlainFns, PlainTransmaterializable, SerializationAdapter,
+    TransmaterializationContext, TransmaterializationResult,
+};
+
+// -------------------------------------------------------------------------------------------------------
+
+macro_rules! adapter_wrapper {
+    ($(#[$meta:meta])* $feature:literal, $name:ident, $adapter:ty, [$($bounds:tt)+]) => {
+        $(#[$meta])*
+        #[cfg(feature = $feature)]
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name<T>(pub T);
+
+        #[cfg(feature = $feature)]
+        impl<T> $name<T> {
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<T> From<T> for $name<T> {
+            fn from(value: T) -> Self {
+                Self(value)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl<T> DerefMut for $name<T> {
+            fn deref_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+        }
+
+        #[cfg(all(feature = $feature, feature = "ractor_cluster"))]
+        impl<T: Send + 'static> ractor::Message for $name<T> {}
+
+        #[cfg(feature = $feature)]
+        impl<T: $($bounds)+> PlainTransmaterializable for $name<T> {
+            fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> TransmaterializationResult<()> {
+                buffer.extend_from_slice(&<$adapter as SerializationAdapter<T>>::immaterialize(self.0)?);
+                Ok(())
+            }
+
+            fn rematerialize_plain(data: &[u8]) -> TransmaterializationResult<Self> {
+                <$adapter as SerializationAdapter<T>>::rematerialize(data).map(Self)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        #[async_trait]
+        impl<T: $($bounds)+ + Send + 'static> ContextTransmaterializable for $name<T> {
+            fn plain() -> Option<PlainFns<Self>> {
+                Some(PlainFns::of())
+            }
+
+            async fn immaterialize(
+                self,
+                _ctx: &TransmaterializationContext,
+            ) -> TransmaterializationResult<Vec<u8>> {
+                <$adapter as SerializationAdapter<T>>::immaterialize(self.0)
+            }
+
+            async fn rematerialize(
+                _ctx: &TransmaterializationContext,
+                data: &[u8],
+            ) -> TransmaterializationResult<Self> {
+                Self::rematerialize_plain(data)
+            }
+        }
+    };
+}
+
+adapter_wrapper!(
+    /// a serde type, sent as json (see `adapters::SerdeAdapter`).
+    "serde",
+    Serde,
+    SerdeAdapter,
+    [serde::Serialize + serde::de::DeserializeOwned]
+);
+
+adapter_wrapper!(
+    /// a bincode type, sent with the standard bincode configuration (see `adapters::BincodeAdapter`).
+    "bincode",
+    Bincode,
+    BincodeAdapter,
+    [bincode::Encode + bincode::Decode<()>]
+);
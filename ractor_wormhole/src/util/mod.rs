@@ -5,3 +5,15 @@ mod function_actor;
 pub use ask::*;
 pub use combinators::*;
 pub use function_actor::*;
+
+/// `Send` on native targets. On wasm, futures usually hold js values, which are not `Send` (and don't need to be).
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+/// `Send` on native targets. On wasm, futures usually hold js values, which are not `Send` (and don't need to be).
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
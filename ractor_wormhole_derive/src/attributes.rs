@@ -0,0 +1,368 @@
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::quote;
+use venial::{Attribute, AttributeValue, TypeExpr};
+
+use crate::util::bail;
+
+// parsing of the `#[wormhole(...)]` attributes
+// -----------------------------------------------------------------------------------------------------------------------------------
+
+/// one entry of `#[wormhole(key, key = value, ...)]`
+pub struct WormholeArg {
+    pub key: Ident,
+    pub value: Option<Vec<TokenTree>>,
+}
+
+/// collects the entries of all `#[wormhole(...)]` attributes.
+pub fn parse_wormhole_args(attributes: &[Attribute]) -> Result<Vec<WormholeArg>, venial::Error> {
+    let mut args = Vec::new();
+
+    for attribute in attributes {
+        if attribute.get_single_path_segment().map(|s| s.to_string()) != Some("wormhole".into()) {
+            continue;
+        }
+        let AttributeValue::Group(_, tokens) = &attribute.value else {
+            return bail!(attribute, "Expected #[wormhole(...)]");
+        };
+
+        for entry in tokens.split(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ',')) {
+            let Some((first, rest)) = entry.split_first() else {
+                continue;
+            };
+            let TokenTree::Ident(key) = first else {
+                return bail!(first, "Expected an identifier in #[wormhole(...)]");
+            };
+            let value = match rest.split_first() {
+                None => None,
+                Some((TokenTree::Punct(p), value)) if p.as_char() == '=' && !value.is_empty() => {
+                    Some(value.to_vec())
+                }
+                Some((other, _)) => {
+                    return bail!(
+                        other,
+                        "Expected `{key}` or `{key} = value` in #[wormhole(...)]"
+                    );
+                }
+            };
+            args.push(WormholeArg {
+                key: key.clone(),
+                value,
+            });
+        }
+    }
+
+    Ok(args)
+}
+
+/// the value as a single identifier or string literal, e.g. `bulk` or `"bulk"`.
+fn value_as_name(arg: &WormholeArg) -> Result<String, venial::Error> {
+    match arg.value.as_deref() {
+        Some([TokenTree::Ident(ident)]) => Ok(ident.to_string()),
+        Some([TokenTree::Literal(literal)]) => {
+            let text = literal.to_string();
+            match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+                Some(name) => Ok(name.to_string()),
+                None => bail!(literal, "Expected a name, e.g. `{} = bulk`", arg.key),
+            }
+        }
+        _ => bail!(&arg.key, "Expected a name, e.g. `{} = bulk`", arg.key),
+    }
+}
+
+// container attributes
+// -----------------------------------------------------------------------------------------------------------------------------------
+
+/// the `#[wormhole(...)]` attributes on the struct or enum itself.
+#[derive(Default)]
+pub struct ContainerAttributes {
+    /// the lane in which the message is sent, see `ractor_wormhole::conduit::priority`
+    pub priority: Option<Ident>,
+    /// `#[wormhole(compact)]`: varint lengths and numeric discriminants instead of u64 lengths and variant names.
+    /// The discriminant is the index of the variant, or its explicit discriminant (`A = 3`).
+    pub compact: Option<Ident>,
+    /// `#[wormhole(transparent)]`: a struct with a single field is sent exactly like that field
+    pub transparent: Option<Ident>,
+    /// `#[wormhole(plain)]`: the type has no `ActorRef`s or `RpcReplyPort`s, it also implements `PlainTransmaterializable`
+    pub plain: Option<Ident>,
+}
+
+impl ContainerAttributes {
+    pub fn parse(attributes: &[Attribute]) -> Result<Self, venial::Error> {
+        let mut result = Self::default();
+
+        for arg in parse_wormhole_args(attributes)? {
+            match arg.key.to_string().as_str() {
+                "priority" => {
+                    let variant = match value_as_name(&arg)?.as_str() {
+                        "control" => "Control",
+                        "reply" => "Reply",
+                        "normal" => "Normal",
+                        "bulk" => "Bulk",
+                        other => {
+                            return bail!(
+                                &arg.key,
+                                "Unknown priority `{other}`, expected one of control, reply, normal, bulk"
+                            );
+                        }
+                    };
+                    result.priority = Some(Ident::new(variant, arg.key.span()));
+                }
+                "compact" => {
+                    require_no_value(&arg)?;
+                    result.compact = Some(arg.key);
+                }
+                "transparent" => {
+                    require_no_value(&arg)?;
+                    result.transparent = Some(arg.key);
+                }
+                "plain" => {
+                    require_no_value(&arg)?;
+                    result.plain = Some(arg.key);
+                }
+                other => return bail!(&arg.key, "Unknown attribute #[wormhole({other})]"),
+            }
+        }
+
+        if let (Some(_), Some(transparent)) = (&result.compact, &result.transparent) {
+            return bail!(
+                transparent,
+                "#[wormhole(transparent)] can't be combined with #[wormhole(compact)], the field isn't framed at all."
+            );
+        }
+
+        Ok(result)
+    }
+
+    /// additional items for the `ContextTransmaterializable` impl
+    pub fn trait_items(&self) -> TokenStream {
+        match &self.priority {
+            Some(priority) => quote! {
+                fn priority(&self) -> ::ractor_wormhole::conduit::priority::Priority {
+                    ::ractor_wormhole::conduit::priority::Priority::#priority
+                }
+            },
+            None => quote! {},
+        }
+    }
+}
+
+// field attributes
+// -----------------------------------------------------------------------------------------------------------------------------------
+
+/// how a field is (im)materialized, selected by the `#[serde]`, `#[bincode]` and `#[wormhole(with = adapter)]` attributes on the field.
+pub enum FieldCodec {
+    /// through `ContextTransmaterializable`, the default
+    Context,
+    /// through a `ractor_wormhole::transmaterialization::SerializationAdapter`, the tokens are the path of the adapter
+    Adapter(TokenStream),
+}
+
+/// the attributes on a field of a struct or enum variant.
+/// Fields are sent by position, so there is no `rename` or `alias` (see the README on versioning).
+pub struct FieldAttributes {
+    pub codec: FieldCodec,
+    /// `#[wormhole(skip)]`: the field isn't sent, it is rematerialized as `Default::default()`
+    pub skip: bool,
+    /// `#[wormhole(default)]`: the field is `Default::default()` if the data ends before it (e.g. it was sent by an older version)
+    pub default: bool,
+}
+
+impl FieldAttributes {
+    pub fn parse(attributes: &[Attribute]) -> Result<Self, venial::Error> {
+        let mut adapters = Vec::new();
+        let mut skip = None;
+        let mut default = false;
+
+        for attribute in attributes {
+            let name = attribute.get_single_path_segment().map(|s| s.to_string());
+            let adapter = match name.as_deref() {
+                Some("serde") => {
+                    quote! { ::ractor_wormhole::transmaterialization::adapters::SerdeAdapter }
+                }
+                Some("bincode") => {
+                    quote! { ::ractor_wormhole::transmaterialization::adapters::BincodeAdapter }
+                }
+                _ => continue,
+            };
+            if !matches!(attribute.value, AttributeValue::Empty) {
+                return bail!(
+                    attribute,
+                    "Expected #[{}] without arguments",
+                    name.unwrap_or_default()
+                );
+            }
+            adapters.push((attribute.path.first().cloned(), adapter));
+        }
+
+        for arg in parse_wormhole_args(attributes)? {
+            match arg.key.to_string().as_str() {
+                "with" => {
+                    let Some(path) = &arg.value else {
+                        return bail!(
+                            &arg.key,
+                            "Expected a path, e.g. `with = my_crate::PostcardAdapter`"
+                        );
+                    };
+                    adapters.push((
+                        Some(TokenTree::Ident(arg.key.clone())),
+                        path.iter().cloned().collect(),
+                    ));
+                }
+                "skip" => {
+                    require_no_value(&arg)?;
+                    skip = Some(arg.key.clone());
+                }
+                "default" => {
+                    require_no_value(&arg)?;
+                    default = true;
+                }
+                "rename" | "alias" => {
+                    return bail!(
+                        &arg.key,
+                        "Fields are sent by position, not by name, so they can be renamed without #[wormhole({})]. Only enum variants are sent by name.",
+                        arg.key
+                    );
+                }
+                other => return bail!(&arg.key, "Unknown field attribute #[wormhole({other})]"),
+            }
+        }
+
+        let codec = match adapters.len() {
+            0 => FieldCodec::Context,
+            1 => FieldCodec::Adapter(adapters.pop().unwrap().1),
+            _ => {
+                return bail!(
+                    &adapters[1].0,
+                    "A field can only have one of #[serde], #[bincode] and #[wormhole(with = ...)]."
+                );
+            }
+        };
+        if let Some(skip) = &skip
+            && (!matches!(codec, FieldCodec::Context) || default)
+        {
+            return bail!(
+                skip,
+                "A skipped field can't have other attributes, it is always `Default::default()`."
+            );
+        }
+
+        Ok(Self {
+            codec,
+            skip: skip.is_some(),
+            default,
+        })
+    }
+
+    /// an expression which immaterializes `value` into a `Vec<u8>`, for use in `ContextTransmaterializable::immaterialize`.
+    pub fn immaterialize(&self, ty: &TypeExpr, value: TokenStream) -> TokenStream {
+        match &self.codec {
+            FieldCodec::Context => quote! {
+                <#ty as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::immaterialize(#value, ctx).await?
+            },
+            FieldCodec::Adapter(adapter) => quote! {
+                <#adapter as ::ractor_wormhole::transmaterialization::SerializationAdapter<#ty>>::immaterialize(#value)?
+            },
+        }
+    }
+
+    /// a statement which appends the immaterialized `value` to `buffer`, for use in `ContextTransmaterializable::immaterialize_into`,
+    /// or in `PlainTransmaterializable::immaterialize_plain_into` if `plain`.
+    /// Plain field types are immaterialized synchronously in both cases.
+    pub fn immaterialize_into(
+        &self,
+        ty: &TypeExpr,
+        value: TokenStream,
+        plain: bool,
+    ) -> TokenStream {
+        match &self.codec {
+            FieldCodec::Context if plain => quote! {
+                <#ty as ::ractor_wormhole::transmaterialization::PlainTransmaterializable>::immaterialize_plain_into(#value, buffer)?;
+            },
+            FieldCodec::Context => quote! {
+                match <#ty as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::plain() {
+                    Some(plain) => (plain.immaterialize_into)(#value, buffer)?,
+                    None => <#ty as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::immaterialize_into(#value, ctx, buffer).await?,
+                }
+            },
+            FieldCodec::Adapter(_) => {
+                let immaterialize = self.immaterialize(ty, value);
+                quote! { buffer.extend_from_slice(&#immaterialize); }
+            }
+        }
+    }
+
+    /// an expression which rematerializes the field from `bytes`, for use in `ContextTransmaterializable::rematerialize`,
+    /// or in `PlainTransmaterializable::rematerialize_plain` if `plain`.
+    pub fn rematerialize(&self, ty: &TypeExpr, bytes: TokenStream, plain: bool) -> TokenStream {
+        match &self.codec {
+            FieldCodec::Context if plain => quote! {
+                <#ty as ::ractor_wormhole::transmaterialization::PlainTransmaterializable>::rematerialize_plain(#bytes)?
+            },
+            FieldCodec::Context => quote! {
+                match <#ty as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::plain() {
+                    Some(plain) => (plain.rematerialize)(#bytes)?,
+                    None => <#ty as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::rematerialize(ctx, #bytes).await?,
+                }
+            },
+            FieldCodec::Adapter(adapter) => quote! {
+                <#adapter as ::ractor_wormhole::transmaterialization::SerializationAdapter<#ty>>::rematerialize(#bytes)?
+            },
+        }
+    }
+}
+
+fn require_no_value(arg: &WormholeArg) -> Result<(), venial::Error> {
+    match &arg.value {
+        Some(value) => bail!(
+            &value[0],
+            "Expected #[wormhole({})] without a value",
+            arg.key
+        ),
+        None => Ok(()),
+    }
+}
+
+// variant attributes
+// -----------------------------------------------------------------------------------------------------------------------------------
+
+/// the attributes on an enum variant. Variants are sent by name.
+#[derive(Default)]
+pub struct VariantAttributes {
+    /// `#[wormhole(rename = "...")]`: the name which is sent instead of the name of the variant
+    pub rename: Option<String>,
+    /// `#[wormhole(alias = "...")]`: other names which are accepted, e.g. the old name of a renamed variant
+    pub aliases: Vec<String>,
+    /// `#[wormhole(other)]`: the variant for all names which aren't known (e.g. sent by a newer version)
+    pub other: Option<Ident>,
+}
+
+impl VariantAttributes {
+    pub fn parse(attributes: &[Attribute]) -> Result<Self, venial::Error> {
+        let mut result = Self::default();
+
+        for arg in parse_wormhole_args(attributes)? {
+            match arg.key.to_string().as_str() {
+                "rename" => result.rename = Some(value_as_string(&arg)?),
+                "alias" => result.aliases.push(value_as_string(&arg)?),
+                "other" => {
+                    require_no_value(&arg)?;
+                    result.other = Some(arg.key.clone());
+                }
+                other => return bail!(&arg.key, "Unknown variant attribute #[wormhole({other})]"),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// the value as a string literal, e.g. `"OldName"`.
+fn value_as_string(arg: &WormholeArg) -> Result<String, venial::Error> {
+    if let Some([TokenTree::Literal(literal)]) = arg.value.as_deref() {
+        let text = literal.to_string();
+        if let Some(name) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            return Ok(name.to_string());
+        }
+    }
+    bail!(&arg.key, "Expected a string, e.g. `{} = \"Name\"`", arg.key)
+}
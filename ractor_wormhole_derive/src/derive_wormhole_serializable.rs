@@ -1,8 +1,11 @@
+use std::collections::HashSet;
+
+use crate::attributes::{ContainerAttributes, FieldAttributes, FieldCodec, VariantAttributes};
 use crate::util::bail;
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, TokenStream, TokenTree};
 use quote::{format_ident, quote};
-use venial::NamedField;
+use venial::{GenericParamList, NamedField, TupleField, TypeExpr, WhereClause};
 
 // Usage
 // -----------------------------------------------------------------------------------------------------------------------------------
@@ -264,238 +267,446 @@ impl ::ractor_wormhole::transmaterialization::ContextTransmaterializable for Dum
 // <end of example>
 // -----------------------------------------------------------------------------------------------------------------------------------
 
-fn for_field(field: &NamedField) -> Result<(TokenStream, TokenStream), venial::Error> {
-    let field_name = field.name.clone();
-    let field_type = field.ty.clone();
-
-    let ident_field_bytes = format_ident!("field_bytes_{field_name}");
-    let ident_field_len = format_ident!("field_len_{field_name}");
-    let ident_field = format_ident!("field_{field_name}");
-
-    let has_attr_serde = field.attributes.iter().any(|a| {
-        matches!(
-            a.get_single_path_segment()
-                .map(|s| s.to_string())
-                .as_deref(),
-            Some("serde")
-        )
-    });
-    let has_attr_bincode = field.attributes.iter().any(|a| {
-        matches!(
-            a.get_single_path_segment()
-                .map(|s| s.to_string())
-                .as_deref(),
-            Some("bincode")
-        )
-    });
+// -----------------------------------------------------------------------------------------------------------------------------------
 
-    if has_attr_serde && has_attr_bincode {
-        return bail!(
-            field,
-            "Field {field_name} cannot have both #[serde] and #[bincode] attributes."
-        );
+/// a field of a struct or enum variant
+struct FieldInfo {
+    attributes: FieldAttributes,
+    ty: TypeExpr,
+    /// the expression the field is immaterialized from, e.g. `self.a`
+    source: TokenStream,
+    /// the variable the field is rematerialized into
+    target: Ident,
+}
+
+impl FieldInfo {
+    /// true if the field is (im)materialized through `ContextTransmaterializable`, which needs the context
+    fn uses_ctx(&self) -> bool {
+        !self.attributes.skip && matches!(self.attributes.codec, FieldCodec::Context)
     }
-    if has_attr_serde {
-        return bail!(field, "#[serde] attribute is not yet implemented");
+
+    fn named(field: &NamedField, source: TokenStream) -> Result<Self, venial::Error> {
+        Ok(Self {
+            attributes: FieldAttributes::parse(&field.attributes)?,
+            ty: field.ty.clone(),
+            source,
+            target: format_ident!("field_{}", field.name),
+        })
     }
-    if has_attr_bincode {
-        return bail!(field, "#[bincode] attribute is not yet implemented");
+
+    fn tuple(field: &TupleField, index: usize, source: TokenStream) -> Result<Self, venial::Error> {
+        Ok(Self {
+            attributes: FieldAttributes::parse(&field.attributes)?,
+            ty: field.ty.clone(),
+            source,
+            target: format_ident!("field_{index}"),
+        })
     }
+}
 
-    let serialize = quote! {
-        let #ident_field_bytes = <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::immaterialize(self.#field_name, ctx).await?;
-        buffer.extend_from_slice(&(#ident_field_bytes.len() as u64).to_le_bytes());
-        buffer.extend_from_slice(&#ident_field_bytes);
-    };
+/// the name of the context parameter, prefixed with `_` if no field uses it.
+fn ctx_param(used: bool) -> TokenStream {
+    match used {
+        true => quote! { ctx },
+        false => quote! { _ctx },
+    }
+}
 
-    let deserialize = quote! {
-        let #ident_field_len = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-        offset += 8;
-        let #ident_field_bytes = &data[offset..offset + #ident_field_len];
-        offset += #ident_field_len;
-        let #ident_field = <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::rematerialize(ctx, #ident_field_bytes).await?;
+/// `#[wormhole(default)]` fields may be missing at the end of the data, so all fields after them must be optional, too.
+fn check_default_fields(fields: &[FieldInfo]) -> Result<(), venial::Error> {
+    let mut after_default = false;
+    for field in fields {
+        if field.attributes.default {
+            after_default = true;
+        } else if after_default && !field.attributes.skip {
+            return bail!(
+                &field.ty,
+                "Fields after a #[wormhole(default)] field must be #[wormhole(default)], too."
+            );
+        }
+    }
+    Ok(())
+}
+
+/// the code which appends the fields to `buffer`: `len:u64 + bytes` (or `len:varint + bytes` if `compact`)
+/// for every field which isn't skipped. The fields are written directly into `buffer`, the length is filled in afterwards.
+/// `plain` generates the synchronous code of a `#[wormhole(plain)]` type.
+fn write_fields(fields: &[FieldInfo], compact: bool, plain: bool) -> TokenStream {
+    let frame = match compact {
+        true => quote! { ::ractor_wormhole::transmaterialization::wire::CompactFieldFrame },
+        false => quote! { ::ractor_wormhole::transmaterialization::wire::FieldFrame },
     };
 
-    Ok((serialize, deserialize))
+    let writes = fields
+        .iter()
+        .filter(|field| !field.attributes.skip)
+        .map(|field| {
+            let immaterialize =
+                field
+                    .attributes
+                    .immaterialize_into(&field.ty, field.source.clone(), plain);
+            quote! {
+                let frame = #frame::begin(buffer);
+                #immaterialize
+                frame.end(buffer);
+            }
+        });
+
+    quote! { #(#writes)* }
 }
 
-fn derive_struct(input: venial::Struct) -> Result<proc_macro2::TokenStream, venial::Error> {
-    let struct_name = input.name.clone();
+/// the code which reads the fields from `data` into their targets. Unknown fields at the end (from a newer version) are ignored.
+fn read_fields(
+    fields: &[FieldInfo],
+    data: &Ident,
+    compact: bool,
+    plain: bool,
+) -> Result<TokenStream, venial::Error> {
+    check_default_fields(fields)?;
+
+    let (read_field, skip_unknown_fields) = match compact {
+        true => (
+            quote! { read_compact_field },
+            quote! { skip_unknown_compact_fields },
+        ),
+        false => (quote! { read_field }, quote! { skip_unknown_fields }),
+    };
+
+    let reads: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let FieldInfo { ty, target, .. } = field;
+            if field.attributes.skip {
+                return quote! {
+                    let #target: #ty = ::core::default::Default::default();
+                };
+            }
 
-    // Extract generic parameters
-    let generic_params = input.generic_params.clone();
+            let rematerialize = field
+                .attributes
+                .rematerialize(ty, quote! { field_bytes }, plain);
+            let read = quote! {
+                let field_bytes = cursor.#read_field()?;
+                #rematerialize
+            };
+            match field.attributes.default {
+                true => quote! {
+                    let #target: #ty = if !cursor.is_empty() {
+                        #read
+                    } else {
+                        ::core::default::Default::default()
+                    };
+                },
+                false => quote! {
+                    let #target: #ty = { #read };
+                },
+            }
+        })
+        .collect();
 
-    // Generate impl generics and type generics
-    let impl_generics = if let Some(generic_params) = &generic_params {
-        quote! { #generic_params }
-    } else {
-        quote! {}
+    let any_read = fields.iter().any(|field| !field.attributes.skip);
+    let cursor = match any_read {
+        true => {
+            quote! { let mut cursor = ::ractor_wormhole::transmaterialization::wire::WireCursor::new(#data); }
+        }
+        false => {
+            quote! { let cursor = ::ractor_wormhole::transmaterialization::wire::WireCursor::new(#data); }
+        }
     };
 
-    // Generate the type with its generics for the impl block
-    let type_generics = if let Some(generic_params) = &generic_params {
-        let params = generic_params.params.iter().map(|(param, _)| {
-            let ident = &param.name;
-            quote! { #ident }
-        });
-        quote! { <#(#params),*> }
-    } else {
-        quote! {}
+    Ok(quote! {
+        #cursor
+        #(#reads)*
+        cursor.#skip_unknown_fields()?;
+    })
+}
+
+/// the code for `#[wormhole(transparent)]`: the single field is written into `buffer` and read from `data` without any framing.
+fn transparent_field(
+    transparent: &Ident,
+    fields: &[FieldInfo],
+    plain: bool,
+) -> Result<(TokenStream, TokenStream), venial::Error> {
+    let [field] = fields else {
+        return bail!(
+            transparent,
+            "#[wormhole(transparent)] requires a struct with exactly one field"
+        );
     };
+    if field.attributes.skip || field.attributes.default {
+        return bail!(
+            &field.ty,
+            "The field of a #[wormhole(transparent)] struct can't be skipped or defaulted"
+        );
+    }
 
-    // Generate trait bounds for generic parameters
-    let extended_where_clause = input.create_derive_where_clause(
-        quote! {::ractor_wormhole::transmaterialization::ContextTransmaterializable},
-    );
+    let FieldInfo { ty, target, .. } = field;
+    let immaterialize = field
+        .attributes
+        .immaterialize_into(ty, field.source.clone(), plain);
+    let rematerialize = field.attributes.rematerialize(ty, quote! { data }, plain);
+    Ok((immaterialize, quote! { let #target: #ty = #rematerialize; }))
+}
 
-    match &input.fields {
-        venial::Fields::Named(named_fields) => {
-            let fields = named_fields
-                .fields
-                .iter()
-                .map(|(field, _)| for_field(field))
-                .collect::<Result<Vec<_>, _>>()?;
+/// the type the impls are generated for
+struct ImplTarget {
+    /// the generic parameters of the impl, e.g. `<T>`
+    impl_generics: TokenStream,
+    /// the type with its generic arguments, e.g. `Message<T>`
+    ty: TokenStream,
+    /// the where clause of the `ContextTransmaterializable` impl
+    context_where: TokenStream,
+    /// the where clause of the `PlainTransmaterializable` impl of a `#[wormhole(plain)]` type
+    plain_where: TokenStream,
+}
 
-            let (serialize, deserialize): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+impl ImplTarget {
+    /// `create_where_clause` adds a bound to every type parameter, i.e. `create_derive_where_clause` of the struct or enum.
+    fn new(
+        name: &Ident,
+        generic_params: &Option<GenericParamList>,
+        create_where_clause: impl Fn(TokenStream) -> WhereClause,
+    ) -> Self {
+        // Generate impl generics and type generics
+        let (impl_generics, type_generics) = match generic_params {
+            Some(generic_params) => {
+                let params = generic_params.params.iter().map(|(param, _)| {
+                    let ident = &param.name;
+                    quote! { #ident }
+                });
+                (quote! { #generic_params }, quote! { <#(#params),*> })
+            }
+            None => (quote! {}, quote! {}),
+        };
 
-            // Create the struct reconstruction with all fields
-            let field_names = named_fields.fields.iter().map(|(field, _)| {
-                let field_name = field.name.clone();
-                let ident_field = format_ident!("field_{field_name}");
-                quote! { #field_name: #ident_field }
-            });
+        // Generate trait bounds for generic parameters
+        let context_where = create_where_clause(
+            quote! {::ractor_wormhole::transmaterialization::ContextTransmaterializable},
+        );
+        let plain_where = create_where_clause(
+            quote! {::ractor_wormhole::transmaterialization::PlainTransmaterializable},
+        );
 
-            let q = quote! {
-                #[::ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
-                impl #impl_generics ::ractor_wormhole::transmaterialization::ContextTransmaterializable for #struct_name #type_generics #extended_where_clause {
-                    async fn immaterialize(self, ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Vec<u8> >  {
-                        let mut buffer = Vec::new();
+        Self {
+            impl_generics,
+            ty: quote! { #name #type_generics },
+            context_where: quote! { #context_where },
+            plain_where: quote! { #plain_where },
+        }
+    }
+}
 
-                        #(#serialize)*
+/// the `ContextTransmaterializable` impl with the bodies of `immaterialize_into` and `rematerialize`.
+///
+/// For a `#[wormhole(plain)]` type, the bodies are synchronous and go into a `PlainTransmaterializable` impl instead,
+/// and the `ContextTransmaterializable` impl delegates to it.
+fn implementations(
+    container: &ContainerAttributes,
+    target: &ImplTarget,
+    ctx: TokenStream,
+    immaterialize_into: TokenStream,
+    rematerialize: TokenStream,
+) -> TokenStream {
+    let ImplTarget {
+        impl_generics,
+        ty,
+        context_where,
+        plain_where,
+    } = target;
+    let trait_items = container.trait_items();
+
+    if container.plain.is_some() {
+        return quote! {
+            impl #impl_generics ::ractor_wormhole::transmaterialization::PlainTransmaterializable for #ty #plain_where {
+                fn immaterialize_plain_into(self, buffer: &mut Vec<u8>) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<()> {
+                    #immaterialize_into
+                }
 
-                        Ok(buffer)
-                    }
+                fn rematerialize_plain(data: &[u8]) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self> {
+                    #rematerialize
+                }
+            }
 
-                    async fn rematerialize(ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext, data: &[u8]) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self>  {
-                        let mut offset = 0;
+            #[::ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
+            impl #impl_generics ::ractor_wormhole::transmaterialization::ContextTransmaterializable for #ty #plain_where {
+                #trait_items
 
-                        #(#deserialize)*
+                fn plain() -> Option<::ractor_wormhole::transmaterialization::PlainFns<Self>> {
+                    Some(::ractor_wormhole::transmaterialization::PlainFns::of())
+                }
 
-                        if data.len() != offset {
-                            return Err(::ractor_wormhole::transmaterialization::transmaterialization_proxies::anyhow!("Rematerialization did not consume all data! Buffer length: {}, consumed: {}", data.len(), offset));
-                        }
+                async fn immaterialize(
+                    self,
+                    _ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
+                ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Vec<u8>> {
+                    ::ractor_wormhole::transmaterialization::PlainTransmaterializable::immaterialize_plain(self)
+                }
 
-                        Ok(Self { #(#field_names),* })
-                    }
+                async fn immaterialize_into(
+                    self,
+                    _ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
+                    buffer: &mut Vec<u8>,
+                ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<()> {
+                    ::ractor_wormhole::transmaterialization::PlainTransmaterializable::immaterialize_plain_into(self, buffer)
                 }
-            };
 
-            Ok(q)
-        }
-        venial::Fields::Tuple(tuple_fields) => {
-            // Handle tuple structs like `struct UserAlias(String)`
-            let field_count = tuple_fields.fields.len();
-            let field_types: Vec<_> = tuple_fields
-                .fields
-                .iter()
-                .map(|(f, _)| f.ty.clone())
-                .collect();
-
-            // Generate serialization code for tuple fields
-            let mut serialize_fields = Vec::new();
-            for i in 0..field_count {
-                let field_type = &field_types[i];
-                let field_bytes_ident = format_ident!("field_bytes_{}", i);
-                let index = proc_macro2::Literal::usize_unsuffixed(i);
-
-                let serialize_field = quote! {
-                    let #field_bytes_ident = <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::immaterialize(
-                        self.#index, ctx
-                    ).await?;
-                    buffer.extend_from_slice(&(#field_bytes_ident.len() as u64).to_le_bytes());
-                    buffer.extend_from_slice(&#field_bytes_ident);
-                };
-                serialize_fields.push(serialize_field);
+                async fn rematerialize(
+                    _ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
+                    data: &[u8],
+                ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self> {
+                    <Self as ::ractor_wormhole::transmaterialization::PlainTransmaterializable>::rematerialize_plain(data)
+                }
             }
+        };
+    }
 
-            // Generate deserialization code for tuple fields
-            let mut deserialize_fields = Vec::new();
-            let mut field_value_idents = Vec::new();
-            for i in 0..field_count {
-                let field_type = &field_types[i];
-                let field_len_ident = format_ident!("field{}_len", i);
-                let field_bytes_ident = format_ident!("field{}_bytes", i);
-                let field_value_ident = format_ident!("field{}_value", i);
-                field_value_idents.push(field_value_ident.clone());
-
-                deserialize_fields.push(quote! {
-                    let #field_len_ident = u64::from_le_bytes(
-                        data[offset..offset + 8].try_into()?
-                    ) as usize;
-                    offset += 8;
-                    let #field_bytes_ident = &data[offset..offset + #field_len_ident];
-                    offset += #field_len_ident;
-                    let #field_value_ident =
-                        <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::rematerialize(
-                            ctx, #field_bytes_ident
-                        ).await?;
-                });
-            }
+    quote! {
+        #[::ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
+        impl #impl_generics ::ractor_wormhole::transmaterialization::ContextTransmaterializable for #ty #context_where {
+            #trait_items
 
-            let q = quote! {
-                #[::ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
-                impl #impl_generics ::ractor_wormhole::transmaterialization::ContextTransmaterializable for #struct_name #type_generics #extended_where_clause {
-                    async fn immaterialize(self, ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Vec<u8> >  {
-                        let mut buffer = Vec::new();
+            // writes into a new buffer with `immaterialize_into`
+            async fn immaterialize(
+                self,
+                ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
+            ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Vec<u8>> {
+                let mut buffer = Vec::new();
+                ::ractor_wormhole::transmaterialization::ContextTransmaterializable::immaterialize_into(self, ctx, &mut buffer).await?;
+                Ok(buffer)
+            }
 
-                        #(#serialize_fields)*
+            async fn immaterialize_into(
+                self,
+                #ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
+                buffer: &mut Vec<u8>,
+            ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<()> {
+                #immaterialize_into
+            }
 
-                        Ok(buffer)
-                    }
+            async fn rematerialize(
+                #ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
+                data: &[u8],
+            ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self> {
+                #rematerialize
+            }
+        }
+    }
+}
 
-                    async fn rematerialize(ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext, data: &[u8]) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self>  {
-                        let mut offset = 0;
+// -----------------------------------------------------------------------------------------------------------------------------------
 
-                        #(#deserialize_fields)*
+fn derive_struct(input: venial::Struct) -> Result<proc_macro2::TokenStream, venial::Error> {
+    let struct_name = input.name.clone();
+    let container = ContainerAttributes::parse(&input.attributes)?;
+    let plain = container.plain.is_some();
+    let target = ImplTarget::new(&struct_name, &input.generic_params, |bound| {
+        input.create_derive_where_clause(bound)
+    });
 
-                        if data.len() != offset {
-                            return Err(::ractor_wormhole::transmaterialization::transmaterialization_proxies::anyhow!("Rematerialization did not consume all data! Buffer length: {}, consumed: {}", data.len(), offset));
-                        }
+    let (fields, construct) = match &input.fields {
+        venial::Fields::Named(named_fields) => {
+            let fields = named_fields
+                .fields
+                .iter()
+                .map(|(field, _)| {
+                    let field_name = &field.name;
+                    FieldInfo::named(field, quote! { self.#field_name })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
 
-                        Ok(Self(#(#field_value_idents),*))
-                    }
-                }
-            };
+            let field_names = named_fields.fields.iter().map(|(field, _)| &field.name);
+            let targets = fields.iter().map(|field| &field.target);
+            let construct = quote! { Self { #(#field_names: #targets),* } };
+            (fields, construct)
+        }
+        // tuple structs like `struct UserAlias(String)`
+        venial::Fields::Tuple(tuple_fields) => {
+            let fields = tuple_fields
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, (field, _))| {
+                    let index = proc_macro2::Literal::usize_unsuffixed(i);
+                    FieldInfo::tuple(field, i, quote! { self.#index })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
 
-            Ok(q)
+            let targets = fields.iter().map(|field| &field.target);
+            let construct = quote! { Self(#(#targets),*) };
+            (fields, construct)
         }
-        venial::Fields::Unit => {
-            // Handle unit structs like `struct EmptyStruct;`
-            let q = quote! {
-                #[::ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
-                impl #impl_generics ::ractor_wormhole::transmaterialization::ContextTransmaterializable for #struct_name #type_generics #extended_where_clause {
-                    async fn immaterialize(self, _ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Vec<u8> >  {
-                        // Unit structs have no data to immaterialize
-                        Ok(Vec::new())
-                    }
-
-                    async fn rematerialize(_ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext, data: &[u8]) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self>  {
-                        // Ensure we received empty data
-                        if data.len() != 0 {
-                            return Err(::ractor_wormhole::transmaterialization::transmaterialization_proxies::anyhow!("Unit struct should have no data to rematerialize, but buffer has len={}", data.len()));
-                        }
-                        Ok(Self)
-                    }
-                }
-            };
+        // unit structs like `struct EmptyStruct;`
+        venial::Fields::Unit => (Vec::new(), quote! { Self }),
+    };
+
+    let ctx = ctx_param(fields.iter().any(FieldInfo::uses_ctx));
+
+    let (write, read) = match &container.transparent {
+        Some(transparent) => transparent_field(transparent, &fields, plain)?,
+        None => (
+            write_fields(&fields, container.compact.is_some(), plain),
+            read_fields(
+                &fields,
+                &format_ident!("data"),
+                container.compact.is_some(),
+                plain,
+            )?,
+        ),
+    };
+
+    Ok(implementations(
+        &container,
+        &target,
+        ctx,
+        quote! {
+            #write
+
+            Ok(())
+        },
+        quote! {
+            #read
+
+            Ok(#construct)
+        },
+    ))
+}
 
-            Ok(q)
+/// the value of an explicit discriminant, e.g. `A = 3`, which is the index of the variant in a `#[wormhole(compact)]` enum.
+fn discriminant_value(value: &TokenTree) -> Result<u64, venial::Error> {
+    let parsed = match value {
+        TokenTree::Literal(literal) => {
+            let text = literal.to_string().replace('_', "");
+            // without the type suffix, e.g. `3u8`
+            let digits = text.split(['u', 'i']).next().unwrap_or_default();
+            match digits.get(..2) {
+                Some("0x") => u64::from_str_radix(&digits[2..], 16).ok(),
+                Some("0o") => u64::from_str_radix(&digits[2..], 8).ok(),
+                Some("0b") => u64::from_str_radix(&digits[2..], 2).ok(),
+                _ => digits.parse().ok(),
+            }
         }
+        _ => None,
+    };
+    match parsed {
+        Some(index) => Ok(index),
+        None => bail!(
+            value,
+            "The discriminants of a #[wormhole(compact)] enum are sent as the index of the variant, so they must be non-negative integer literals."
+        ),
     }
 }
 
 fn derive_enum(input: venial::Enum) -> Result<proc_macro2::TokenStream, venial::Error> {
     let enum_name = input.name.clone();
+    let container = ContainerAttributes::parse(&input.attributes)?;
+    if let Some(transparent) = &container.transparent {
+        return bail!(
+            transparent,
+            "#[wormhole(transparent)] can only be used on structs"
+        );
+    }
+    let compact = container.compact.is_some();
+    let plain = container.plain.is_some();
 
     // Generate match arms for serialization
     let mut serialize_arms = Vec::new();
@@ -503,211 +714,216 @@ fn derive_enum(input: venial::Enum) -> Result<proc_macro2::TokenStream, venial::
     // Generate match arms for deserialization
     let mut deserialize_arms = Vec::new();
 
+    // all names which are accepted, to detect duplicates
+    let mut known_names = HashSet::new();
+    let mut other_variant = None;
+    let mut uses_ctx = false;
+
+    // compact enums send the index of the variant: its explicit discriminant, or the one after the previous variant
+    let mut next_index = 0u64;
+    let mut known_indices = HashSet::new();
+
     for (variant, _) in input.variants.iter() {
         let variant_name = &variant.name;
-        let variant_name_str = variant_name.to_string();
-
-        match &variant.fields {
-            // Unit variant (e.g., Case2)
-            venial::Fields::Unit => {
-                serialize_arms.push(quote! {
-                    #enum_name::#variant_name => (#variant_name_str.to_string(), vec![]),
-                });
+        let attributes = VariantAttributes::parse(&variant.attributes)?;
+        if compact && (attributes.rename.is_some() || !attributes.aliases.is_empty()) {
+            return bail!(
+                variant_name,
+                "The variants of a #[wormhole(compact)] enum are sent by their index, not by name, so they can't be renamed or aliased. New variants must be added at the end, or get explicit discriminants."
+            );
+        }
 
-                deserialize_arms.push(quote! {
-                    #variant_name_str => Self::#variant_name,
-                });
+        let index = match (&variant.value, compact) {
+            (Some(value), true) => discriminant_value(&value.value)?,
+            _ => next_index,
+        };
+        next_index = index.wrapping_add(1);
+        if compact && !known_indices.insert(index) {
+            return bail!(
+                variant_name,
+                "The index {index} is used by more than one variant"
+            );
+        }
+        let wire_name = attributes
+            .rename
+            .clone()
+            .unwrap_or_else(|| variant_name.to_string());
+
+        let accepted_names: Vec<_> = std::iter::once(wire_name.clone())
+            .chain(attributes.aliases.iter().cloned())
+            .collect();
+        for name in &accepted_names {
+            if !known_names.insert(name.clone()) {
+                return bail!(
+                    variant_name,
+                    "The variant name \"{name}\" is used more than once"
+                );
             }
+        }
 
-            // Tuple variant (e.g., Case1(u32, String))
-            venial::Fields::Tuple(fields) => {
-                let field_count = fields.fields.len();
-                let field_idents: Vec<_> = (0..field_count)
-                    .map(|i| format_ident!("field{}", i))
-                    .collect();
-                let field_types: Vec<_> = fields.fields.iter().map(|(f, _)| f.clone()).collect();
-
-                // Serialization for tuple variant fields
-                let mut serialize_fields = Vec::new();
-                for (i, field_type) in field_types.iter().enumerate() {
-                    let field_ident = &field_idents[i];
-                    let field_bytes_ident = format_ident!("field_bytes_{}", i);
-
-                    let serialize_field = quote! {
-                        let #field_bytes_ident = <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::immaterialize(
-                            #field_ident, ctx
-                        ).await?;
-                        buffer.extend_from_slice(&(#field_bytes_ident.len() as u64).to_le_bytes());
-                        buffer.extend_from_slice(&#field_bytes_ident);
-                    };
-
-                    serialize_fields.push(serialize_field);
-                }
+        if let Some(other) = &attributes.other {
+            if other_variant.is_some() {
+                return bail!(other, "Only one variant can be #[wormhole(other)]");
+            }
+            if !matches!(variant.fields, venial::Fields::Unit) {
+                return bail!(
+                    other,
+                    "The #[wormhole(other)] variant must be a unit variant"
+                );
+            }
+            other_variant = Some(variant_name.clone());
+        }
 
-                serialize_arms.push(quote! {
-                    #enum_name::#variant_name(#(#field_idents),*) => {
-                        let mut buffer = Vec::new();
-                        #(#serialize_fields)*
-                        (#variant_name_str.to_string(), buffer)
-                    },
-                });
+        // the fields are bound to `value_*` variables, so they can't shadow the variables of the generated code
+        let (fields, pattern, construct) = match &variant.fields {
+            // Unit variant (e.g., Case2)
+            venial::Fields::Unit => (Vec::new(), quote! {}, quote! { Self::#variant_name }),
 
-                // Deserialization for tuple variant fields
-                let mut deserialize_fields = Vec::new();
-                let mut field_value_idents = Vec::new();
-
-                for (i, field_type) in field_types.iter().enumerate() {
-                    let field_len_ident = format_ident!("field{}_len", i);
-                    let field_bytes_ident = format_ident!("field{}_bytes", i);
-                    let field_value_ident = format_ident!("field{}_value", i);
-                    field_value_idents.push(field_value_ident.clone());
-
-                    deserialize_fields.push(quote! {
-                        let #field_len_ident = u64::from_le_bytes(
-                            payload_data[payload_offset..payload_offset + 8].try_into()?
-                        ) as usize;
-                        payload_offset += 8;
-                        let #field_bytes_ident = &payload_data[payload_offset..payload_offset + #field_len_ident];
-                        payload_offset += #field_len_ident;
-                        let #field_value_ident =
-                            <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::rematerialize(
-                                ctx, #field_bytes_ident
-                            ).await?;
-                    });
-                }
+            // Tuple variant (e.g., Case1(u32, String))
+            venial::Fields::Tuple(tuple_fields) => {
+                let fields = tuple_fields
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (field, _))| {
+                        let value = format_ident!("value_{i}");
+                        FieldInfo::tuple(field, i, quote! { #value })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
 
-                deserialize_arms.push(quote! {
-                    #variant_name_str => {
-                        let mut payload_offset = 0;
-                        #(#deserialize_fields)*
-                        Self::#variant_name(#(#field_value_idents),*)
-                    },
+                let bindings = fields.iter().map(|field| match field.attributes.skip {
+                    true => quote! { _ },
+                    false => field.source.clone(),
                 });
+                let targets = fields.iter().map(|field| &field.target);
+                let pattern = quote! { (#(#bindings),*) };
+                let construct = quote! { Self::#variant_name(#(#targets),*) };
+                (fields, pattern, construct)
             }
 
             // Struct variant (e.g., Case3 { field1: u32, field2: String })
             venial::Fields::Named(named_fields) => {
-                let field_defs = named_fields
+                let fields = named_fields
                     .fields
                     .iter()
-                    .map(|(field, _)| for_field(field))
+                    .map(|(field, _)| {
+                        let value = format_ident!("value_{}", field.name);
+                        FieldInfo::named(field, quote! { #value })
+                    })
                     .collect::<Result<Vec<_>, _>>()?;
 
                 let field_names: Vec<_> = named_fields
                     .fields
                     .iter()
-                    .map(|(field, _)| field.name.clone())
+                    .map(|(field, _)| &field.name)
                     .collect();
-
-                // Generate field serialization
-                let (serialize_field_codes, _): (Vec<_>, Vec<_>) = field_defs.into_iter().unzip();
-
-                serialize_arms.push(quote! {
-                    #enum_name::#variant_name { #(#field_names),* } => {
-                        let mut buffer = Vec::new();
-                        #(#serialize_field_codes)*
-                        (#variant_name_str.to_string(), buffer)
-                    },
-                });
-
-                // Generate field deserialization
-                let mut deserialize_fields = Vec::new();
-                let mut field_value_pairs = Vec::new();
-
-                for field in named_fields.fields.iter() {
-                    let field_name = field.0.name.clone();
-                    let field_type = field.0.ty.clone();
-                    let field_len_ident = format_ident!("field_len_{}", field_name);
-                    let field_bytes_ident = format_ident!("field_bytes_{}", field_name);
-                    let field_value_ident = format_ident!("field_{}", field_name);
-
-                    deserialize_fields.push(quote! {
-                        let #field_len_ident = u64::from_le_bytes(
-                            payload_data[payload_offset..payload_offset + 8].try_into()?
-                        ) as usize;
-                        payload_offset += 8;
-                        let #field_bytes_ident = &payload_data[payload_offset..payload_offset + #field_len_ident];
-                        payload_offset += #field_len_ident;
-                        let #field_value_ident =
-                            <#field_type as ::ractor_wormhole::transmaterialization::ContextTransmaterializable>::rematerialize(
-                                ctx, #field_bytes_ident
-                            ).await?;
-                    });
-
-                    field_value_pairs.push(quote! {
-                        #field_name: #field_value_ident
-                    });
-                }
-
-                deserialize_arms.push(quote! {
-                    #variant_name_str => {
-                        let mut payload_offset = 0;
-                        #(#deserialize_fields)*
-                        Self::#variant_name { #(#field_value_pairs),* }
-                    },
+                let bindings = fields.iter().map(|field| match field.attributes.skip {
+                    true => quote! { _ },
+                    false => field.source.clone(),
                 });
+                let targets = fields.iter().map(|field| &field.target);
+                let pattern = quote! { { #(#field_names: #bindings),* } };
+                let construct = quote! { Self::#variant_name { #(#field_names: #targets),* } };
+                (fields, pattern, construct)
             }
-        }
-    }
+        };
 
-    // Complete implementation
-    let q = quote! {
-        #[::ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
-        impl ::ractor_wormhole::transmaterialization::ContextTransmaterializable for #enum_name {
-            async fn immaterialize(
-                self,
-                ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
-            ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Vec<u8>> {
-                let mut buffer = Vec::new();
+        uses_ctx |= fields.iter().any(FieldInfo::uses_ctx);
+        // the variant is either sent as `name_len:u64 + name + payload_len:u64 + payload`,
+        // or (compact) as `index:varint + payload`, where the payload is the rest of the data
+        let write = write_fields(&fields, compact, plain);
+        let write_variant = match compact {
+            true => quote! {
+                ::ractor_wormhole::transmaterialization::wire::write_varint(buffer, #index);
+                #write
+            },
+            false => quote! {
+                ::ractor_wormhole::transmaterialization::wire::write_field(buffer, #wire_name.as_bytes());
+                let payload = ::ractor_wormhole::transmaterialization::wire::FieldFrame::begin(buffer);
+                #write
+                payload.end(buffer);
+            },
+        };
+        serialize_arms.push(quote! {
+            #enum_name::#variant_name #pattern => {
+                #write_variant
+            },
+        });
 
-                let (case, bytes): (String, Vec<u8>) = match self {
-                    #(#serialize_arms)*
-                };
-                buffer.extend_from_slice(&(case.len() as u64).to_le_bytes());
-                buffer.extend_from_slice(case.as_bytes());
-                buffer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
-                buffer.extend_from_slice(&bytes);
+        // unit variants ignore their payload, so fields can be added to them later
+        let read = match fields.is_empty() {
+            true => quote! {},
+            false => read_fields(&fields, &format_ident!("payload_data"), compact, plain)?,
+        };
+        let accepted = match compact {
+            true => quote! { #index },
+            false => quote! { #(#accepted_names)|* },
+        };
+        deserialize_arms.push(quote! {
+            #accepted => {
+                #read
+                Ok(#construct)
+            },
+        });
+    }
 
-                Ok(buffer)
-            }
+    let unknown_arm = match &other_variant {
+        Some(other) => quote! {
+            _ => Ok(Self::#other),
+        },
+        None => quote! {
+            _ => Err(::ractor_wormhole::transmaterialization::transmaterialization_proxies::anyhow!("Unknown variant: {}", variant)),
+        },
+    };
 
-            async fn rematerialize(
-                ctx: &::ractor_wormhole::transmaterialization::TransmaterializationContext,
-                data: &[u8],
-            ) -> ::ractor_wormhole::transmaterialization::TransmaterializationResult<Self> {
-                let mut offset = 0;
-
-                // Read the variant name
-                let variant_name_len = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-                offset += 8;
-                let variant_name_bytes = &data[offset..offset + variant_name_len];
-                offset += variant_name_len;
-                let variant_name = std::str::from_utf8(variant_name_bytes)?.to_string();
-
-                // Read the payload data length
-                let payload_data_len = u64::from_le_bytes(data[offset..offset + 8].try_into()?) as usize;
-                offset += 8;
-                let payload_data = &data[offset..offset + payload_data_len];
-                offset += payload_data_len;
-
-                // Construct the enum variant based on the name
-                let result = match variant_name.as_str() {
-                    #(#deserialize_arms)*
-                    _ => {
-                        return Err(::ractor_wormhole::transmaterialization::transmaterialization_proxies::anyhow!("Unknown variant: {}", variant_name));
-                    }
-                };
+    let read_variant = match compact {
+        true => quote! {
+            let variant = cursor.read_varint()?;
+            let payload_data = cursor.read_rest();
+        },
+        false => quote! {
+            let variant = std::str::from_utf8(cursor.read_field()?)?;
+            let payload_data = cursor.read_field()?;
+        },
+    };
 
-                if data.len() != offset {
-                    return Err(::ractor_wormhole::transmaterialization::transmaterialization_proxies::anyhow!("Rematerialization did not consume all data! Buffer length: {}, consumed: {}", data.len(), offset));
-                }
+    let ctx = ctx_param(uses_ctx);
+    let target = ImplTarget::new(&enum_name, &input.generic_params, |bound| {
+        input.create_derive_where_clause(bound)
+    });
 
-                Ok(result)
+    // an empty enum has no values, so the match doesn't return
+    let immaterialize_into = match serialize_arms.is_empty() {
+        true => quote! {
+            match self {}
+        },
+        false => quote! {
+            match self {
+                #(#serialize_arms)*
             }
-        }
+
+            Ok(())
+        },
     };
 
-    Ok(q)
+    Ok(implementations(
+        &container,
+        &target,
+        ctx,
+        immaterialize_into,
+        quote! {
+            // the variant and the payload
+            let mut cursor = ::ractor_wormhole::transmaterialization::wire::WireCursor::new(data);
+            #read_variant
+            cursor.finish()?;
+
+            // Construct the enum variant based on the name (or index)
+            match variant {
+                #(#deserialize_arms)*
+                #unknown_arm
+            }
+        },
+    ))
 }
 
 pub fn derive_wormhole_serializable_impl(
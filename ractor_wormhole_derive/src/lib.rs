@@ -1,3 +1,4 @@
+mod attributes;
 mod derive_wormhole_serializable;
 mod util;
 
@@ -6,7 +7,7 @@ use proc_macro2::TokenStream as TokenStream2;
 
 use derive_wormhole_serializable::derive_wormhole_serializable_impl;
 
-#[proc_macro_derive(WormholeTransmaterializable, attributes(serde, bincode))]
+#[proc_macro_derive(WormholeTransmaterializable, attributes(serde, bincode, wormhole))]
 pub fn derive_wormhole_transmaterializable(input: TokenStream) -> TokenStream {
     let result = derive_wormhole_serializable_impl(TokenStream2::from(input));
     match result {
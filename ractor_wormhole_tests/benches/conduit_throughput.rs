@@ -0,0 +1,116 @@
+//! Throughput of the raw conduits (without the portal on top): frames are written into the sink of one side
+//! and read from the source of the other side.
+//!
+//! `cargo bench -p ractor_wormhole_tests --bench conduit_throughput`
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use ractor_wormhole::conduit::websocket::mapping::split_websocket;
+use ractor_wormhole::conduit::{ConduitMessage, ConduitSink, ConduitSource, shared_memory};
+use tokio::net::TcpListener;
+
+/// the bytes sent per iteration
+const BYTES_PER_ITERATION: usize = 4 * 1024 * 1024;
+const FRAME_SIZES: [usize; 2] = [1024, 64 * 1024];
+
+async fn transfer(
+    (sink, source): &mut (ConduitSink, ConduitSource),
+    frame_size: usize,
+) -> anyhow::Result<()> {
+    let frame = vec![0x5a; frame_size];
+    let count = BYTES_PER_ITERATION / frame_size;
+
+    let send = async {
+        for _ in 0..count {
+            sink.feed(ConduitMessage::Binary(frame.clone())).await?;
+        }
+        sink.flush().await
+    };
+    let receive = async {
+        for _ in 0..count {
+            match source.next().await {
+                Some(Ok(ConduitMessage::Binary(data))) => assert_eq!(data.len(), frame_size),
+                _ => anyhow::bail!("the conduit ended early"),
+            }
+        }
+        Ok(())
+    };
+    futures::try_join!(send, receive)?;
+    Ok(())
+}
+
+/// the writing half of one side and the reading half of the other side
+fn in_memory() -> (ConduitSink, ConduitSource) {
+    let (tx, rx) = mpsc::channel::<ConduitMessage>(64);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let source: ConduitSource = Box::pin(rx.map(Ok));
+    (sink, source)
+}
+
+async fn websocket() -> anyhow::Result<(ConduitSink, ConduitSource)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let websocket = tokio_tungstenite::accept_async(stream).await?;
+        anyhow::Ok(split_websocket(websocket))
+    });
+    let (client, _) = tokio_tungstenite::connect_async(format!("ws://{addr}")).await?;
+    let (client_sink, _client_source) = split_websocket(client);
+    let (_server_sink, server_source) = server.await??;
+    Ok((client_sink, server_source))
+}
+
+fn shared_memory() -> anyhow::Result<(ConduitSink, ConduitSource)> {
+    let path = std::env::temp_dir().join(format!("wormhole-bench-{}", std::process::id()));
+    let (sink, _) = shared_memory::create_conduit(&path, shared_memory::DEFAULT_CAPACITY)?;
+    let (_, source) = shared_memory::connect_conduit(&path)?;
+    std::fs::remove_file(&path)?;
+    Ok((sink, source))
+}
+
+fn conduit_throughput(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("conduit_throughput");
+    group.throughput(Throughput::Bytes(BYTES_PER_ITERATION as u64));
+    group.sample_size(20);
+
+    for frame_size in FRAME_SIZES {
+        let mut conduit = runtime.block_on(async { in_memory() });
+        group.bench_function(BenchmarkId::new("in_memory", frame_size), |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(transfer(&mut conduit, frame_size))
+                    .unwrap()
+            })
+        });
+
+        let mut conduit = runtime.block_on(websocket()).unwrap();
+        group.bench_function(BenchmarkId::new("websocket", frame_size), |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(transfer(&mut conduit, frame_size))
+                    .unwrap()
+            })
+        });
+
+        let mut conduit = runtime.block_on(async { shared_memory() }).unwrap();
+        group.bench_function(BenchmarkId::new("shared_memory", frame_size), |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(transfer(&mut conduit, frame_size))
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, conduit_throughput);
+criterion_main!(benches);
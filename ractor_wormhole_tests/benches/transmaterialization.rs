@@ -0,0 +1,173 @@
+//! (Im)materialization of nested messages, without sending them.
+//!
+//! The `copying` variants wrap every level in `Copying<T>`, which only implements `immaterialize` (like implementations
+//! which don't override `immaterialize_into`), so every level is allocated and copied into its parent.
+//! The `plain` variants are `#[wormhole(plain)]`, so they are (im)materialized without any futures.
+//!
+//! `cargo bench -p ractor_wormhole_tests --bench transmaterialization`
+
+use std::time::Duration;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use ractor_wormhole::WormholeTransmaterializable;
+use ractor_wormhole::conduit::{ConduitMessage, ConduitSink, from_sink_source};
+use ractor_wormhole::nexus::start_nexus;
+use ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait;
+use ractor_wormhole::transmaterialization::{
+    ContextTransmaterializable, TransmaterializationContext, TransmaterializationResult,
+};
+
+/// a value which is immaterialized into its own buffer, and then copied into the buffer of its parent
+#[derive(Clone, Debug)]
+pub struct Copying<T>(T);
+
+#[async_trait]
+impl<T: ContextTransmaterializable + Send + Sync + 'static> ContextTransmaterializable
+    for Copying<T>
+{
+    async fn immaterialize(
+        self,
+        ctx: &TransmaterializationContext,
+    ) -> TransmaterializationResult<Vec<u8>> {
+        self.0.immaterialize(ctx).await
+    }
+
+    async fn rematerialize(
+        ctx: &TransmaterializationContext,
+        data: &[u8],
+    ) -> TransmaterializationResult<Self> {
+        Ok(Copying(T::rematerialize(ctx, data).await?))
+    }
+}
+
+#[derive(Clone, Debug, WormholeTransmaterializable)]
+pub struct Node {
+    pub id: u64,
+    pub label: String,
+    pub children: Vec<Node>,
+}
+
+#[derive(Clone, Debug, WormholeTransmaterializable)]
+#[wormhole(plain)]
+pub struct PlainNode {
+    pub id: u64,
+    pub label: String,
+    pub children: Vec<PlainNode>,
+}
+
+#[derive(Clone, Debug, WormholeTransmaterializable)]
+pub struct CopyingNode {
+    pub id: u64,
+    pub label: String,
+    pub children: Vec<Copying<CopyingNode>>,
+}
+
+fn tree(depth: u32) -> Node {
+    Node {
+        id: depth as u64,
+        label: format!("node {depth}"),
+        children: match depth {
+            0 => vec![],
+            _ => (0..4).map(|_| tree(depth - 1)).collect(),
+        },
+    }
+}
+
+fn plain_tree(depth: u32) -> PlainNode {
+    PlainNode {
+        id: depth as u64,
+        label: format!("node {depth}"),
+        children: match depth {
+            0 => vec![],
+            _ => (0..4).map(|_| plain_tree(depth - 1)).collect(),
+        },
+    }
+}
+
+fn copying_tree(depth: u32) -> CopyingNode {
+    CopyingNode {
+        id: depth as u64,
+        label: format!("node {depth}"),
+        children: match depth {
+            0 => vec![],
+            _ => (0..4).map(|_| Copying(copying_tree(depth - 1))).collect(),
+        },
+    }
+}
+
+fn matrix(size: u32) -> Vec<Vec<u32>> {
+    (0..size)
+        .map(|row| (0..size).map(|col| row * col).collect())
+        .collect()
+}
+
+/// a context on a portal which isn't connected to anything
+async fn context() -> anyhow::Result<TransmaterializationContext> {
+    let nexus = start_nexus(Some("transmaterialization bench".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let (tx, _rx) = mpsc::channel::<ConduitMessage>(100);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let portal = from_sink_source(
+        nexus,
+        "bench".to_string(),
+        sink,
+        Box::pin(futures::stream::pending().map(Ok)),
+    )
+    .await?;
+    Ok(TransmaterializationContext {
+        connection: portal,
+        default_rpc_port_timeout: Duration::from_secs(5),
+    })
+}
+
+fn bench_type<T>(
+    c: &mut Criterion,
+    runtime: &tokio::runtime::Runtime,
+    ctx: &TransmaterializationContext,
+    group: &str,
+    name: &str,
+    value: T,
+) where
+    T: ContextTransmaterializable + Clone + Send + Sync + 'static,
+{
+    let mut group = c.benchmark_group(group);
+    let data = runtime.block_on(value.clone().immaterialize(ctx)).unwrap();
+
+    group.bench_function(BenchmarkId::new("immaterialize", name), |b| {
+        b.iter(|| runtime.block_on(value.clone().immaterialize(ctx)).unwrap())
+    });
+    group.bench_function(BenchmarkId::new("rematerialize", name), |b| {
+        b.iter(|| runtime.block_on(T::rematerialize(ctx, &data)).unwrap())
+    });
+    group.finish();
+}
+
+fn transmaterialization(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let ctx = runtime.block_on(context()).unwrap();
+
+    // 4^6 leaves
+    bench_type(c, &runtime, &ctx, "nested_struct", "into", tree(6));
+    bench_type(c, &runtime, &ctx, "nested_struct", "plain", plain_tree(6));
+    bench_type(
+        c,
+        &runtime,
+        &ctx,
+        "nested_struct",
+        "copying",
+        copying_tree(6),
+    );
+
+    bench_type(c, &runtime, &ctx, "vec_vec_u32", "into", matrix(256));
+    let copying: Vec<Copying<Vec<u32>>> = matrix(256).into_iter().map(Copying).collect();
+    bench_type(c, &runtime, &ctx, "vec_vec_u32", "copying", copying);
+}
+
+criterion_group!(benches, transmaterialization);
+criterion_main!(benches);
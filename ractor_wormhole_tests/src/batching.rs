@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use ractor_wormhole::conduit::priority::{BatchConfig, LaneWeights, Priority, PriorityOutbox};
+use ractor_wormhole::conduit::{ConduitMessage, from_sink_source};
+use ractor_wormhole::nexus::{start_nexus, start_nexus_with_config};
+use ractor_wormhole::portal::{CrossPortalMessage, Portal, PortalActorMessage, PortalConfig};
+use ractor_wormhole::transmaterialization::internal_serializations::SimpleByteTransmaterializable;
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::channel;
+
+#[tokio::test]
+pub async fn test_small_frames_are_batched() -> anyhow::Result<()> {
+    let nexus = start_nexus(Some("batching: outbox".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let (portal_sink, _portal_source) = channel();
+    let portal = from_sink_source(
+        nexus,
+        "batching: dummy".to_string(),
+        portal_sink,
+        Box::pin(futures::stream::pending()),
+    )
+    .await?;
+
+    let (sink, mut wire) = channel();
+    let outbox = PriorityOutbox::spawn(
+        sink,
+        LaneWeights::default(),
+        64,
+        Some(BatchConfig {
+            max_bytes: 1024,
+            max_delay: Duration::from_millis(50),
+        }),
+        portal,
+        "outbox".to_string(),
+    );
+
+    for i in 0..4u8 {
+        outbox
+            .send(Priority::Normal, ConduitMessage::Binary(vec![i; 10]))
+            .await?;
+    }
+    outbox
+        .send(Priority::Normal, ConduitMessage::Bulk(vec![4; 10]))
+        .await?;
+    outbox
+        .send(Priority::Normal, ConduitMessage::Binary(vec![0xff; 2000]))
+        .await?;
+    outbox
+        .send(Priority::Control, ConduitMessage::Close(None))
+        .await?;
+
+    let mut received = Vec::new();
+    while let Some(msg) = tokio::time::timeout(Duration::from_secs(5), wire.next()).await? {
+        received.push(msg.map_err(|err| anyhow::anyhow!(err))?);
+    }
+    assert_eq!(received.len(), 3);
+
+    // the small frames are coalesced, in order. The batch contains a bulk frame, so it is a bulk frame too
+    let ConduitMessage::Bulk(batch) = &received[0] else {
+        panic!("expected a bulk frame");
+    };
+    let CrossPortalMessage::Batch(frames) = CrossPortalMessage::rematerialize(batch)? else {
+        panic!("expected a batch");
+    };
+    let expected: Vec<Box<[u8]>> = (0..5u8).map(|i| vec![i; 10].into()).collect();
+    assert_eq!(frames, expected);
+
+    // the large frame doesn't fit into the batch and is sent as is
+    assert!(matches!(&received[1], ConduitMessage::Binary(data) if data.len() == 2000));
+    assert!(matches!(&received[2], ConduitMessage::Close(None)));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_batched_portals() -> anyhow::Result<()> {
+    let config = PortalConfig {
+        batching: Some(BatchConfig::default()),
+        ..Default::default()
+    };
+    let nexus_a = start_nexus_with_config(Some("batching: a".to_string()), None, config.clone())
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let nexus_b = start_nexus_with_config(Some("batching: b".to_string()), None, config)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+
+    let (sink_a, source_b) = channel();
+    let (sink_b, source_a) = channel();
+    let portal_a = from_sink_source(nexus_a, "a".to_string(), sink_a, source_a).await?;
+    let portal_b = from_sink_source(nexus_b, "b".to_string(), sink_b, source_b).await?;
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg.msg);
+        }
+    })
+    .await?;
+    portal_b
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+
+    let hello_actor_id = portal_a
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let proxy = portal_a
+        .instantiate_proxy_for_remote_actor::<HelloMsg>(hello_actor_id)
+        .await?;
+
+    for i in 0..100 {
+        proxy.send_message(HelloMsg {
+            msg: format!("hello {i}"),
+        })?;
+    }
+
+    for i in 0..100 {
+        let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?;
+        assert_eq!(received, Some(format!("hello {i}")));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_binary_introduction_is_not_batched() -> anyhow::Result<()> {
+    // a holds its introduction for a long time, b's introduction arrives meanwhile
+    let config_a = PortalConfig {
+        binary_introduction: true,
+        batching: Some(BatchConfig {
+            max_bytes: 64 * 1024,
+            max_delay: Duration::from_millis(500),
+        }),
+        ..Default::default()
+    };
+    let config_b = PortalConfig {
+        binary_introduction: true,
+        ..Default::default()
+    };
+    let nexus_a = start_nexus_with_config(Some("batching: intro a".to_string()), None, config_a)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let nexus_b = start_nexus_with_config(Some("batching: intro b".to_string()), None, config_b)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+
+    let (sink_a, source_b) = channel();
+    let (sink_b, source_a) = channel();
+    let portal_a = from_sink_source(nexus_a, "a".to_string(), sink_a, source_a).await?;
+    let portal_b = from_sink_source(nexus_b, "b".to_string(), sink_b, source_b).await?;
+
+    let (hello_actor, _) =
+        FnActor::<HelloMsg>::start_fn(async move |mut ctx| while ctx.rx.recv().await.is_some() {})
+            .await?;
+    portal_b
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+
+    // a is open as soon as b's introduction arrives, so the lookup is queued while a's introduction waits for a batch
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_a
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    Ok(())
+}
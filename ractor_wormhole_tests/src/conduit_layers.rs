@@ -0,0 +1,151 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use ractor_wormhole::conduit::layer::{Conduit, ConduitLayer, ConduitLayers, MetricsLayer};
+use ractor_wormhole::conduit::websocket::{
+    client::tokio_tungstenite::{ClientConfig, connect_to_server_with_config},
+    server::tokio_tungstenite::{ServerConfig, start_server_with_config},
+};
+use ractor_wormhole::conduit::{
+    ConduitError, ConduitMessage, ConduitSink, ConduitSource, ConnectionContext,
+    from_sink_source_with_layers,
+};
+use ractor_wormhole::portal::Portal;
+
+use crate::util::nexus;
+
+/// prefixes every outgoing text message with the tag
+struct TagLayer(&'static str);
+
+impl ConduitLayer for TagLayer {
+    fn wrap(&self, conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>> {
+        let tag = self.0;
+        Box::pin(async move {
+            let sink = conduit.sink.with(move |msg| {
+                let msg = match msg {
+                    ConduitMessage::Text(text) => ConduitMessage::Text(format!("{tag}{text}")),
+                    other => other,
+                };
+                futures::future::ready(Ok::<_, ConduitError>(msg))
+            });
+            Ok(Conduit {
+                sink: Box::pin(sink),
+                ..conduit
+            })
+        })
+    }
+}
+
+/// a layer whose handshake always fails
+struct RejectingLayer;
+
+impl ConduitLayer for RejectingLayer {
+    fn wrap(&self, _conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>> {
+        Box::pin(async { Err(anyhow::anyhow!("rejected")) })
+    }
+}
+
+fn channel_conduit() -> (ConduitSink, ConduitSource, mpsc::Receiver<ConduitMessage>) {
+    let (tx, rx) = mpsc::channel::<ConduitMessage>(100);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    // nothing is ever received
+    let source: ConduitSource = Box::pin(futures::stream::pending());
+    (sink, source, rx)
+}
+
+#[tokio::test]
+pub async fn test_layer_order() -> anyhow::Result<()> {
+    let (sink, source, mut wire) = channel_conduit();
+
+    // the first layer wraps the transport, so its tag ends up in front
+    let layers = ConduitLayers::new()
+        .with(TagLayer("outer:"))
+        .with(TagLayer("inner:"));
+    let _portal = from_sink_source_with_layers(
+        nexus("layers: order").await?,
+        "layers".to_string(),
+        ConnectionContext::default(),
+        sink,
+        source,
+        &layers,
+    )
+    .await?;
+
+    // the introduction
+    let msg = tokio::time::timeout(Duration::from_secs(5), wire.next()).await?;
+    match msg {
+        Some(ConduitMessage::Text(text)) => assert!(text.starts_with("outer:inner:{")),
+        _ => panic!("expected the introduction"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_failing_layer() -> anyhow::Result<()> {
+    let (sink, source, _wire) = channel_conduit();
+
+    let layers = ConduitLayers::new().with(RejectingLayer);
+    let result = from_sink_source_with_layers(
+        nexus("layers: failing").await?,
+        "layers".to_string(),
+        ConnectionContext::default(),
+        sink,
+        source,
+        &layers,
+    )
+    .await;
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_metrics_layer_over_websocket() -> anyhow::Result<()> {
+    let server_metrics = MetricsLayer::new();
+    let config = ServerConfig {
+        layers: ConduitLayers::new().with(server_metrics.clone()),
+        ..Default::default()
+    };
+    let server = start_server_with_config(
+        nexus("layers: server").await?,
+        "127.0.0.1:0".parse()?,
+        config,
+    )
+    .await?;
+
+    let client_metrics = MetricsLayer::new();
+    let config = ClientConfig {
+        layers: ConduitLayers::new().with(client_metrics.clone()),
+        ..Default::default()
+    };
+    let portal = connect_to_server_with_config(
+        nexus("layers: client").await?,
+        format!("ws://{}", server.local_addr()),
+        config,
+    )
+    .await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+
+    // both introductions have passed both layers
+    let client = client_metrics.metrics();
+    let server = server_metrics.metrics();
+    for _ in 0..100 {
+        if server.messages_received.load(Ordering::Relaxed) >= 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(client.messages_sent.load(Ordering::Relaxed) >= 1);
+    assert!(client.messages_received.load(Ordering::Relaxed) >= 1);
+    assert!(server.messages_received.load(Ordering::Relaxed) >= 1);
+    assert_eq!(
+        client.bytes_received.load(Ordering::Relaxed),
+        server.bytes_sent.load(Ordering::Relaxed)
+    );
+
+    Ok(())
+}
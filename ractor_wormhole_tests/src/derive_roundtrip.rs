@@ -0,0 +1,980 @@
+//! Round-trips of derived messages through a real portal.
+
+use std::time::Duration;
+
+use ractor_wormhole::WormholeTransmaterializable;
+use ractor_wormhole::conduit::from_sink_source;
+use ractor_wormhole::nexus::start_nexus;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::transmaterialization::{
+    ContextTransmaterializable, TransmaterializationContext,
+};
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::util::channel;
+
+/// sends the messages from one portal to an actor behind another portal, and returns what the actor received.
+pub async fn roundtrip<T>(name: &str, messages: Vec<T>) -> anyhow::Result<Vec<T>>
+where
+    T: ContextTransmaterializable + ractor::Message + Sync + std::fmt::Debug,
+{
+    send_across(name, messages).await
+}
+
+/// a context on a portal which isn't connected to anything, to (im)materialize messages directly.
+pub async fn test_context(name: &str) -> anyhow::Result<TransmaterializationContext> {
+    let nexus = start_nexus(Some(name.to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let (sink, _source) = channel();
+    let portal = from_sink_source(
+        nexus,
+        name.to_string(),
+        sink,
+        Box::pin(futures::stream::pending()),
+    )
+    .await?;
+    Ok(TransmaterializationContext {
+        connection: portal,
+        default_rpc_port_timeout: Duration::from_secs(5),
+    })
+}
+
+/// like `roundtrip`, but the actor receives a different version of the type.
+pub async fn send_across<From, To>(name: &str, messages: Vec<From>) -> anyhow::Result<Vec<To>>
+where
+    From: ContextTransmaterializable + ractor::Message + Sync + std::fmt::Debug,
+    To: ContextTransmaterializable + ractor::Message + Sync + std::fmt::Debug,
+{
+    let (sink_a, source_b) = channel();
+    let (sink_b, source_a) = channel();
+
+    let nexus_a = start_nexus(Some(format!("{name}: a")), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let nexus_b = start_nexus(Some(format!("{name}: b")), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let portal_a = from_sink_source(nexus_a, "a".to_string(), sink_a, source_a).await?;
+    let portal_b = from_sink_source(nexus_b, "b".to_string(), sink_b, source_b).await?;
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (receiver, _) = FnActor::<To>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg);
+        }
+    })
+    .await?;
+    portal_b
+        .publish_named_actor("receiver".to_string(), receiver)
+        .await?;
+
+    let receiver_id = portal_a
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("receiver".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let proxy = portal_a
+        .instantiate_proxy_for_remote_actor::<From>(receiver_id)
+        .await?;
+
+    let count = messages.len();
+    for msg in messages {
+        proxy.send_message(msg)?;
+    }
+
+    let mut received = Vec::with_capacity(count);
+    for _ in 0..count {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await? {
+            Some(msg) => received.push(msg),
+            None => return Err(anyhow::anyhow!("The receiver stopped")),
+        }
+    }
+    Ok(received)
+}
+
+// #[serde] fields
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Settings {
+    pub theme: String,
+    pub volume: Option<f32>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct SerdeStruct {
+    pub id: u32,
+    #[serde]
+    pub settings: Settings,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct SerdeTupleStruct(#[serde] Settings, String);
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum SerdeEnum {
+    Reset,
+    Apply(u32, #[serde] Settings),
+    Rename {
+        id: u32,
+        #[serde]
+        settings: Settings,
+    },
+}
+
+fn settings(theme: &str) -> Settings {
+    Settings {
+        theme: theme.to_string(),
+        volume: Some(0.5),
+        tags: vec!["a".to_string(), "b".to_string()],
+    }
+}
+
+#[tokio::test]
+pub async fn test_serde_fields() -> anyhow::Result<()> {
+    let msg = SerdeStruct {
+        id: 7,
+        settings: settings("dark"),
+    };
+    assert_eq!(
+        roundtrip("serde: struct", vec![msg.clone()]).await?,
+        vec![msg]
+    );
+
+    let msg = SerdeTupleStruct(settings("light"), "tuple".to_string());
+    assert_eq!(
+        roundtrip("serde: tuple", vec![msg.clone()]).await?,
+        vec![msg]
+    );
+
+    let messages = vec![
+        SerdeEnum::Reset,
+        SerdeEnum::Apply(1, settings("dark")),
+        SerdeEnum::Rename {
+            id: 2,
+            settings: settings("light"),
+        },
+    ];
+    assert_eq!(roundtrip("serde: enum", messages.clone()).await?, messages);
+
+    Ok(())
+}
+
+// #[bincode] fields
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, bincode::Encode, bincode::Decode)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct BincodeStruct {
+    #[bincode]
+    pub position: Position,
+    #[serde]
+    pub settings: Settings,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct BincodeTupleStruct(u8, #[bincode] Position);
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum BincodeEnum {
+    Stop,
+    Move(#[bincode] Position, #[bincode] Position),
+    Teleport {
+        #[bincode]
+        to: Position,
+    },
+}
+
+fn position(x: i32, y: i32) -> Position {
+    Position {
+        x,
+        y,
+        label: Some(format!("{x}/{y}")),
+    }
+}
+
+#[tokio::test]
+pub async fn test_bincode_fields() -> anyhow::Result<()> {
+    let msg = BincodeStruct {
+        position: position(1, -2),
+        settings: settings("dark"),
+    };
+    assert_eq!(
+        roundtrip("bincode: struct", vec![msg.clone()]).await?,
+        vec![msg]
+    );
+
+    let msg = BincodeTupleStruct(3, position(-4, 5));
+    assert_eq!(
+        roundtrip("bincode: tuple", vec![msg.clone()]).await?,
+        vec![msg]
+    );
+
+    let messages = vec![
+        BincodeEnum::Stop,
+        BincodeEnum::Move(position(0, 0), position(6, 7)),
+        BincodeEnum::Teleport { to: position(8, 9) },
+    ];
+    assert_eq!(
+        roundtrip("bincode: enum", messages.clone()).await?,
+        messages
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_bincode_trailing_bytes_are_an_error() -> anyhow::Result<()> {
+    use ractor_wormhole::transmaterialization::transmaterialization_proxies::bincode_proxy;
+
+    let mut data = bincode_proxy::immaterialize(position(1, 2))?;
+    assert_eq!(
+        bincode_proxy::rematerialize::<Position>(&data)?,
+        position(1, 2)
+    );
+
+    // a malformed payload from the remote side must not panic
+    data.push(0);
+    assert!(bincode_proxy::rematerialize::<Position>(&data).is_err());
+    Ok(())
+}
+
+/// the proxies only require the direction they implement, e.g. for types which are only ever sent
+#[test]
+pub fn test_proxies_only_need_one_direction() -> anyhow::Result<()> {
+    use ractor_wormhole::transmaterialization::transmaterialization_proxies::{
+        bincode_proxy, serde_proxy,
+    };
+
+    #[derive(serde::Serialize, bincode::Encode)]
+    struct Outgoing {
+        value: u32,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize, bincode::Decode)]
+    struct Incoming {
+        value: u32,
+    }
+
+    let data = serde_proxy::immaterialize(Outgoing { value: 7 })?;
+    assert_eq!(
+        serde_proxy::rematerialize::<Incoming>(&data)?,
+        Incoming { value: 7 }
+    );
+
+    let data = bincode_proxy::immaterialize(Outgoing { value: 8 })?;
+    assert_eq!(
+        bincode_proxy::rematerialize::<Incoming>(&data)?,
+        Incoming { value: 8 }
+    );
+    Ok(())
+}
+
+// #[wormhole(with = adapter)] fields
+// -----------------------------------------------------------------------------
+
+pub mod adapters {
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    use ractor_wormhole::transmaterialization::{SerializationAdapter, TransmaterializationResult};
+
+    /// any type with a string representation, e.g. the (foreign) `std::net::SocketAddr`
+    pub struct ViaString;
+
+    impl<T: Display + FromStr<Err: Display>> SerializationAdapter<T> for ViaString {
+        fn immaterialize(value: T) -> TransmaterializationResult<Vec<u8>> {
+            Ok(value.to_string().into_bytes())
+        }
+
+        fn rematerialize(data: &[u8]) -> TransmaterializationResult<T> {
+            std::str::from_utf8(data)?
+                .parse()
+                .map_err(|err| anyhow::anyhow!("Invalid value: {err}"))
+        }
+    }
+
+    /// a hand-written codec for a single type
+    pub struct RgbAdapter;
+
+    impl SerializationAdapter<super::Rgb> for RgbAdapter {
+        fn immaterialize(value: super::Rgb) -> TransmaterializationResult<Vec<u8>> {
+            Ok(vec![value.r, value.g, value.b])
+        }
+
+        fn rematerialize(data: &[u8]) -> TransmaterializationResult<super::Rgb> {
+            match data {
+                [r, g, b] => Ok(super::Rgb {
+                    r: *r,
+                    g: *g,
+                    b: *b,
+                }),
+                _ => Err(anyhow::anyhow!("Expected 3 bytes, got {}", data.len())),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct AdapterStruct {
+    #[wormhole(with = adapters::ViaString)]
+    pub peer: std::net::SocketAddr,
+    #[wormhole(with = self::adapters::RgbAdapter)]
+    pub color: Rgb,
+    #[wormhole(with = ractor_wormhole::transmaterialization::adapters::SerdeAdapter)]
+    pub settings: Settings,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum AdapterEnum {
+    Paint(#[wormhole(with = adapters::RgbAdapter)] Rgb),
+    Resize {
+        #[wormhole(with = adapters::ViaString)]
+        width: u16,
+    },
+}
+
+#[tokio::test]
+pub async fn test_adapter_fields() -> anyhow::Result<()> {
+    let msg = AdapterStruct {
+        peer: "127.0.0.1:8080".parse()?,
+        color: Rgb { r: 1, g: 2, b: 3 },
+        settings: settings("dark"),
+    };
+    assert_eq!(
+        roundtrip("adapter: struct", vec![msg.clone()]).await?,
+        vec![msg]
+    );
+
+    let messages = vec![
+        AdapterEnum::Paint(Rgb {
+            r: 255,
+            g: 0,
+            b: 128,
+        }),
+        AdapterEnum::Resize { width: 640 },
+    ];
+    assert_eq!(
+        roundtrip("adapter: enum", messages.clone()).await?,
+        messages
+    );
+
+    Ok(())
+}
+
+// Serde<T> and Bincode<T> messages
+// -----------------------------------------------------------------------------
+
+#[tokio::test]
+pub async fn test_wrapped_messages() -> anyhow::Result<()> {
+    use ractor_wormhole::transmaterialization::{Bincode, Serde};
+
+    // serde and bincode types are sent as messages without a derive
+    let messages: Vec<Serde<Settings>> = vec![settings("dark").into(), settings("light").into()];
+    let received = roundtrip("wrapped: serde", messages.clone()).await?;
+    assert_eq!(received, messages);
+    assert_eq!(received[0].theme, "dark");
+
+    let messages = vec![Bincode(position(1, 2)), Bincode(position(-3, 4))];
+    let received = roundtrip("wrapped: bincode", messages.clone()).await?;
+    assert_eq!(received, messages);
+    assert_eq!(received[1].clone().into_inner(), position(-3, 4));
+
+    Ok(())
+}
+
+// schema evolution
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct ProfileV1 {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct ProfileV2 {
+    pub id: u32,
+    pub display_name: String,
+    #[wormhole(skip)]
+    pub cached_avatar: Option<Vec<u8>>,
+    #[wormhole(default)]
+    pub tags: Vec<String>,
+    #[wormhole(default)]
+    pub age: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum EventV1 {
+    Start,
+    Stop(u32),
+    Kick {
+        user: String,
+    },
+    #[wormhole(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum EventV2 {
+    #[wormhole(rename = "Start")]
+    Begin {
+        #[wormhole(default)]
+        delay_ms: u32,
+    },
+    Stop(u32, #[wormhole(default)] String),
+    #[wormhole(alias = "Kick")]
+    Ban {
+        user: String,
+        #[wormhole(default)]
+        days: Option<u32>,
+    },
+    Pause,
+    #[wormhole(other)]
+    Unknown,
+}
+
+#[tokio::test]
+pub async fn test_struct_evolution() -> anyhow::Result<()> {
+    // an old peer sends to a new peer: the new fields are the default
+    let received: Vec<ProfileV2> = send_across(
+        "evolution: struct v1 to v2",
+        vec![ProfileV1 {
+            id: 1,
+            name: "alice".to_string(),
+        }],
+    )
+    .await?;
+    assert_eq!(
+        received,
+        vec![ProfileV2 {
+            id: 1,
+            display_name: "alice".to_string(),
+            cached_avatar: None,
+            tags: vec![],
+            age: None,
+        }]
+    );
+
+    // a new peer sends to an old peer: the new fields are ignored
+    let received: Vec<ProfileV1> = send_across(
+        "evolution: struct v2 to v1",
+        vec![ProfileV2 {
+            id: 2,
+            display_name: "bob".to_string(),
+            cached_avatar: Some(vec![1, 2, 3]),
+            tags: vec!["admin".to_string()],
+            age: Some(42),
+        }],
+    )
+    .await?;
+    assert_eq!(
+        received,
+        vec![ProfileV1 {
+            id: 2,
+            name: "bob".to_string(),
+        }]
+    );
+
+    // skipped fields are never sent
+    let received = roundtrip(
+        "evolution: skip",
+        vec![ProfileV2 {
+            id: 3,
+            display_name: "carol".to_string(),
+            cached_avatar: Some(vec![4, 5, 6]),
+            tags: vec![],
+            age: Some(7),
+        }],
+    )
+    .await?;
+    assert_eq!(received[0].cached_avatar, None);
+    assert_eq!(received[0].age, Some(7));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_enum_evolution() -> anyhow::Result<()> {
+    let received: Vec<EventV2> = send_across(
+        "evolution: enum v1 to v2",
+        vec![
+            EventV1::Start,
+            EventV1::Stop(3),
+            EventV1::Kick {
+                user: "mallory".to_string(),
+            },
+            EventV1::Unknown,
+        ],
+    )
+    .await?;
+    assert_eq!(
+        received,
+        vec![
+            EventV2::Begin { delay_ms: 0 },
+            EventV2::Stop(3, String::new()),
+            EventV2::Ban {
+                user: "mallory".to_string(),
+                days: None,
+            },
+            EventV2::Unknown,
+        ]
+    );
+
+    let received: Vec<EventV1> = send_across(
+        "evolution: enum v2 to v1",
+        vec![
+            EventV2::Begin { delay_ms: 100 },
+            EventV2::Stop(4, "done".to_string()),
+            EventV2::Ban {
+                user: "mallory".to_string(),
+                days: Some(7),
+            },
+            EventV2::Pause,
+        ],
+    )
+    .await?;
+    assert_eq!(
+        received,
+        vec![
+            EventV1::Start,
+            EventV1::Stop(4),
+            // the new name isn't known to the old peer
+            EventV1::Unknown,
+            EventV1::Unknown,
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_malformed_data_is_an_error() -> anyhow::Result<()> {
+    let ctx = test_context("evolution: malformed").await?;
+
+    let data = EventV2::Ban {
+        user: "mallory".to_string(),
+        days: Some(7),
+    }
+    .immaterialize(&ctx)
+    .await?;
+
+    // every truncation fails cleanly instead of panicking
+    for len in 0..data.len() {
+        let result =
+            <EventV2 as ContextTransmaterializable>::rematerialize(&ctx, &data[..len]).await;
+        assert!(result.is_err(), "truncated to {len} bytes");
+    }
+
+    // data after the payload of an enum is an error, too
+    let mut extended = data.clone();
+    extended.push(0);
+    assert!(
+        <EventV2 as ContextTransmaterializable>::rematerialize(&ctx, &extended)
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
+// compact and transparent encoding
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct Sample {
+    pub sensor: u8,
+    pub value: i16,
+    pub flags: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(compact)]
+pub struct CompactSample {
+    pub sensor: u8,
+    pub value: i16,
+    pub flags: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(compact)]
+pub enum CompactEventV1 {
+    Ping,
+    Sample(CompactSample),
+    Log {
+        level: u8,
+        text: String,
+    },
+    #[wormhole(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(compact)]
+pub enum CompactEventV2 {
+    Ping,
+    Sample(CompactSample),
+    Log {
+        level: u8,
+        text: String,
+        #[wormhole(default)]
+        module: Option<String>,
+    },
+    Reset,
+    #[wormhole(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(transparent)]
+pub struct UserAlias(String);
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(transparent)]
+pub struct SessionToken {
+    #[serde]
+    pub token: String,
+}
+
+#[tokio::test]
+pub async fn test_compact_encoding_is_smaller() -> anyhow::Result<()> {
+    let ctx = test_context("compact: sizes").await?;
+
+    let sample = Sample {
+        sensor: 1,
+        value: -300,
+        flags: 0,
+    };
+    let compact = CompactSample {
+        sensor: 1,
+        value: -300,
+        flags: 0,
+    };
+    let default_size = sample.immaterialize(&ctx).await?.len();
+    let compact_size = compact.clone().immaterialize(&ctx).await?.len();
+    // 3 * 8 bytes of lengths vs. 3 * 1 byte
+    assert_eq!(default_size, 4 + 3 * 8);
+    assert_eq!(compact_size, 4 + 3);
+
+    // the variant index instead of the name, no payload length
+    let event = CompactEventV1::Sample(compact).immaterialize(&ctx).await?;
+    assert_eq!(event.len(), 1 + 1 + compact_size);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_compact_roundtrip() -> anyhow::Result<()> {
+    let events = vec![
+        CompactEventV2::Ping,
+        CompactEventV2::Sample(CompactSample {
+            sensor: 200,
+            value: i16::MIN,
+            flags: 255,
+        }),
+        CompactEventV2::Log {
+            level: 3,
+            // longer than 127 bytes, so the length takes two bytes
+            text: "x".repeat(300),
+            module: Some("net".to_string()),
+        },
+        CompactEventV2::Reset,
+        CompactEventV2::Unknown,
+    ];
+    let received = roundtrip("compact: roundtrip", events.clone()).await?;
+    assert_eq!(received, events);
+
+    // an older peer falls back to the `other` variant, and ignores the new field
+    let received: Vec<CompactEventV1> = send_across("compact: v2 to v1", events).await?;
+    assert_eq!(
+        received,
+        vec![
+            CompactEventV1::Ping,
+            CompactEventV1::Sample(CompactSample {
+                sensor: 200,
+                value: i16::MIN,
+                flags: 255,
+            }),
+            CompactEventV1::Log {
+                level: 3,
+                text: "x".repeat(300),
+            },
+            CompactEventV1::Unknown,
+            // the index of `Unknown` in v2 isn't known to v1 either
+            CompactEventV1::Unknown,
+        ]
+    );
+
+    // truncated data is an error
+    let ctx = test_context("compact: truncated").await?;
+    let data = CompactEventV1::Log {
+        level: 1,
+        text: "hello".to_string(),
+    }
+    .immaterialize(&ctx)
+    .await?;
+    for len in 0..data.len() {
+        let result =
+            <CompactEventV1 as ContextTransmaterializable>::rematerialize(&ctx, &data[..len]).await;
+        assert!(result.is_err(), "truncated to {len} bytes");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_transparent() -> anyhow::Result<()> {
+    let ctx = test_context("transparent: encoding").await?;
+
+    // a transparent newtype is sent exactly like its field
+    let alias = UserAlias("alice".to_string()).immaterialize(&ctx).await?;
+    let string = "alice".to_string().immaterialize(&ctx).await?;
+    assert_eq!(alias, string);
+
+    let received = roundtrip(
+        "transparent: roundtrip",
+        vec![UserAlias("alice".to_string()), UserAlias(String::new())],
+    )
+    .await?;
+    assert_eq!(
+        received,
+        vec![UserAlias("alice".to_string()), UserAlias(String::new())]
+    );
+
+    let received = roundtrip(
+        "transparent: codec",
+        vec![SessionToken {
+            token: "secret".to_string(),
+        }],
+    )
+    .await?;
+    assert_eq!(received[0].token, "secret");
+
+    Ok(())
+}
+
+// nested messages are written into one buffer
+// -----------------------------------------------------------------------------
+
+/// an implementation which only provides `immaterialize`, like the ones written before `immaterialize_into`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Legacy(pub u16);
+
+#[ractor_wormhole::transmaterialization::transmaterialization_proxies::async_trait]
+impl ContextTransmaterializable for Legacy {
+    async fn immaterialize(self, _ctx: &TransmaterializationContext) -> anyhow::Result<Vec<u8>> {
+        Ok(self.0.to_string().into_bytes())
+    }
+
+    async fn rematerialize(
+        _ctx: &TransmaterializationContext,
+        data: &[u8],
+    ) -> anyhow::Result<Self> {
+        Ok(Legacy(std::str::from_utf8(data)?.parse()?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct Inner {
+    pub values: Vec<Vec<u32>>,
+    pub legacy: Legacy,
+    pub pair: (String, Option<i64>),
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum Outer {
+    Many(Vec<Inner>),
+    One {
+        inner: Result<Inner, String>,
+        #[serde]
+        note: String,
+    },
+}
+
+#[derive(Debug, WormholeTransmaterializable)]
+pub struct Lookup {
+    pub keys: Vec<(String, Vec<u8>)>,
+    pub reply: Option<ractor::RpcReplyPort<Vec<Option<String>>>>,
+}
+
+fn sample_inner(seed: u32) -> Inner {
+    Inner {
+        values: vec![vec![seed, seed + 1], vec![], vec![seed * 100]],
+        legacy: Legacy(seed as u16),
+        pair: (format!("inner {seed}"), Some(-(seed as i64))),
+    }
+}
+
+#[tokio::test]
+pub async fn test_nested_buffers() -> anyhow::Result<()> {
+    let ctx = test_context("nested: buffers").await?;
+
+    let messages = vec![
+        Outer::Many(vec![sample_inner(1), sample_inner(2)]),
+        Outer::One {
+            inner: Ok(sample_inner(3)),
+            note: "ok".to_string(),
+        },
+        Outer::One {
+            inner: Err("failed".to_string()),
+            note: String::new(),
+        },
+    ];
+
+    // writing into an existing buffer appends exactly the bytes of `immaterialize`
+    for msg in &messages {
+        let bytes = msg.clone().immaterialize(&ctx).await?;
+        let mut buffer = vec![0xaa; 3];
+        msg.clone().immaterialize_into(&ctx, &mut buffer).await?;
+        assert_eq!(&buffer[..3], &[0xaa; 3]);
+        assert_eq!(&buffer[3..], bytes.as_slice());
+    }
+
+    let received = roundtrip("nested: roundtrip", messages.clone()).await?;
+    assert_eq!(received, messages);
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_nested_reply_port() -> anyhow::Result<()> {
+    let (reply, rx) = ractor::concurrency::oneshot();
+    let lookup = Lookup {
+        keys: vec![("a".to_string(), vec![1]), ("b".to_string(), vec![])],
+        reply: Some(reply.into()),
+    };
+
+    let mut received = roundtrip("nested: reply port", vec![lookup]).await?;
+    let lookup = received.remove(0);
+    assert_eq!(lookup.keys.len(), 2);
+    let answer = vec![Some("1".to_string()), None];
+    lookup
+        .reply
+        .ok_or_else(|| anyhow::anyhow!("The reply port is missing"))?
+        .send(answer.clone())?;
+
+    assert_eq!(
+        tokio::time::timeout(Duration::from_secs(5), rx).await??,
+        answer
+    );
+    Ok(())
+}
+
+// #[wormhole(plain)]: synchronous (im)materialization without the context
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(plain)]
+pub struct Reading {
+    pub sensor: String,
+    pub values: Vec<f64>,
+    #[serde]
+    pub settings: Settings,
+    pub flags: Option<(u8, bool)>,
+    pub command: Command,
+}
+
+/// the same fields as `Reading`, without `#[wormhole(plain)]`
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub struct ContextReading {
+    pub sensor: String,
+    pub values: Vec<f64>,
+    #[serde]
+    pub settings: Settings,
+    pub flags: Option<(u8, bool)>,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(plain, compact)]
+pub enum Command {
+    Stop,
+    Move(i32, i32),
+    Configure {
+        gain: Celsius,
+        #[wormhole(default)]
+        label: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(plain, transparent)]
+pub struct Celsius(f32);
+
+fn reading(seed: u8) -> Reading {
+    Reading {
+        sensor: format!("sensor {seed}"),
+        values: vec![f64::from(seed), -0.5, f64::MAX],
+        settings: settings("plain"),
+        flags: Some((seed, seed.is_multiple_of(2))),
+        command: match seed % 3 {
+            0 => Command::Stop,
+            1 => Command::Move(-(seed as i32), 7),
+            _ => Command::Configure {
+                gain: Celsius(21.5),
+                label: "warm".to_string(),
+            },
+        },
+    }
+}
+
+#[tokio::test]
+pub async fn test_plain_types() -> anyhow::Result<()> {
+    use ractor_wormhole::transmaterialization::PlainTransmaterializable;
+
+    let ctx = test_context("plain: bytes").await?;
+
+    for seed in 0..3 {
+        let plain = reading(seed);
+        let context = ContextReading {
+            sensor: plain.sensor.clone(),
+            values: plain.values.clone(),
+            settings: plain.settings.clone(),
+            flags: plain.flags,
+            command: plain.command.clone(),
+        };
+
+        // the plain implementation writes the same bytes as the one with the context
+        let bytes = plain.clone().immaterialize_plain()?;
+        assert_eq!(bytes, plain.clone().immaterialize(&ctx).await?);
+        assert_eq!(bytes, context.immaterialize(&ctx).await?);
+        assert_eq!(Reading::rematerialize_plain(&bytes)?, plain);
+
+        // and containers of plain types are plain as well
+        let nested = vec![(seed, vec![plain.clone()])];
+        let bytes = nested.clone().immaterialize_plain()?;
+        assert_eq!(bytes, nested.clone().immaterialize(&ctx).await?);
+        assert_eq!(
+            <Vec<(u8, Vec<Reading>)>>::rematerialize(&ctx, &bytes).await?,
+            nested
+        );
+    }
+
+    assert!(<Vec<Vec<u32>>>::plain().is_some());
+    assert!(<(String, Option<Reading>, Result<u8, String>)>::plain().is_some());
+    assert!(<ContextReading>::plain().is_none());
+    assert!(<Vec<(u8, Lookup)>>::plain().is_none());
+
+    let received = roundtrip("plain: roundtrip", (0..3).map(reading).collect()).await?;
+    assert_eq!(received, (0..3).map(reading).collect::<Vec<_>>());
+
+    let received: Vec<ContextReading> = send_across("plain: across", vec![reading(2)]).await?;
+    assert_eq!(received[0].command, reading(2).command);
+    Ok(())
+}
@@ -1,9 +1,9 @@
-#![allow(unreachable_code)] // 'unreachable' warning, todo: fix in 'WormholeSerializable'
-
 use ractor_wormhole::{
     WormholeTransmaterializable, transmaterialization::ContextTransmaterializable,
 };
 
+use crate::derive_roundtrip::{roundtrip, test_context};
+
 // Structs
 // -----------------------------------------------------------------------------
 
@@ -64,29 +64,222 @@ pub struct TwoTupleStruct(f32, f64);
 // Enums
 // -----------------------------------------------------------------------------
 
-// note: this shows a 'unreachable' warning, todo: fix in 'WormholeSerializable'
-
 #[derive(Debug, Clone, WormholeTransmaterializable)]
 pub enum EmptyEnum {}
 
-#[derive(Debug, Clone, WormholeTransmaterializable)]
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
 pub enum SingleCaseNoDataEnum {
     A,
 }
 
-#[derive(Debug, Clone, WormholeTransmaterializable)]
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
 pub enum TwoCaseNoDataEnum {
     A,
     B,
 }
 
-#[derive(Debug, Clone, WormholeTransmaterializable)]
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
 pub enum SingleCaseIntEnum {
     B = 1,
 }
 
-// todo: unnamed structs in enum cases aren't supported yet
-// #[derive(Debug, Clone, WormholeTransmaterializable)]
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
 pub enum StructEnum {
     X { a: u32 },
 }
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum MixedEnum {
+    Unit,
+    EmptyTuple(),
+    Tuple(u32, String),
+    EmptyNamed {},
+    Named { a: i64, b: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum GenericEnum<T: ContextTransmaterializable + Send, E: ContextTransmaterializable + Send> {
+    Value(T),
+    Pair { value: T, error: E },
+    Nothing,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+pub enum GenericEnumWithWhereClause<T>
+where
+    T: ContextTransmaterializable + Send + Sync + 'static,
+{
+    One(T),
+    Many { values: Vec<T> },
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(plain)]
+pub enum GenericPlainEnum<T: Send> {
+    Some(T),
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[repr(u8)]
+pub enum DiscriminantEnum {
+    A = 10,
+    B,
+    C(u32) = 0x20,
+    D { a: bool } = 5,
+}
+
+/// the discriminants are the indices on the wire
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(compact)]
+#[repr(u8)]
+pub enum CompactDiscriminantEnum {
+    A = 10,
+    B,
+    C(u32) = 0x20,
+    D { a: bool } = 5,
+}
+
+/// `CompactDiscriminantEnum`, with the variants reordered
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(compact)]
+#[repr(u8)]
+pub enum ReorderedDiscriminantEnum {
+    D { a: bool } = 5,
+    C(u32) = 0x20,
+    B = 11,
+    A = 10,
+}
+
+// Round-trips through a portal
+// -----------------------------------------------------------------------------
+
+#[tokio::test]
+pub async fn test_enum_variant_shapes() -> anyhow::Result<()> {
+    let messages = vec![
+        MixedEnum::Unit,
+        MixedEnum::EmptyTuple(),
+        MixedEnum::Tuple(7, "seven".to_string()),
+        MixedEnum::EmptyNamed {},
+        MixedEnum::Named {
+            a: -1,
+            b: Some("b".to_string()),
+        },
+        MixedEnum::Named { a: 2, b: None },
+    ];
+    assert_eq!(
+        roundtrip("enums: shapes", messages.clone()).await?,
+        messages
+    );
+
+    let messages = vec![StructEnum::X { a: 42 }];
+    assert_eq!(
+        roundtrip("enums: struct", messages.clone()).await?,
+        messages
+    );
+
+    let messages = vec![TwoCaseNoDataEnum::B, TwoCaseNoDataEnum::A];
+    assert_eq!(
+        roundtrip("enums: no data", messages.clone()).await?,
+        messages
+    );
+
+    let messages = vec![SingleCaseNoDataEnum::A];
+    assert_eq!(
+        roundtrip("enums: single", messages.clone()).await?,
+        messages
+    );
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_generic_enums() -> anyhow::Result<()> {
+    let messages: Vec<GenericEnum<String, u8>> = vec![
+        GenericEnum::Value("value".to_string()),
+        GenericEnum::Pair {
+            value: "pair".to_string(),
+            error: 3,
+        },
+        GenericEnum::Nothing,
+    ];
+    assert_eq!(
+        roundtrip("enums: generic", messages.clone()).await?,
+        messages
+    );
+
+    let messages = vec![
+        GenericEnumWithWhereClause::One((1u8, true)),
+        GenericEnumWithWhereClause::Many {
+            values: vec![(2, false), (3, true)],
+        },
+    ];
+    assert_eq!(roundtrip("enums: where", messages.clone()).await?, messages);
+
+    let messages = vec![
+        GenericPlainEnum::Some(vec!["plain".to_string()]),
+        GenericPlainEnum::None,
+    ];
+    assert!(GenericPlainEnum::<Vec<String>>::plain().is_some());
+    assert_eq!(roundtrip("enums: plain", messages.clone()).await?, messages);
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_enum_discriminants() -> anyhow::Result<()> {
+    let messages = vec![
+        DiscriminantEnum::A,
+        DiscriminantEnum::B,
+        DiscriminantEnum::C(3),
+        DiscriminantEnum::D { a: true },
+    ];
+    assert_eq!(
+        roundtrip("enums: discriminants", messages.clone()).await?,
+        messages
+    );
+
+    let messages = vec![
+        CompactDiscriminantEnum::A,
+        CompactDiscriminantEnum::B,
+        CompactDiscriminantEnum::C(3),
+        CompactDiscriminantEnum::D { a: true },
+    ];
+    assert_eq!(
+        roundtrip("enums: compact discriminants", messages.clone()).await?,
+        messages
+    );
+
+    // the discriminant is sent as the index
+    let ctx = test_context("enums: indices").await?;
+    assert_eq!(
+        CompactDiscriminantEnum::B.immaterialize(&ctx).await?,
+        vec![11]
+    );
+    assert_eq!(
+        CompactDiscriminantEnum::C(3).immaterialize(&ctx).await?[0],
+        0x20
+    );
+
+    // so the variants can be reordered
+    let received: Vec<ReorderedDiscriminantEnum> =
+        crate::derive_roundtrip::send_across("enums: reordered", messages).await?;
+    assert_eq!(
+        received,
+        vec![
+            ReorderedDiscriminantEnum::A,
+            ReorderedDiscriminantEnum::B,
+            ReorderedDiscriminantEnum::C(3),
+            ReorderedDiscriminantEnum::D { a: true },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_empty_enum() -> anyhow::Result<()> {
+    let ctx = test_context("enums: empty").await?;
+
+    // there is no value to send, and nothing can be received
+    let data = SingleCaseNoDataEnum::A.immaterialize(&ctx).await?;
+    assert!(EmptyEnum::rematerialize(&ctx, &data).await.is_err());
+    Ok(())
+}
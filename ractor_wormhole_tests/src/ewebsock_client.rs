@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use ractor_wormhole::conduit::websocket::{
+    client::ewebsock::connect_to_server, server::tokio_tungstenite::start_server,
+};
+use ractor_wormhole::conduit::{CloseCode, CloseReason};
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+
+use crate::util::nexus;
+
+#[tokio::test]
+pub async fn test_ewebsock_connect() -> anyhow::Result<()> {
+    let server = start_server(nexus("ewebsock: server").await?, "127.0.0.1:0".parse()?).await?;
+
+    let url = format!("ws://{}", server.local_addr());
+    let portal = connect_to_server(nexus("ewebsock: client").await?, url).await?;
+    // the introductions were exchanged in both directions
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+    assert_eq!(server.portals().len(), 1);
+
+    // closing the client side closes the server side, too
+    portal.send_message(PortalActorMessage::Disconnect(None))?;
+    portal.get_cell().wait(Some(Duration::from_secs(5))).await?;
+    for _ in 0..100 {
+        if server.connection_count() == 0 {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Err(anyhow::anyhow!(
+        "the server didn't notice the closed connection"
+    ))
+}
+
+#[tokio::test]
+pub async fn test_ewebsock_connect_failure() -> anyhow::Result<()> {
+    // find a port nobody listens on
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+    let connecting = connect_to_server(
+        nexus("ewebsock failure: client").await?,
+        format!("ws://{addr}"),
+    );
+    // the error is reported, instead of waiting forever
+    let result = tokio::time::timeout(Duration::from_secs(10), connecting).await?;
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_ewebsock_server_close() -> anyhow::Result<()> {
+    let server = start_server(
+        nexus("ewebsock close: server").await?,
+        "127.0.0.1:0".parse()?,
+    )
+    .await?;
+
+    let url = format!("ws://{}", server.local_addr());
+    let portal = connect_to_server(nexus("ewebsock close: client").await?, url).await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+
+    // the close is propagated into the client portal, but ewebsock doesn't report its reason
+    let closed = tokio::spawn({
+        let portal = portal.clone();
+        async move { portal.wait_for_closed().await }
+    });
+    server.shutdown("maintenance", Duration::from_secs(5)).await;
+    portal.get_cell().wait(Some(Duration::from_secs(5))).await?;
+    assert_eq!(closed.await??, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_ewebsock_close_reason_is_not_sent() -> anyhow::Result<()> {
+    let server = start_server(
+        nexus("ewebsock reason: server").await?,
+        "127.0.0.1:0".parse()?,
+    )
+    .await?;
+
+    let url = format!("ws://{}", server.local_addr());
+    let portal = connect_to_server(nexus("ewebsock reason: client").await?, url).await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+    let server_portal = server.portals().remove(0);
+    let closed = tokio::spawn(async move { server_portal.wait_for_closed().await });
+
+    // the client closes with a reason, the server side is closed without it
+    portal.send_message(PortalActorMessage::Disconnect(Some(CloseReason::new(
+        CloseCode::Normal,
+        "logged out",
+    ))))?;
+    let reason = tokio::time::timeout(Duration::from_secs(5), closed).await???;
+    assert_eq!(reason, None);
+
+    Ok(())
+}
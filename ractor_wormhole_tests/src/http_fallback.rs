@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::{Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use ractor_wormhole::conduit::http_fallback::client;
+use ractor_wormhole::conduit::http_fallback::server::{HttpFallbackConfig, HttpFallbackServer};
+use ractor_wormhole::conduit::{CloseCode, CloseReason, ConnectionContext};
+use ractor_wormhole::nexus::{OnActorConnectedMessage, start_nexus};
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::util::FnActor;
+use tokio::net::TcpListener;
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::hello_proxy;
+
+/// a hyper server which serves the fallback routes below `/wormhole`, and publishes a `hello` actor on every new portal.
+async fn start_server(
+    name: &str,
+    config: HttpFallbackConfig,
+) -> anyhow::Result<(
+    String,
+    HttpFallbackServer,
+    tokio::sync::mpsc::UnboundedReceiver<String>,
+    tokio::sync::mpsc::UnboundedReceiver<ConnectionContext>,
+)> {
+    let (hello_tx, hello_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = hello_tx.send(msg.msg);
+        }
+    })
+    .await?;
+
+    let (connected_tx, connected_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (on_connected, _) = FnActor::<OnActorConnectedMessage>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = msg
+                .actor_ref
+                .publish_named_actor("hello".to_string(), hello_actor.clone())
+                .await;
+            let _ = connected_tx.send(msg.context);
+        }
+    })
+    .await?;
+
+    let nexus = start_nexus(Some(name.to_string()), Some(on_connected))
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let fallback = HttpFallbackServer::with_config(nexus, "/wormhole", config);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = fallback.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, remote_addr)) = listener.accept().await {
+            let server = server.clone();
+            let service = hyper::service::service_fn(move |request| {
+                let server = server.clone();
+                async move {
+                    if server.is_fallback_request(&request) {
+                        return Ok::<_, anyhow::Error>(
+                            server.handle(request, Some(remote_addr)).await,
+                        );
+                    }
+                    let mut response = Response::new(Full::new(Bytes::from("not found")));
+                    *response.status_mut() = StatusCode::NOT_FOUND;
+                    Ok(response.map(http_body_util::BodyExt::boxed_unsync))
+                }
+            });
+            tokio::spawn(
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service),
+            );
+        }
+    });
+
+    Ok((
+        format!("http://{addr}/wormhole"),
+        fallback,
+        hello_rx,
+        connected_rx,
+    ))
+}
+
+#[tokio::test]
+pub async fn test_http_fallback() -> anyhow::Result<()> {
+    let (url, fallback, mut hello_rx, mut connected_rx) =
+        start_server("fallback: server", Default::default()).await?;
+
+    let client_nexus = start_nexus(Some("fallback: client".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let portal = client::connect_to_server(client_nexus, &url).await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let context = tokio::time::timeout(Duration::from_secs(5), connected_rx.recv())
+        .await?
+        .expect("the server reports the connection");
+    assert_eq!(context.path.as_deref(), Some("/wormhole"));
+    assert!(context.remote_addr.is_some());
+
+    // the lookup goes both ways, then the messages arrive in order
+    let proxy = hello_proxy(&portal).await?;
+    for i in 0..20 {
+        proxy.send_message(HelloMsg {
+            msg: format!("hello {i}"),
+        })?;
+    }
+    for i in 0..20 {
+        let received = tokio::time::timeout(Duration::from_secs(5), hello_rx.recv()).await?;
+        assert_eq!(received, Some(format!("hello {i}")));
+    }
+
+    // disconnecting the client ends the session on the server
+    assert_eq!(fallback.session_count(), 1);
+    portal.cast(PortalActorMessage::Disconnect(Some(CloseReason::new(
+        CloseCode::Normal,
+        "bye",
+    ))))?;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while fallback.session_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_http_fallback_unknown_session() -> anyhow::Result<()> {
+    let (url, _fallback, _hello_rx, _connected_rx) =
+        start_server("fallback: unknown", Default::default()).await?;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{url}/0123"))
+        .body("hello")
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = client.get(format!("{url}/0123")).send().await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_http_fallback_limits() -> anyhow::Result<()> {
+    let config = HttpFallbackConfig {
+        max_sessions: Some(1),
+        open_timeout: Duration::from_millis(200),
+        max_body_size: 16,
+        ..Default::default()
+    };
+    let (url, fallback, _hello_rx, _connected_rx) =
+        start_server("fallback: limits", config).await?;
+
+    let client = reqwest::Client::new();
+    let response = client.post(&url).send().await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let session = response.text().await?;
+
+    // only one session at a time
+    let response = client.post(&url).send().await?;
+    assert_eq!(response.status().as_u16(), 503);
+
+    let response = client
+        .post(format!("{url}/{session}"))
+        .body(vec![0u8; 100])
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 413);
+
+    // the event stream is never requested, so the session is closed
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while fallback.session_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+    let response = client.get(format!("{url}/{session}")).send().await?;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = client.post(&url).send().await?;
+    assert_eq!(response.status().as_u16(), 200);
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::{Router, routing::get};
+use hyper_util::rt::TokioIo;
+use ractor::ActorRef;
+use ractor_wormhole::conduit::{ConnectionContext, websocket};
+use ractor_wormhole::nexus::{NexusActorMessage, OnActorConnectedMessage, start_nexus};
+use ractor_wormhole::portal::Portal;
+use ractor_wormhole::util::FnActor;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tungstenite::ClientRequestBuilder;
+
+/// starts a nexus which reports the context of every new portal
+async fn start_reporting_nexus(
+    name: &str,
+) -> anyhow::Result<(
+    ActorRef<NexusActorMessage>,
+    UnboundedReceiver<(String, ConnectionContext)>,
+)> {
+    let (connected_tx, connected_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (on_connected, _) = FnActor::<OnActorConnectedMessage>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = connected_tx.send((msg.identifier, msg.context));
+        }
+    })
+    .await?;
+
+    let nexus = start_nexus(Some(name.to_string()), Some(on_connected))
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    Ok((nexus, connected_rx))
+}
+
+/// connects a client to the url, sending an additional header
+async fn connect_client(name: &str, url: String) -> anyhow::Result<()> {
+    let client_nexus = start_nexus(Some(name.to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let request = ClientRequestBuilder::new(url.parse()?).with_header("x-wormhole-test", "42");
+    let portal =
+        websocket::client::tokio_tungstenite::connect_to_server(client_nexus, request).await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+    Ok(())
+}
+
+async fn next_connection(
+    connected_rx: &mut UnboundedReceiver<(String, ConnectionContext)>,
+) -> anyhow::Result<(String, ConnectionContext)> {
+    tokio::time::timeout(Duration::from_secs(5), connected_rx.recv())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("the server didn't report the connection"))
+}
+
+#[tokio::test]
+pub async fn test_axum_upgrade() -> anyhow::Result<()> {
+    let (server_nexus, mut connected_rx) = start_reporting_nexus("axum: server").await?;
+
+    let app = Router::new()
+        .route("/wormhole", get(websocket::server::axum::websocket_handler))
+        .with_state(server_nexus);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+    });
+
+    connect_client("axum: client", format!("ws://{addr}/wormhole")).await?;
+
+    let (identifier, context) = next_connection(&mut connected_rx).await?;
+    assert_eq!(context.path.as_deref(), Some("/wormhole"));
+    assert_eq!(context.header("X-Wormhole-Test"), Some("42"));
+    let remote_addr = context.remote_addr.expect("the remote address is known");
+    assert!(remote_addr.ip().is_loopback());
+    assert_eq!(identifier, format!("ws://{remote_addr}/wormhole"));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_hyper_service() -> anyhow::Result<()> {
+    let (server_nexus, mut connected_rx) = start_reporting_nexus("hyper: server").await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((stream, remote_addr)) = listener.accept().await {
+            let service = websocket::server::hyper::websocket_service(
+                server_nexus.clone(),
+                Some(remote_addr),
+            );
+            tokio::spawn(
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .with_upgrades(),
+            );
+        }
+    });
+
+    connect_client("hyper: client", format!("ws://{addr}/some/path")).await?;
+
+    let (_, context) = next_connection(&mut connected_rx).await?;
+    assert_eq!(context.path.as_deref(), Some("/some/path"));
+    assert_eq!(context.header("x-wormhole-test"), Some("42"));
+    assert!(context.remote_addr.is_some());
+
+    Ok(())
+}
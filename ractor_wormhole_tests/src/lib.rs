@@ -1,6 +1,26 @@
 #![cfg(test)]
 
+pub mod batching;
+pub mod conduit_layers;
+pub mod derive_roundtrip;
 pub mod derive_tests;
+#[cfg(feature = "ewebsock")]
+pub mod ewebsock_client;
+pub mod http_fallback;
+pub mod http_upgrade;
+pub mod multiplex;
+pub mod noise_layer;
+pub mod priority_lanes;
+pub mod quic_conduit;
 pub mod readme;
+pub mod reconnect;
 pub mod remote_linking;
+#[cfg(target_os = "linux")]
+pub mod serial_conduit;
+#[cfg(target_os = "linux")]
+pub mod shared_memory;
+pub mod std_types;
 pub mod tiny_wormhole;
+pub mod tls_websocket;
+pub mod util;
+pub mod websocket_server;
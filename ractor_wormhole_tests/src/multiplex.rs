@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use ractor::ActorRef;
+use ractor_wormhole::conduit::multiplex::{MultiplexConfig, MultiplexLink};
+use ractor_wormhole::conduit::{CloseCode, CloseReason, ConnectionContext};
+use ractor_wormhole::nexus::OnActorConnectedMessage;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::{channel, reporting_nexus};
+
+async fn linked_pair(
+    name: &str,
+    server_config: MultiplexConfig,
+) -> anyhow::Result<(
+    MultiplexLink,
+    MultiplexLink,
+    tokio::sync::mpsc::UnboundedReceiver<OnActorConnectedMessage>,
+)> {
+    let (sink_a, source_b) = channel();
+    let (sink_b, source_a) = channel();
+
+    let (client_nexus, _) = reporting_nexus(&format!("{name}: client")).await?;
+    let (server_nexus, server_connected) = reporting_nexus(&format!("{name}: server")).await?;
+
+    let client = MultiplexLink::new(
+        client_nexus,
+        "client".to_string(),
+        ConnectionContext::default(),
+        sink_a,
+        source_a,
+    );
+    let server = MultiplexLink::with_config(
+        server_nexus,
+        "server".to_string(),
+        ConnectionContext::default(),
+        sink_b,
+        source_b,
+        server_config,
+    );
+    Ok((client, server, server_connected))
+}
+
+/// publishes an actor named "hello" on the portal, which forwards every message to the returned receiver
+async fn publish_hello(
+    portal: &ActorRef<PortalActorMessage>,
+) -> anyhow::Result<tokio::sync::mpsc::UnboundedReceiver<String>> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg.msg);
+        }
+    })
+    .await?;
+    portal
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+    Ok(rx)
+}
+
+async fn send_hello(portal: &ActorRef<PortalActorMessage>, msg: &str) -> anyhow::Result<()> {
+    let hello_actor_id = portal
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let proxy = portal
+        .instantiate_proxy_for_remote_actor(hello_actor_id)
+        .await?;
+    proxy.send_message(HelloMsg {
+        msg: msg.to_string(),
+    })?;
+    Ok(())
+}
+
+/// the portals opened by the remote side are registered asynchronously
+async fn wait_for_portals(link: &MultiplexLink, count: usize) -> anyhow::Result<()> {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while link.portals().len() != count {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_multiplexed_portals_are_independent() -> anyhow::Result<()> {
+    let (client, server, mut server_connected) =
+        linked_pair("multiplex", MultiplexConfig::default()).await?;
+
+    let portal_a = client.open_portal().await?;
+    let portal_b = client.open_portal().await?;
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let remote_a = tokio::time::timeout(Duration::from_secs(5), server_connected.recv())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no portal"))?
+        .actor_ref;
+    let remote_b = tokio::time::timeout(Duration::from_secs(5), server_connected.recv())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no portal"))?
+        .actor_ref;
+
+    // the same name is published on both portals, each one only reaches its own actor
+    let mut received_a = publish_hello(&remote_a).await?;
+    let mut received_b = publish_hello(&remote_b).await?;
+
+    // the portals are opened concurrently, so the order on the server is not known
+    send_hello(&portal_a, "first").await?;
+    send_hello(&portal_b, "second").await?;
+    let (first, second) = tokio::select! {
+        msg = received_a.recv() => (msg, received_b.recv().await),
+        msg = received_b.recv() => (received_a.recv().await, msg),
+    };
+    let mut received = vec![first.unwrap_or_default(), second.unwrap_or_default()];
+    received.sort();
+    assert_eq!(received, vec!["first".to_string(), "second".to_string()]);
+
+    // closing one portal leaves the other one and the link open
+    client.close_portal(&portal_a, Some(CloseReason::new(CloseCode::Normal, "done")));
+    portal_a
+        .get_cell()
+        .wait(Some(Duration::from_secs(5)))
+        .await?;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while remote_a.get_status() != ractor::ActorStatus::Stopped
+            && remote_b.get_status() != ractor::ActorStatus::Stopped
+        {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await?;
+    assert!(!client.is_closed());
+    assert_eq!(client.portals().len(), 1);
+    wait_for_portals(&server, 1).await?;
+
+    send_hello(&portal_b, "still there").await?;
+    let msg = tokio::time::timeout(Duration::from_secs(5), async {
+        tokio::select! {
+            msg = received_a.recv() => msg,
+            msg = received_b.recv() => msg,
+        }
+    })
+    .await?;
+    assert_eq!(msg.as_deref(), Some("still there"));
+
+    // a new portal can be opened on the same link
+    let portal_c = client.open_portal().await?;
+    portal_c.wait_for_opened(Duration::from_secs(5)).await?;
+    assert_eq!(client.portals().len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_multiplex_both_sides_open_and_close() -> anyhow::Result<()> {
+    let (client, server, mut server_connected) =
+        linked_pair("multiplex close", MultiplexConfig::default()).await?;
+
+    // both sides open a portal; their channel ids don't collide
+    let from_client = client.open_portal().await?;
+    let from_server = server.open_portal().await?;
+    from_client.wait_for_opened(Duration::from_secs(5)).await?;
+    from_server.wait_for_opened(Duration::from_secs(5)).await?;
+    tokio::time::timeout(Duration::from_secs(5), server_connected.recv()).await?;
+    wait_for_portals(&client, 2).await?;
+    wait_for_portals(&server, 2).await?;
+
+    // closing the link closes all of its portals, on both sides
+    client.close(Some(CloseReason::new(CloseCode::GoingAway, "bye")));
+    tokio::time::timeout(Duration::from_secs(5), server.closed()).await?;
+    assert!(client.is_closed());
+    for portal in [&from_client, &from_server] {
+        portal.get_cell().wait(Some(Duration::from_secs(5))).await?;
+    }
+    assert!(client.open_portal().await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_multiplex_channel_limit() -> anyhow::Result<()> {
+    let config = MultiplexConfig {
+        max_remote_channels: 2,
+    };
+    let (client, server, _server_connected) = linked_pair("multiplex limit", config).await?;
+
+    let first = client.open_portal().await?;
+    let second = client.open_portal().await?;
+    first.wait_for_opened(Duration::from_secs(5)).await?;
+    second.wait_for_opened(Duration::from_secs(5)).await?;
+
+    // the server refuses the third channel, so its portal is closed
+    let third = client.open_portal().await?;
+    third.get_cell().wait(Some(Duration::from_secs(5))).await?;
+    assert!(
+        third
+            .wait_for_opened(Duration::from_millis(100))
+            .await
+            .is_err()
+    );
+    wait_for_portals(&server, 2).await?;
+
+    // the limit only applies to the remote side
+    let from_server = server.open_portal().await?;
+    from_server.wait_for_opened(Duration::from_secs(5)).await?;
+
+    // a closed channel makes room for a new one
+    client.close_portal(&first, None);
+    wait_for_portals(&server, 2).await?;
+    let fourth = client.open_portal().await?;
+    fourth.wait_for_opened(Duration::from_secs(5)).await?;
+
+    Ok(())
+}
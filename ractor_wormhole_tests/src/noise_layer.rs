@@ -0,0 +1,199 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use ractor_wormhole::conduit::layer::ConduitLayers;
+use ractor_wormhole::conduit::noise::{NoiseLayer, generate_keypair};
+use ractor_wormhole::conduit::{
+    CloseCode, CloseReason, ConduitMessage, ConduitSink, ConduitSource, ConnectionContext,
+    from_sink_source_with_layers,
+};
+use ractor_wormhole::nexus::start_nexus;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::reporting_nexus;
+
+/// one direction of the wire, recording everything which is sent over it
+fn tapped_channel(recorded: Arc<Mutex<Vec<Vec<u8>>>>) -> (ConduitSink, ConduitSource) {
+    let (tx, rx) = mpsc::channel::<ConduitMessage>(100);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let source: ConduitSource = Box::pin(rx.map(move |msg| {
+        let bytes = match &msg {
+            ConduitMessage::Text(text) => text.as_bytes().to_vec(),
+            ConduitMessage::Binary(data) | ConduitMessage::Bulk(data) => data.clone(),
+            ConduitMessage::Close(_) => Vec::new(),
+        };
+        recorded.lock().unwrap().push(bytes);
+        Ok(msg)
+    }));
+    (sink, source)
+}
+
+#[tokio::test]
+pub async fn test_noise_roundtrip() -> anyhow::Result<()> {
+    let client_key = generate_keypair()?;
+    let server_key = generate_keypair()?;
+
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let (sink_a, source_b) = tapped_channel(recorded.clone());
+    let (sink_b, source_a) = tapped_channel(recorded.clone());
+
+    let (server_nexus, mut server_connected) = reporting_nexus("noise: server").await?;
+    let (client_nexus, mut client_connected) = reporting_nexus("noise: client").await?;
+
+    // the server only accepts this client
+    let server_layers = ConduitLayers::new().with(
+        NoiseLayer::responder(server_key.private.clone())
+            .with_allowed_remote_keys(vec![client_key.public.clone()]),
+    );
+    let client_layers =
+        ConduitLayers::new().with(NoiseLayer::initiator(client_key.private.clone()));
+
+    // both handshakes have to run at the same time
+    let server = tokio::spawn(async move {
+        from_sink_source_with_layers(
+            server_nexus,
+            "server".to_string(),
+            ConnectionContext::default(),
+            sink_a,
+            source_a,
+            &server_layers,
+        )
+        .await
+    });
+    let client_portal = from_sink_source_with_layers(
+        client_nexus,
+        "client".to_string(),
+        ConnectionContext::default(),
+        sink_b,
+        source_b,
+        &client_layers,
+    )
+    .await?;
+    let server_portal = server.await??;
+
+    client_portal
+        .wait_for_opened(Duration::from_secs(5))
+        .await?;
+    server_portal
+        .wait_for_opened(Duration::from_secs(5))
+        .await?;
+
+    // both sides know the authenticated key of the other side
+    let server_context =
+        tokio::time::timeout(Duration::from_secs(5), server_connected.recv()).await?;
+    assert_eq!(
+        server_context.and_then(|msg| msg.context.remote_static_key),
+        Some(client_key.public.clone())
+    );
+    let client_context =
+        tokio::time::timeout(Duration::from_secs(5), client_connected.recv()).await?;
+    assert_eq!(
+        client_context.and_then(|msg| msg.context.remote_static_key),
+        Some(server_key.public.clone())
+    );
+
+    // a message through the encrypted portal
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg.msg);
+        }
+    })
+    .await?;
+    server_portal
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+    let hello_actor_id = client_portal
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let proxy = client_portal
+        .instantiate_proxy_for_remote_actor(hello_actor_id)
+        .await?;
+    proxy.send_message(HelloMsg {
+        msg: "top secret".to_string(),
+    })?;
+    let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?;
+    assert_eq!(received.as_deref(), Some("top secret"));
+
+    // the close reason arrives, encrypted
+    let reason = CloseReason::new(CloseCode::PolicyViolation, "secret reason");
+    server_portal.send_message(PortalActorMessage::Disconnect(Some(reason.clone())))?;
+    let closed =
+        tokio::time::timeout(Duration::from_secs(5), client_portal.wait_for_closed()).await??;
+    assert_eq!(closed, Some(reason));
+
+    // nothing readable went over the wire
+    for bytes in recorded.lock().unwrap().iter() {
+        let text = String::from_utf8_lossy(bytes);
+        assert!(!text.contains("top secret"));
+        assert!(!text.contains("secret reason"));
+        assert!(!text.contains("channel_id_contribution"));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_noise_rejects_unknown_key() -> anyhow::Result<()> {
+    let client_key = generate_keypair()?;
+    let server_key = generate_keypair()?;
+    let other_key = generate_keypair()?;
+
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let (sink_a, source_b) = tapped_channel(recorded.clone());
+    let (sink_b, source_a) = tapped_channel(recorded);
+
+    let server_layers = ConduitLayers::new().with(
+        NoiseLayer::responder(server_key.private).with_allowed_remote_keys(vec![other_key.public]),
+    );
+    let client_layers = ConduitLayers::new().with(NoiseLayer::initiator(client_key.private));
+
+    let server_nexus = start_nexus(Some("noise reject: server".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let client_nexus = start_nexus(Some("noise reject: client".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+
+    let server = tokio::spawn(async move {
+        from_sink_source_with_layers(
+            server_nexus,
+            "server".to_string(),
+            ConnectionContext::default(),
+            sink_a,
+            source_a,
+            &server_layers,
+        )
+        .await
+    });
+    let client = from_sink_source_with_layers(
+        client_nexus,
+        "client".to_string(),
+        ConnectionContext::default(),
+        sink_b,
+        source_b,
+        &client_layers,
+    )
+    .await;
+
+    // the server refuses the client
+    assert!(server.await?.is_err());
+
+    // the client completed its part of the handshake, but the portal fails to open (depending on the timing,
+    //  sending the introduction fails), or is closed right away
+    if let Ok(client_portal) = client {
+        client_portal
+            .get_cell()
+            .wait(Some(Duration::from_secs(5)))
+            .await?;
+    }
+
+    Ok(())
+}
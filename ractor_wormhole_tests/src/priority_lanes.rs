@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use ractor::ActorRef;
+use ractor_wormhole::WormholeTransmaterializable;
+use ractor_wormhole::conduit::priority::{LaneWeights, Priority, PriorityOutbox};
+use ractor_wormhole::conduit::{
+    CloseCode, CloseReason, ConduitMessage, ConduitSink, ConduitSource, from_sink_source,
+};
+use ractor_wormhole::nexus::start_nexus;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::transmaterialization::ContextTransmaterializable;
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::channel;
+
+#[derive(Debug, WormholeTransmaterializable)]
+#[wormhole(priority = bulk)]
+pub struct FileChunk {
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, WormholeTransmaterializable)]
+#[wormhole(priority = "control")]
+pub enum Heartbeat {
+    Ping,
+    Pong,
+}
+
+#[test]
+fn test_derived_priority() {
+    assert_eq!(FileChunk { data: vec![] }.priority(), Priority::Bulk);
+    assert_eq!(Heartbeat::Ping.priority(), Priority::Control);
+    assert_eq!(HelloMsg { msg: String::new() }.priority(), Priority::Normal);
+}
+
+/// a portal which is never opened, only used to receive the errors of the outbox
+async fn dummy_portal(name: &str) -> anyhow::Result<ActorRef<PortalActorMessage>> {
+    let nexus = start_nexus(Some(name.to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let (tx, _rx) = mpsc::channel::<ConduitMessage>(100);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let source: ConduitSource = Box::pin(futures::stream::pending());
+    from_sink_source(nexus, name.to_string(), sink, source).await
+}
+
+fn text(msg: ConduitMessage) -> String {
+    match msg {
+        ConduitMessage::Text(text) => text,
+        ConduitMessage::Binary(_) | ConduitMessage::Bulk(_) => "<binary>".to_string(),
+        ConduitMessage::Close(reason) => match reason {
+            Some(reason) => format!("close: {}", reason.reason),
+            None => "close: ".to_string(),
+        },
+    }
+}
+
+#[tokio::test]
+pub async fn test_control_overtakes_queued_bulk_frames() -> anyhow::Result<()> {
+    // a sink without buffer, so frames queue up in the outbox while the wire is busy
+    let (tx, mut wire) = mpsc::channel::<ConduitMessage>(0);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let outbox = PriorityOutbox::spawn(
+        sink,
+        LaneWeights::default(),
+        64,
+        None,
+        dummy_portal("priority: outbox").await?,
+        "outbox".to_string(),
+    );
+
+    // the first bulk frame is already on the wire, the writer is blocked on the second one
+    outbox
+        .send(Priority::Bulk, ConduitMessage::Text("bulk 0".into()))
+        .await?;
+    outbox
+        .send(Priority::Bulk, ConduitMessage::Text("bulk 1".into()))
+        .await?;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    for i in 2..10 {
+        outbox
+            .send(Priority::Bulk, ConduitMessage::Text(format!("bulk {i}")))
+            .await?;
+    }
+    outbox
+        .send(Priority::Reply, ConduitMessage::Text("reply".into()))
+        .await?;
+    outbox
+        .send(Priority::Control, ConduitMessage::Text("control".into()))
+        .await?;
+    outbox
+        .send(
+            Priority::Control,
+            ConduitMessage::Close(Some(CloseReason::new(CloseCode::Normal, "done"))),
+        )
+        .await?;
+
+    let mut received = Vec::new();
+    while let Some(msg) = tokio::time::timeout(Duration::from_secs(5), wire.next()).await? {
+        received.push(text(msg));
+    }
+
+    assert_eq!(received.len(), 13);
+    let position = |name: &str| received.iter().position(|msg| msg == name).unwrap();
+    // only the frames which were already on their way are in front of the control and reply frames
+    assert!(position("control") <= 2);
+    assert!(position("reply") <= 3);
+    // the close comes after everything which was queued before it
+    assert_eq!(received.last().map(String::as_str), Some("close: done"));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_full_lane_waits() -> anyhow::Result<()> {
+    let (tx, mut wire) = mpsc::channel::<ConduitMessage>(0);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let outbox = PriorityOutbox::spawn(
+        sink,
+        LaneWeights::default(),
+        2,
+        None,
+        dummy_portal("priority: full lane").await?,
+        "outbox".to_string(),
+    );
+
+    // nobody reads the wire, so the bulk lane fills up
+    let mut queued = 0;
+    while tokio::time::timeout(
+        Duration::from_millis(100),
+        outbox.send(
+            Priority::Bulk,
+            ConduitMessage::Text(format!("bulk {queued}")),
+        ),
+    )
+    .await
+    .is_ok()
+    {
+        queued += 1;
+        assert!(queued < 10, "the lane is not bounded");
+    }
+
+    // the other lanes are not affected
+    tokio::time::timeout(
+        Duration::from_millis(100),
+        outbox.send(Priority::Control, ConduitMessage::Text("control".into())),
+    )
+    .await??;
+
+    // once the wire is read, the lane has room again
+    tokio::spawn(async move { while wire.next().await.is_some() {} });
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        outbox.send(Priority::Bulk, ConduitMessage::Text("bulk".into())),
+    )
+    .await??;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_exit_waits_for_queued_messages() -> anyhow::Result<()> {
+    let (tx, mut wire) = mpsc::channel::<ConduitMessage>(0);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let outbox = PriorityOutbox::spawn(
+        sink,
+        LaneWeights::default(),
+        64,
+        None,
+        dummy_portal("priority: exit").await?,
+        "outbox".to_string(),
+    );
+
+    for i in 0..5 {
+        outbox
+            .send(
+                Priority::Normal,
+                ConduitMessage::Text(format!("normal {i}")),
+            )
+            .await?;
+    }
+    outbox
+        .send(Priority::Control, ConduitMessage::Text("control".into()))
+        .await?;
+    outbox
+        .send_after_queued(Priority::Control, ConduitMessage::Text("exit".into()))
+        .await?;
+    outbox
+        .send(Priority::Normal, ConduitMessage::Text("normal 5".into()))
+        .await?;
+    outbox
+        .send(Priority::Control, ConduitMessage::Close(None))
+        .await?;
+
+    let mut received = Vec::new();
+    while let Some(msg) = tokio::time::timeout(Duration::from_secs(5), wire.next()).await? {
+        received.push(text(msg));
+    }
+
+    let position = |name: &str| received.iter().position(|msg| msg == name).unwrap();
+    // a plain control frame still overtakes the queued messages
+    assert!(position("control") <= 1);
+    // the exit comes after every message which was queued before it, but not after the later ones
+    assert_eq!(position("exit"), position("normal 4") + 1);
+    assert_eq!(position("normal 5"), position("exit") + 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_proxy_with_priority() -> anyhow::Result<()> {
+    let (sink_a, source_b) = channel();
+    let (sink_b, source_a) = channel();
+
+    let nexus_a = start_nexus(Some("priority: a".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let nexus_b = start_nexus(Some("priority: b".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let portal_a = from_sink_source(nexus_a, "a".to_string(), sink_a, source_a).await?;
+    let portal_b = from_sink_source(nexus_b, "b".to_string(), sink_b, source_b).await?;
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg.msg);
+        }
+    })
+    .await?;
+    portal_b
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+
+    let hello_actor_id = portal_a
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let proxy = portal_a
+        .instantiate_proxy_for_remote_actor_with_priority::<HelloMsg>(
+            hello_actor_id,
+            Priority::Bulk,
+        )
+        .await?;
+    proxy.send_message(HelloMsg {
+        msg: "in the bulk lane".to_string(),
+    })?;
+
+    let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?;
+    assert_eq!(received.as_deref(), Some("in the bulk lane"));
+
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ractor::{ActorRef, RpcReplyPort};
+use ractor_wormhole::conduit::quic::{
+    self, ServerConfig,
+    quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+};
+use ractor_wormhole::conduit::{CloseCode, CloseReason};
+use ractor_wormhole::nexus::NexusActorMessage;
+use ractor_wormhole::portal::{self, Portal};
+use ractor_wormhole::transmaterialization::GetRematerializer;
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::util::nexus;
+
+#[cfg_attr(
+    feature = "ractor_cluster",
+    derive(ractor_cluster_derive::RactorMessage)
+)]
+#[derive(Debug, ractor_wormhole::WormholeTransmaterializable)]
+pub enum QuicTestMsg {
+    Hello(String),
+    Double(u32, RpcReplyPort<u32>),
+}
+
+fn self_signed_certificate() -> anyhow::Result<(CertificateDer<'static>, PrivateKeyDer<'static>)> {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+    let cert = certified.cert.der().clone();
+    let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()).into();
+    Ok((cert, key))
+}
+
+#[tokio::test]
+pub async fn test_quic_conduit() -> anyhow::Result<()> {
+    let (cert, key) = self_signed_certificate()?;
+    let loopback: SocketAddr = "127.0.0.1:0".parse()?;
+
+    // server: publish a named actor on the nexus, so it is available on every portal
+    let received: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let received_clone = received.clone();
+    let (test_actor, _) = FnActor::<QuicTestMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            match msg {
+                QuicTestMsg::Hello(text) => received_clone.lock().unwrap().push(text),
+                QuicTestMsg::Double(value, reply) => {
+                    let _ = reply.send(value * 2);
+                }
+            }
+        }
+    })
+    .await?;
+
+    let server_nexus = ractor_wormhole::nexus::start_nexus(Some("quic: server".into()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    server_nexus.send_message(NexusActorMessage::PublishNamedActor(
+        "test".to_string(),
+        test_actor.get_cell(),
+        QuicTestMsg::get_rematerializer(),
+    ))?;
+
+    let server_endpoint = quic::server_endpoint(loopback, vec![cert.clone()], key)?;
+    let server_addr = server_endpoint.local_addr()?;
+    quic::start_server(server_nexus, server_endpoint).await?;
+
+    // client
+    let client_nexus = ractor_wormhole::nexus::start_nexus(Some("quic: client".into()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let client_endpoint = quic::client_endpoint(loopback, vec![cert])?;
+    let portal =
+        quic::connect_to_server(client_nexus, &client_endpoint, server_addr, "localhost").await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let test_actor_id = portal
+        .ask(
+            |rpc| portal::PortalActorMessage::QueryNamedRemoteActor("test".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let remote_test_actor: ActorRef<QuicTestMsg> = portal
+        .instantiate_proxy_for_remote_actor(test_actor_id)
+        .await?;
+
+    remote_test_actor.send_message(QuicTestMsg::Hello("before migration".to_string()))?;
+    let doubled = remote_test_actor
+        .ask(
+            |rpc| QuicTestMsg::Double(21, rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await?;
+    assert_eq!(doubled, 42);
+
+    // simulate the client switching networks: the connection must survive the new address
+    client_endpoint.rebind(std::net::UdpSocket::bind(loopback)?)?;
+
+    remote_test_actor.send_message(QuicTestMsg::Hello("after migration".to_string()))?;
+    let doubled = remote_test_actor
+        .ask(
+            |rpc| QuicTestMsg::Double(50, rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await?;
+    assert_eq!(doubled, 100);
+
+    let received = received.lock().unwrap();
+    assert_eq!(*received, vec!["before migration", "after migration"]);
+
+    Ok(())
+}
+
+/// polls until the condition is true, or fails after a few seconds
+async fn eventually(condition: impl Fn() -> bool) -> anyhow::Result<()> {
+    for _ in 0..100 {
+        if condition() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Err(anyhow::anyhow!("condition was not met in time"))
+}
+
+#[tokio::test]
+pub async fn test_quic_handshake_timeout() -> anyhow::Result<()> {
+    let (cert, key) = self_signed_certificate()?;
+    let loopback: SocketAddr = "127.0.0.1:0".parse()?;
+
+    let config = ServerConfig {
+        handshake_timeout: Duration::from_millis(200),
+        ..Default::default()
+    };
+    let server = quic::start_server_with_config(
+        nexus("quic handshake timeout: server").await?,
+        quic::server_endpoint(loopback, vec![cert.clone()], key)?,
+        config,
+    )
+    .await?;
+
+    // complete the QUIC handshake, but never open the streams
+    let client_endpoint = quic::client_endpoint(loopback, vec![cert])?;
+    let connection = client_endpoint
+        .connect(server.local_addr(), "localhost")?
+        .await?;
+    eventually(|| server.connection_count() == 1).await?;
+
+    // the server gives up on the connection and closes it
+    tokio::time::timeout(Duration::from_secs(5), connection.closed()).await?;
+    eventually(|| server.connection_count() == 0).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_quic_connection_limits() -> anyhow::Result<()> {
+    let (cert, key) = self_signed_certificate()?;
+    let loopback: SocketAddr = "127.0.0.1:0".parse()?;
+
+    let config = ServerConfig {
+        max_connections: Some(1),
+        ..Default::default()
+    };
+    let server = quic::start_server_with_config(
+        nexus("quic limits: server").await?,
+        quic::server_endpoint(loopback, vec![cert.clone()], key)?,
+        config,
+    )
+    .await?;
+
+    let client_nexus = nexus("quic limits: client").await?;
+    let client_endpoint = quic::client_endpoint(loopback, vec![cert])?;
+    let first = quic::connect_to_server(
+        client_nexus.clone(),
+        &client_endpoint,
+        server.local_addr(),
+        "localhost",
+    )
+    .await?;
+    first.wait_for_opened(Duration::from_secs(5)).await?;
+
+    // the second connection is refused
+    assert!(
+        quic::connect_to_server(
+            client_nexus,
+            &client_endpoint,
+            server.local_addr(),
+            "localhost"
+        )
+        .await
+        .is_err()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_quic_graceful_shutdown() -> anyhow::Result<()> {
+    let (cert, key) = self_signed_certificate()?;
+    let loopback: SocketAddr = "127.0.0.1:0".parse()?;
+
+    let server = quic::start_server(
+        nexus("quic shutdown: server").await?,
+        quic::server_endpoint(loopback, vec![cert.clone()], key)?,
+    )
+    .await?;
+
+    let client_nexus = nexus("quic shutdown: client").await?;
+    let client_endpoint = quic::client_endpoint(loopback, vec![cert])?;
+    let portal = quic::connect_to_server(
+        client_nexus.clone(),
+        &client_endpoint,
+        server.local_addr(),
+        "localhost",
+    )
+    .await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+    eventually(|| server.portals().len() == 1).await?;
+
+    let closed = tokio::spawn({
+        let portal = portal.clone();
+        async move { portal.wait_for_closed().await }
+    });
+    tokio::task::yield_now().await;
+
+    server.shutdown("maintenance", Duration::from_secs(5)).await;
+    assert_eq!(server.connection_count(), 0);
+
+    // the client side of the portal is closed, too, and learns why
+    let reason = tokio::time::timeout(Duration::from_secs(5), closed).await???;
+    assert_eq!(
+        reason,
+        Some(CloseReason::new(CloseCode::GoingAway, "maintenance"))
+    );
+
+    // and no new connections are accepted
+    assert!(
+        quic::connect_to_server(
+            client_nexus,
+            &client_endpoint,
+            server.local_addr(),
+            "localhost"
+        )
+        .await
+        .is_err()
+    );
+
+    Ok(())
+}
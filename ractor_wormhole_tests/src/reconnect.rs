@@ -0,0 +1,250 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use ractor::ActorRef;
+use ractor_wormhole::conduit::ConduitError;
+use ractor_wormhole::conduit::layer::{Conduit, ConduitLayer, ConduitLayers};
+use ractor_wormhole::conduit::reconnect::{ConnectionState, ReconnectConfig};
+use ractor_wormhole::conduit::websocket::{
+    client::tokio_tungstenite::{ClientConfig, connect_to_server_with_reconnect},
+    server::tokio_tungstenite::start_server,
+};
+use ractor_wormhole::portal::PortalActorMessage;
+use ractor_wormhole::util::FnActor;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::util::nexus;
+
+async fn state_listener() -> anyhow::Result<(
+    ActorRef<ConnectionState>,
+    UnboundedReceiver<ConnectionState>,
+)> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let (actor, _) = FnActor::<ConnectionState>::start_fn(async move |mut ctx| {
+        while let Some(state) = ctx.rx.recv().await {
+            let _ = tx.send(state);
+        }
+    })
+    .await?;
+    Ok((actor, rx))
+}
+
+/// skips states until one matches
+async fn wait_for_state(
+    rx: &mut UnboundedReceiver<ConnectionState>,
+    matches: impl Fn(&ConnectionState) -> bool,
+) -> anyhow::Result<ConnectionState> {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(state) = rx.recv().await {
+            if matches(&state) {
+                return Ok(state);
+            }
+        }
+        Err(anyhow::anyhow!("the state listener was closed"))
+    })
+    .await?
+}
+
+/// counts the conduits it wraps, and leaves them unchanged
+struct CountingLayer(Arc<AtomicU32>);
+
+impl ConduitLayer for CountingLayer {
+    fn wrap(&self, conduit: Conduit) -> BoxFuture<'static, Result<Conduit, ConduitError>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async { Ok(conduit) })
+    }
+}
+
+fn fast_config() -> ReconnectConfig {
+    ReconnectConfig {
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(200),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+pub async fn test_reconnect_after_server_restart() -> anyhow::Result<()> {
+    let server = start_server(nexus("reconnect: server 1").await?, "127.0.0.1:0".parse()?).await?;
+    let addr = server.local_addr();
+
+    let bootstrap_count = Arc::new(AtomicU32::new(0));
+    let bootstrap_count_copy = bootstrap_count.clone();
+    let layer_count = Arc::new(AtomicU32::new(0));
+    let layer_count_copy = layer_count.clone();
+    let (on_state, mut states) = state_listener().await?;
+    let client = connect_to_server_with_reconnect(
+        nexus("reconnect: client").await?,
+        format!("ws://{addr}"),
+        move || ClientConfig {
+            layers: ConduitLayers::new().with(CountingLayer(layer_count_copy.clone())),
+            ..Default::default()
+        },
+        move |_portal| {
+            let bootstrap_count = bootstrap_count_copy.clone();
+            async move {
+                bootstrap_count.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        },
+        fast_config(),
+        Some(on_state),
+    );
+
+    wait_for_state(&mut states, |s| matches!(s, ConnectionState::Open(_))).await?;
+    assert_eq!(bootstrap_count.load(Ordering::SeqCst), 1);
+    let first_portal = client.portal().expect("connected");
+
+    // restart the server on the same address
+    server.shutdown("restart", Duration::from_secs(5)).await;
+    wait_for_state(&mut states, |s| matches!(s, ConnectionState::Lost(_))).await?;
+    let _server = start_server(nexus("reconnect: server 2").await?, addr).await?;
+
+    wait_for_state(&mut states, |s| matches!(s, ConnectionState::Open(_))).await?;
+    assert_eq!(bootstrap_count.load(Ordering::SeqCst), 2);
+    // the client config is used for the new connection, too
+    assert_eq!(layer_count.load(Ordering::SeqCst), 2);
+    let second_portal = client.portal().expect("reconnected");
+    assert_ne!(first_portal.get_id(), second_portal.get_id());
+
+    client.stop();
+    wait_for_state(&mut states, |s| matches!(s, ConnectionState::GaveUp(_))).await?;
+    assert!(client.portal().is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_reconnect_gives_up() -> anyhow::Result<()> {
+    // find a port nobody listens on
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+
+    let (on_state, mut states) = state_listener().await?;
+    let config = ReconnectConfig {
+        max_attempts: Some(3),
+        ..fast_config()
+    };
+    let _client = connect_to_server_with_reconnect(
+        nexus("give up: client").await?,
+        format!("ws://{addr}"),
+        ClientConfig::default,
+        |_portal| async { Ok(()) },
+        config,
+        Some(on_state),
+    );
+
+    let mut attempts = 0;
+    loop {
+        match wait_for_state(&mut states, |_| true).await? {
+            ConnectionState::Connecting { attempt } => attempts = attempt,
+            ConnectionState::Open(_) => panic!("there is no server"),
+            ConnectionState::Lost(_) => {}
+            ConnectionState::GaveUp(_) => break,
+        }
+    }
+    assert_eq!(attempts, 3);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_backoff_delay() {
+    let config = ReconnectConfig {
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(10),
+        multiplier: 2.0,
+        jitter: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(config.delay(0), Duration::from_secs(1));
+    assert_eq!(config.delay(1), Duration::from_secs(2));
+    assert_eq!(config.delay(3), Duration::from_secs(8));
+    assert_eq!(config.delay(10), Duration::from_secs(10));
+
+    let config = ReconnectConfig {
+        jitter: 0.5,
+        ..config
+    };
+    for _ in 0..100 {
+        let delay = config.delay(1);
+        assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(3));
+    }
+}
+
+#[tokio::test]
+pub async fn test_short_lived_connections_count_as_failures() -> anyhow::Result<()> {
+    let server = start_server(nexus("short lived: server").await?, "127.0.0.1:0".parse()?).await?;
+
+    let (on_state, mut states) = state_listener().await?;
+    let config = ReconnectConfig {
+        max_attempts: Some(3),
+        ..fast_config()
+    };
+    // every connection is dropped right after it was opened
+    let _client = connect_to_server_with_reconnect(
+        nexus("short lived: client").await?,
+        format!("ws://{}", server.local_addr()),
+        ClientConfig::default,
+        |portal| async move {
+            portal.send_message(PortalActorMessage::Disconnect(None))?;
+            Ok(())
+        },
+        config,
+        Some(on_state),
+    );
+
+    let mut opened = 0;
+    loop {
+        match wait_for_state(&mut states, |_| true).await? {
+            ConnectionState::Open(_) => opened += 1,
+            ConnectionState::GaveUp(_) => break,
+            ConnectionState::Connecting { .. } | ConnectionState::Lost(_) => {}
+        }
+    }
+    assert_eq!(opened, 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_stop_during_bootstrap() -> anyhow::Result<()> {
+    let server = start_server(nexus("stop: server").await?, "127.0.0.1:0".parse()?).await?;
+
+    let (bootstrapping_tx, mut bootstrapping) = tokio::sync::mpsc::unbounded_channel();
+    let (on_state, mut states) = state_listener().await?;
+    let client = connect_to_server_with_reconnect(
+        nexus("stop: client").await?,
+        format!("ws://{}", server.local_addr()),
+        ClientConfig::default,
+        move |_portal| {
+            let _ = bootstrapping_tx.send(());
+            // a bootstrap which never completes
+            futures::future::pending()
+        },
+        fast_config(),
+        Some(on_state),
+    );
+
+    tokio::time::timeout(Duration::from_secs(10), bootstrapping.recv()).await?;
+    client.stop();
+    match wait_for_state(&mut states, |s| {
+        !matches!(s, ConnectionState::Connecting { .. })
+    })
+    .await?
+    {
+        ConnectionState::GaveUp(reason) => assert_eq!(reason, "Stopped"),
+        state => panic!("expected GaveUp, got {state:?}"),
+    }
+
+    // the portal of the abandoned attempt is closed
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while server.connection_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+
+    Ok(())
+}
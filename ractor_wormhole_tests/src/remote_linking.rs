@@ -1,9 +1,10 @@
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use ractor::ActorStatus;
+use ractor::{ActorRef, ActorStatus};
 use ractor_wormhole::conduit::{ConduitMessage, ConduitSink, ConduitSource};
-use ractor_wormhole::portal::{self, Portal};
+use ractor_wormhole::portal::{self, CrossPortalMessage, Portal};
+use ractor_wormhole::transmaterialization::internal_serializations::SimpleByteTransmaterializable;
 use ractor_wormhole::util::{ActorRef_Ask, FnActor};
 
 use futures::channel::mpsc;
@@ -105,3 +106,122 @@ pub async fn test_remote_linking_works() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// forwards the frames, but holds back bulk frames until an exit notification has passed them,
+/// like QUIC can when the bulk stream is slower than the control stream.
+/// Signals each held bulk frame on `held`.
+fn reordering_relay(
+    mut from: mpsc::Receiver<ConduitMessage>,
+    mut to: mpsc::Sender<ConduitMessage>,
+    held: mpsc::UnboundedSender<()>,
+) {
+    tokio::spawn(async move {
+        let mut bulk = Vec::new();
+        while let Some(msg) = from.next().await {
+            match msg {
+                ConduitMessage::Bulk(data) => {
+                    bulk.push(ConduitMessage::Bulk(data));
+                    let _ = held.unbounded_send(());
+                }
+                ConduitMessage::Binary(data) => {
+                    let exited = matches!(
+                        CrossPortalMessage::rematerialize(&data),
+                        Ok(CrossPortalMessage::ActorExited(_))
+                    );
+                    if to.send(ConduitMessage::Binary(data)).await.is_err() {
+                        break;
+                    }
+                    if exited {
+                        for msg in bulk.drain(..) {
+                            let _ = to.send(msg).await;
+                        }
+                    }
+                }
+                msg => {
+                    if to.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[tokio::test]
+pub async fn test_exit_overtaking_a_message() -> anyhow::Result<()> {
+    let (tx1, rx1) = mpsc::channel::<ConduitMessage>(100);
+    let (relayed_tx, relayed_rx) = mpsc::channel::<ConduitMessage>(100);
+    let (tx2, rx2) = mpsc::channel::<ConduitMessage>(100);
+    let (held_tx, mut held_rx) = mpsc::unbounded();
+    reordering_relay(rx1, relayed_tx, held_tx);
+
+    let sink1: ConduitSink = Box::pin(tx1.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let source1: ConduitSource = Box::pin(relayed_rx.map(Ok));
+    let sink2: ConduitSink = Box::pin(tx2.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let source2: ConduitSource = Box::pin(rx2.map(Ok));
+
+    let nexus_1 =
+        ractor_wormhole::nexus::start_nexus(Some("remote-linking: reordered 1".into()), None)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+    let nexus_2 =
+        ractor_wormhole::nexus::start_nexus(Some("remote-linking: reordered 2".into()), None)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+    let portal1 = ractor_wormhole::conduit::from_sink_source(
+        nexus_1,
+        "reordered 1".to_string(),
+        sink1,
+        source2,
+    )
+    .await?;
+    let portal2 = ractor_wormhole::conduit::from_sink_source(
+        nexus_2,
+        "reordered 2".to_string(),
+        sink2,
+        source1,
+    )
+    .await?;
+
+    // side "2" receives actor refs
+    let (refs_tx, mut refs_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (inbox, _) = FnActor::<ActorRef<String>>::start_fn(async move |mut ctx| {
+        while let Some(actor) = ctx.rx.recv().await {
+            let _ = refs_tx.send(actor);
+        }
+    })
+    .await?;
+    portal2
+        .publish_named_actor("inbox".to_string(), inbox)
+        .await?;
+
+    portal1.wait_for_opened(Duration::from_secs(5)).await?;
+    let inbox_id = portal1
+        .ask(
+            |rpc| portal::PortalActorMessage::QueryNamedRemoteActor("inbox".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let inbox_proxy = portal1
+        .instantiate_proxy_for_remote_actor::<ActorRef<String>>(inbox_id)
+        .await?;
+
+    // side "1" sends a ref to an actor, which exits while the message is still held back
+    let (short_lived, _) =
+        FnActor::<String>::start_fn(async move |mut ctx| while ctx.rx.recv().await.is_some() {})
+            .await?;
+    inbox_proxy.send_message(short_lived.clone())?;
+    tokio::time::timeout(Duration::from_secs(5), held_rx.next()).await?;
+    short_lived
+        .stop_and_wait(None, Some(Duration::from_secs(5)))
+        .await?;
+
+    // the proxy for the exited actor is stopped, although the exit arrived before the message
+    let received = tokio::time::timeout(Duration::from_secs(5), refs_rx.recv())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("The inbox stopped"))?;
+    tokio::time::timeout(Duration::from_secs(5), received.get_cell().wait(None)).await??;
+    assert_eq!(received.get_status(), ActorStatus::Stopped);
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use nix::fcntl::{FcntlArg, OFlag, fcntl};
+use nix::sys::termios::{SetArg, cfmakeraw, tcgetattr, tcsetattr};
+use ractor_wormhole::conduit::serial;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+use std::os::fd::{AsRawFd, OwnedFd};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::nexus;
+
+/// one end of a pty pair, like a serial port
+struct PtyStream {
+    fd: AsyncFd<File>,
+}
+
+impl PtyStream {
+    fn new(fd: OwnedFd) -> anyhow::Result<Self> {
+        fcntl(fd.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+        Ok(Self {
+            fd: AsyncFd::new(File::from(fd))?,
+        })
+    }
+}
+
+impl AsyncRead for PtyStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.fd.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|file| file.get_ref().read(unfilled)) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for PtyStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.fd.poll_write_ready(cx))?;
+            match guard.try_io(|file| file.get_ref().write(buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// opens a pty pair in raw mode, so that the line discipline passes all bytes through unchanged
+fn open_pty() -> anyhow::Result<(OwnedFd, OwnedFd)> {
+    let pty = nix::pty::openpty(None, None)?;
+    let mut termios = tcgetattr(&pty.slave)?;
+    cfmakeraw(&mut termios);
+    tcsetattr(&pty.slave, SetArg::TCSANOW, &termios)?;
+    Ok((pty.master, pty.slave))
+}
+
+#[tokio::test]
+pub async fn test_serial_over_pty() -> anyhow::Result<()> {
+    let (master, slave) = open_pty()?;
+
+    // line noise in both directions, before the portals are opened
+    File::from(master.try_clone()?).write_all(&[0x13, 0x37, 0x00, 0xff, 0x42])?;
+    File::from(slave.try_clone()?).write_all(&[0x01, 0x02, 0x03])?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg.msg);
+        }
+    })
+    .await?;
+
+    let portal_a = serial::from_stream(
+        nexus("serial: a").await?,
+        "pty master".to_string(),
+        PtyStream::new(master)?,
+    )
+    .await?;
+    portal_a
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+
+    let portal_b = serial::from_stream(
+        nexus("serial: b").await?,
+        "pty slave".to_string(),
+        PtyStream::new(slave)?,
+    )
+    .await?;
+
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let hello_actor_id = portal_b
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    let hello_actor_proxy = portal_b
+        .instantiate_proxy_for_remote_actor(hello_actor_id)
+        .await?;
+    hello_actor_proxy.send_message(HelloMsg {
+        msg: "Hello over the wire".to_string(),
+    })?;
+
+    let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?;
+    assert_eq!(received.as_deref(), Some("Hello over the wire"));
+
+    // closing one side closes the other
+    portal_b.send_message(PortalActorMessage::Disconnect(None))?;
+    portal_a
+        .get_cell()
+        .wait(Some(Duration::from_secs(5)))
+        .await?;
+
+    Ok(())
+}
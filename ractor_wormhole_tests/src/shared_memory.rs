@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use ractor_wormhole::conduit::{CloseCode, CloseReason, shared_memory};
+use ractor_wormhole::nexus::start_nexus;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::util::FnActor;
+
+use crate::tiny_wormhole::HelloMsg;
+use crate::util::hello_proxy;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("wormhole-{name}-{}", std::process::id()))
+}
+
+#[tokio::test]
+pub async fn test_shared_memory_portals() -> anyhow::Result<()> {
+    let path = temp_path("shm");
+
+    let nexus_a = start_nexus(Some("shm: a".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let nexus_b = start_nexus(Some("shm: b".to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+
+    // a small ring, so the large messages wrap around and are streamed in pieces
+    let portal_a = shared_memory::create(nexus_a, &path, 16 * 1024).await?;
+    let portal_b = shared_memory::connect(nexus_b.clone(), &path).await?;
+    portal_a.wait_for_opened(Duration::from_secs(5)).await?;
+    portal_b.wait_for_opened(Duration::from_secs(5)).await?;
+
+    // only one process may connect
+    assert!(shared_memory::connect(nexus_b, &path).await.is_err());
+    std::fs::remove_file(&path)?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (hello_actor, _) = FnActor::<HelloMsg>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg.msg);
+        }
+    })
+    .await?;
+    portal_b
+        .publish_named_actor("hello".to_string(), hello_actor)
+        .await?;
+
+    let proxy = hello_proxy(&portal_a).await?;
+    let messages: Vec<String> = (0..50)
+        .map(|i| format!("{i}").repeat(if i % 10 == 0 { 20_000 } else { 1 }))
+        .collect();
+    for msg in &messages {
+        proxy.send_message(HelloMsg { msg: msg.clone() })?;
+    }
+    for msg in messages {
+        let received = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?;
+        assert_eq!(received, Some(msg));
+    }
+
+    // closing one side ends the other one, too
+    portal_a.cast(PortalActorMessage::Disconnect(Some(CloseReason::new(
+        CloseCode::Normal,
+        "bye",
+    ))))?;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while portal_b.get_status() != ractor::ActorStatus::Stopped {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+
+    Ok(())
+}
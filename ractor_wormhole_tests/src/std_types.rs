@@ -0,0 +1,268 @@
+//! The implementations for the standard library types, `uuid::Uuid` and `bytes::Bytes`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::num::{NonZeroI64, NonZeroU8, NonZeroUsize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ractor::ActorRef;
+use ractor_wormhole::WormholeTransmaterializable;
+use ractor_wormhole::transmaterialization::{ContextTransmaterializable, PlainTransmaterializable};
+use ractor_wormhole::util::FnActor;
+
+use crate::derive_roundtrip::{roundtrip, test_context};
+
+#[cfg_attr(
+    feature = "ractor_cluster",
+    derive(ractor_cluster_derive::RactorMessage)
+)]
+#[derive(Debug, Clone, PartialEq, WormholeTransmaterializable)]
+#[wormhole(plain)]
+pub struct StdTypes {
+    pub hash_map: HashMap<String, Vec<u32>>,
+    pub btree_map: BTreeMap<u8, Option<String>>,
+    pub hash_set: HashSet<i64>,
+    pub btree_set: BTreeSet<String>,
+    pub queue: VecDeque<(u8, bool)>,
+    pub array: [u16; 4],
+    pub digest: [u8; 4],
+    pub byte_queue: VecDeque<u8>,
+    pub boxed: Box<Option<u32>>,
+    pub shared: Arc<String>,
+    pub text: Box<str>,
+    pub letters: Vec<char>,
+    pub timeout: Duration,
+    pub times: Vec<SystemTime>,
+    pub id: uuid::Uuid,
+    pub payload: bytes::Bytes,
+    pub non_zero: (NonZeroU8, NonZeroUsize, NonZeroI64),
+}
+
+fn std_types() -> StdTypes {
+    StdTypes {
+        hash_map: HashMap::from([("a".to_string(), vec![1, 2]), ("b".to_string(), vec![])]),
+        btree_map: BTreeMap::from([(1, Some("one".to_string())), (2, None)]),
+        hash_set: HashSet::from([-1, 0, i64::MAX]),
+        btree_set: BTreeSet::from(["x".to_string(), "y".to_string()]),
+        queue: VecDeque::from([(1, true), (2, false)]),
+        array: [1, 2, 3, u16::MAX],
+        digest: [0xde, 0xad, 0xbe, 0xef],
+        byte_queue: VecDeque::from([0, 1, u8::MAX]),
+        boxed: Box::new(Some(7)),
+        shared: Arc::new("shared".to_string()),
+        text: "boxed str".into(),
+        letters: vec!['a', 'ä', '🦀'],
+        timeout: Duration::new(3, 999_999_999),
+        times: vec![
+            SystemTime::now(),
+            UNIX_EPOCH,
+            UNIX_EPOCH - Duration::new(10, 250),
+        ],
+        id: uuid::Uuid::new_v4(),
+        payload: bytes::Bytes::from_static(b"\x00\x01payload"),
+        non_zero: (
+            NonZeroU8::new(8).unwrap(),
+            NonZeroUsize::new(usize::MAX).unwrap(),
+            NonZeroI64::new(-1).unwrap(),
+        ),
+    }
+}
+
+#[tokio::test]
+pub async fn test_std_types_roundtrip() -> anyhow::Result<()> {
+    let value = std_types();
+    assert!(StdTypes::plain().is_some());
+
+    let bytes = value.clone().immaterialize_plain()?;
+    assert_eq!(StdTypes::rematerialize_plain(&bytes)?, value);
+
+    let received = roundtrip("std types", vec![value.clone()]).await?;
+    assert_eq!(received, vec![value]);
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_sixteen_tuple() -> anyhow::Result<()> {
+    let ctx = test_context("std types: tuple").await?;
+
+    // std doesn't implement `PartialEq` and `Debug` for tuples of more than 12 elements
+    let tuple = (
+        1u8,
+        2u16,
+        3u32,
+        4u64,
+        -5i8,
+        -6i16,
+        -7i32,
+        -8i64,
+        true,
+        'z',
+        "sixteen".to_string(),
+        (),
+        0.5f32,
+        Some(-0.25f64),
+        u128::MAX,
+        vec![i128::MIN],
+    );
+    let bytes = tuple.clone().immaterialize_plain()?;
+    assert_eq!(bytes, tuple.immaterialize(&ctx).await?);
+
+    let received = <(
+        u8,
+        u16,
+        u32,
+        u64,
+        i8,
+        i16,
+        i32,
+        i64,
+        bool,
+        char,
+        String,
+        (),
+        f32,
+        Option<f64>,
+        u128,
+        Vec<i128>,
+    )>::rematerialize(&ctx, &bytes)
+    .await?;
+    assert_eq!((received.0, received.7, received.9), (1, -8, 'z'));
+    assert_eq!(received.10, "sixteen");
+    assert_eq!((received.13, received.14), (Some(-0.25), u128::MAX));
+    assert_eq!(received.15, vec![i128::MIN]);
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_collections_are_sent_like_vecs() -> anyhow::Result<()> {
+    let ctx = test_context("std types: collections").await?;
+
+    let map = HashMap::from([(1u32, "one".to_string())]);
+    let bytes = map.clone().immaterialize(&ctx).await?;
+    assert_eq!(
+        bytes,
+        vec![(1u32, "one".to_string())].immaterialize_plain()?
+    );
+    assert_eq!(
+        BTreeMap::<u32, String>::rematerialize(&ctx, &bytes).await?,
+        BTreeMap::from([(1, "one".to_string())])
+    );
+
+    // (byte collections are sent as a blob, see below)
+    let vec = vec![3u16, 1, 2];
+    let bytes = vec.clone().immaterialize_plain()?;
+    assert_eq!(
+        BTreeSet::<u16>::rematerialize_plain(&bytes)?,
+        BTreeSet::from([1, 2, 3])
+    );
+    assert_eq!(<[u16; 3]>::rematerialize_plain(&bytes)?, [3, 1, 2]);
+    assert!(<[u16; 2]>::rematerialize_plain(&bytes).is_err());
+    assert_eq!(
+        VecDeque::<u16>::rematerialize(&ctx, &bytes).await?,
+        VecDeque::from(vec)
+    );
+
+    // the element count must match
+    let mut wrong_count = bytes.clone();
+    wrong_count[0] = 4;
+    assert!(VecDeque::<u16>::rematerialize_plain(&wrong_count).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_byte_collections_are_sent_like_vec_u8() -> anyhow::Result<()> {
+    let ctx = test_context("std types: byte collections").await?;
+
+    let vec = vec![3u8, 1, 2];
+    let bytes = vec.clone().immaterialize(&ctx).await?;
+    assert_eq!(
+        bytes,
+        bytes::Bytes::from(vec.clone()).immaterialize_plain()?
+    );
+    assert_eq!([3u8, 1, 2].immaterialize(&ctx).await?, bytes);
+    assert_eq!(
+        VecDeque::from(vec.clone()).immaterialize(&ctx).await?,
+        bytes
+    );
+
+    assert_eq!(<[u8; 3]>::rematerialize(&ctx, &bytes).await?, [3, 1, 2]);
+    assert!(<[u8; 2]>::rematerialize_plain(&bytes).is_err());
+    assert!(<[u8; 4]>::rematerialize_plain(&bytes).is_err());
+    assert_eq!(
+        VecDeque::<u8>::rematerialize(&ctx, &bytes).await?,
+        VecDeque::from(vec.clone())
+    );
+
+    // a deque which wraps around is sent in order
+    let mut queue = VecDeque::from(vec![1u8, 2]);
+    queue.push_front(3);
+    assert_eq!(queue.immaterialize_plain()?, bytes);
+    Ok(())
+}
+
+#[test]
+pub fn test_invalid_values_are_an_error() -> anyhow::Result<()> {
+    assert!(char::rematerialize_plain(&0xd800u32.to_le_bytes()).is_err());
+    assert!(NonZeroU8::rematerialize_plain(&[0]).is_err());
+    assert!(uuid::Uuid::rematerialize_plain(&[0; 15]).is_err());
+
+    let mut duration = Duration::from_secs(1).immaterialize_plain()?;
+    duration[8..12].copy_from_slice(&1_000_000_000u32.to_le_bytes());
+    assert!(Duration::rematerialize_plain(&duration).is_err());
+
+    let mut time = UNIX_EPOCH.immaterialize_plain()?;
+    time[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(SystemTime::rematerialize_plain(&time).is_err());
+    Ok(())
+}
+
+// ActorRefs inside of maps
+// -----------------------------------------------------------------------------
+
+#[cfg_attr(
+    feature = "ractor_cluster",
+    derive(ractor_cluster_derive::RactorMessage)
+)]
+#[derive(Debug, WormholeTransmaterializable)]
+pub struct Directory {
+    pub by_name: HashMap<String, ActorRef<String>>,
+    pub by_shard: BTreeMap<u8, Vec<ActorRef<String>>>,
+}
+
+#[tokio::test]
+pub async fn test_actor_refs_in_maps() -> anyhow::Result<()> {
+    assert!(HashMap::<String, u32>::plain().is_some());
+    assert!(HashMap::<String, ActorRef<String>>::plain().is_none());
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let (inbox, _) = FnActor::<String>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg);
+        }
+    })
+    .await?;
+
+    let directory = Directory {
+        by_name: HashMap::from([("inbox".to_string(), inbox.clone())]),
+        by_shard: BTreeMap::from([(0, vec![inbox.clone(), inbox])]),
+    };
+    let mut received = roundtrip("std types: actor refs", vec![directory]).await?;
+    let directory = received.remove(0);
+
+    // the received refs are proxies which send back to the inbox
+    directory.by_name["inbox"].send_message("by name".to_string())?;
+    for actor in &directory.by_shard[&0] {
+        actor.send_message("by shard".to_string())?;
+    }
+
+    let mut messages = Vec::new();
+    for _ in 0..3 {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await? {
+            Some(msg) => messages.push(msg),
+            None => return Err(anyhow::anyhow!("The inbox stopped")),
+        }
+    }
+    messages.sort();
+    assert_eq!(messages, vec!["by name", "by shard", "by shard"]);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use ractor_wormhole::conduit::websocket::{
+    client::tokio_tungstenite::connect_to_server_tls,
+    server::tokio_tungstenite::start_server_tls,
+    tls::{
+        ClientTlsConfig, ServerTlsConfig,
+        rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    },
+};
+use ractor_wormhole::nexus::{OnActorConnectedMessage, start_nexus};
+use ractor_wormhole::portal::Portal;
+use ractor_wormhole::util::FnActor;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa, KeyPair};
+
+struct TestPki {
+    ca: Certificate,
+    ca_key: KeyPair,
+}
+
+impl TestPki {
+    fn new() -> anyhow::Result<Self> {
+        let mut params = CertificateParams::new(vec![])?;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate()?;
+        let ca = params.self_signed(&ca_key)?;
+        Ok(Self { ca, ca_key })
+    }
+
+    fn ca_certificate(&self) -> CertificateDer<'static> {
+        self.ca.der().clone()
+    }
+
+    fn issue(
+        &self,
+        name: &str,
+    ) -> anyhow::Result<(CertificateDer<'static>, PrivateKeyDer<'static>)> {
+        let key = KeyPair::generate()?;
+        let cert = CertificateParams::new(vec![name.to_string()])?.signed_by(
+            &key,
+            &self.ca,
+            &self.ca_key,
+        )?;
+        let key = PrivatePkcs8KeyDer::from(key.serialize_der()).into();
+        Ok((cert.der().clone(), key))
+    }
+}
+
+#[tokio::test]
+pub async fn test_websocket_mutual_tls() -> anyhow::Result<()> {
+    let pki = TestPki::new()?;
+    let (server_cert, server_key) = pki.issue("localhost")?;
+    let (client_cert, client_key) = pki.issue("client")?;
+
+    // the server reports every new portal, together with the connection context
+    let (connected_tx, mut connected_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (on_connected, _) = FnActor::<OnActorConnectedMessage>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = connected_tx.send(msg.context);
+        }
+    })
+    .await?;
+
+    let server_nexus = start_nexus(Some("tls: server".into()), Some(on_connected))
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let server_tls = ServerTlsConfig::new(vec![server_cert], server_key)
+        .with_client_authentication(vec![pki.ca_certificate()]);
+    let server = start_server_tls(server_nexus, "127.0.0.1:0".parse()?, server_tls).await?;
+
+    let url = format!("wss://localhost:{}", server.local_addr().port());
+
+    // a client with a valid certificate is accepted, and the server sees its certificate
+    let client_nexus = start_nexus(Some("tls: client".into()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let client_tls = ClientTlsConfig::with_root_certificates(vec![pki.ca_certificate()])
+        .with_client_certificate(vec![client_cert.clone()], client_key);
+    let portal = connect_to_server_tls(client_nexus.clone(), url.as_str(), client_tls).await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+
+    let context = tokio::time::timeout(Duration::from_secs(5), connected_rx.recv())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("the server didn't report the connection"))?;
+    assert_eq!(context.peer_certificates, vec![client_cert.to_vec()]);
+    assert!(context.remote_addr.is_some());
+
+    // a client without a certificate is rejected
+    let client_tls = ClientTlsConfig::with_root_certificates(vec![pki.ca_certificate()]);
+    let result = connect_to_server_tls(client_nexus, url.as_str(), client_tls).await;
+    assert!(result.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Helpers which are shared by the tests.
+
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+use ractor::ActorRef;
+use ractor_wormhole::conduit::{ConduitMessage, ConduitSink, ConduitSource};
+use ractor_wormhole::nexus::{NexusActorMessage, OnActorConnectedMessage, start_nexus};
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+use ractor_wormhole::util::{ActorRef_Ask, FnActor};
+
+use crate::tiny_wormhole::HelloMsg;
+
+/// one direction of an in-memory conduit: what is sent into the sink comes out of the source.
+pub fn channel() -> (ConduitSink, ConduitSource) {
+    let (tx, rx) = mpsc::channel::<ConduitMessage>(100);
+    let sink: ConduitSink = Box::pin(tx.sink_map_err(|e| anyhow::Error::msg(e.to_string())));
+    let source: ConduitSource = Box::pin(rx.map(Ok));
+    (sink, source)
+}
+
+pub async fn nexus(name: &str) -> anyhow::Result<ActorRef<NexusActorMessage>> {
+    start_nexus(Some(name.to_string()), None)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))
+}
+
+/// starts a nexus which reports every new portal
+pub async fn reporting_nexus(
+    name: &str,
+) -> anyhow::Result<(
+    ActorRef<NexusActorMessage>,
+    tokio::sync::mpsc::UnboundedReceiver<OnActorConnectedMessage>,
+)> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let (on_connected, _) = FnActor::<OnActorConnectedMessage>::start_fn(async move |mut ctx| {
+        while let Some(msg) = ctx.rx.recv().await {
+            let _ = tx.send(msg);
+        }
+    })
+    .await?;
+    let nexus = start_nexus(Some(name.to_string()), Some(on_connected))
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+    Ok((nexus, rx))
+}
+
+/// looks up the actor named "hello" on the remote side of the portal, and returns a proxy for it
+pub async fn hello_proxy(
+    portal: &ActorRef<PortalActorMessage>,
+) -> anyhow::Result<ActorRef<HelloMsg>> {
+    let hello_actor_id = portal
+        .ask(
+            |rpc| PortalActorMessage::QueryNamedRemoteActor("hello".to_string(), rpc),
+            Some(Duration::from_secs(5)),
+        )
+        .await??;
+    portal
+        .instantiate_proxy_for_remote_actor::<HelloMsg>(hello_actor_id)
+        .await
+}
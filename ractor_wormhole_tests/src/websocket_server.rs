@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use ractor::ActorRef;
+use ractor_wormhole::conduit::websocket::{
+    client::tokio_tungstenite::connect_to_server,
+    server::tokio_tungstenite::{ServerConfig, ServerHandle, start_server_with_config},
+};
+use ractor_wormhole::conduit::{CloseCode, CloseReason};
+use ractor_wormhole::nexus::NexusActorMessage;
+use ractor_wormhole::portal::{Portal, PortalActorMessage};
+
+use crate::util::nexus;
+
+async fn connect(
+    client_nexus: &ActorRef<NexusActorMessage>,
+    server: &ServerHandle,
+) -> anyhow::Result<ActorRef<PortalActorMessage>> {
+    let url = format!("ws://{}", server.local_addr());
+    let portal = connect_to_server(client_nexus.clone(), url).await?;
+    portal.wait_for_opened(Duration::from_secs(5)).await?;
+    Ok(portal)
+}
+
+/// polls until the condition is true, or fails after a few seconds
+async fn eventually(condition: impl Fn() -> bool) -> anyhow::Result<()> {
+    for _ in 0..100 {
+        if condition() {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Err(anyhow::anyhow!("condition was not met in time"))
+}
+
+#[tokio::test]
+pub async fn test_connection_limits() -> anyhow::Result<()> {
+    let config = ServerConfig {
+        max_connections_per_ip: Some(1),
+        ..Default::default()
+    };
+    let server = start_server_with_config(
+        nexus("limits: server").await?,
+        "127.0.0.1:0".parse()?,
+        config,
+    )
+    .await?;
+    let client_nexus = nexus("limits: client").await?;
+
+    let first = connect(&client_nexus, &server).await?;
+    assert_eq!(server.connection_count(), 1);
+
+    // the second connection from the same IP is refused
+    assert!(connect(&client_nexus, &server).await.is_err());
+
+    // once the first connection is closed, there is room again
+    first.send_message(PortalActorMessage::Disconnect(None))?;
+    eventually(|| server.connection_count() == 0).await?;
+    connect(&client_nexus, &server).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_handshake_timeout() -> anyhow::Result<()> {
+    let config = ServerConfig {
+        handshake_timeout: Duration::from_millis(200),
+        ..Default::default()
+    };
+    let server = start_server_with_config(
+        nexus("handshake timeout: server").await?,
+        "127.0.0.1:0".parse()?,
+        config,
+    )
+    .await?;
+
+    // connect, but never send the websocket handshake
+    let _stream = tokio::net::TcpStream::connect(server.local_addr()).await?;
+    eventually(|| server.connection_count() == 1).await?;
+    eventually(|| server.connection_count() == 0).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_max_message_size() -> anyhow::Result<()> {
+    // the introduction of the client alone is larger than that
+    let config = ServerConfig {
+        max_message_size: Some(16),
+        ..Default::default()
+    };
+    let server = start_server_with_config(
+        nexus("message size: server").await?,
+        "127.0.0.1:0".parse()?,
+        config,
+    )
+    .await?;
+    let client_nexus = nexus("message size: client").await?;
+
+    // the server drops the connection as soon as it receives the introduction of the client
+    let url = format!("ws://{}", server.local_addr());
+    let portal = connect_to_server(client_nexus, url).await?;
+    portal.get_cell().wait(Some(Duration::from_secs(5))).await?;
+    eventually(|| server.connection_count() == 0).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_graceful_shutdown() -> anyhow::Result<()> {
+    let server = start_server_with_config(
+        nexus("shutdown: server").await?,
+        "127.0.0.1:0".parse()?,
+        ServerConfig::default(),
+    )
+    .await?;
+    let client_nexus = nexus("shutdown: client").await?;
+
+    let portal = connect(&client_nexus, &server).await?;
+    assert_eq!(server.portals().len(), 1);
+
+    let closed = tokio::spawn({
+        let portal = portal.clone();
+        async move { portal.wait_for_closed().await }
+    });
+    tokio::task::yield_now().await;
+
+    server.shutdown("maintenance", Duration::from_secs(5)).await;
+    assert_eq!(server.connection_count(), 0);
+
+    // the client side of the portal is closed, too, and learns why
+    let reason = tokio::time::timeout(Duration::from_secs(5), closed).await???;
+    assert_eq!(
+        reason,
+        Some(CloseReason::new(CloseCode::GoingAway, "maintenance"))
+    );
+    portal.get_cell().wait(Some(Duration::from_secs(5))).await?;
+
+    // and no new connections are accepted
+    assert!(connect(&client_nexus, &server).await.is_err());
+
+    Ok(())
+}